@@ -2,9 +2,14 @@ use std::path::PathBuf;
 use clap::{Parser, ValueEnum};
 use crate::analysis::chess_position::ChessPosition;
 use crate::analysis::database::rows::ConspiracyMergeFn;
-use crate::analysis::{match_orchestration, openings_dataset};
+use crate::analysis::{chess960_dataset, match_orchestration, openings_dataset};
 use crate::analysis::match_orchestration::{ConspiracySearchOptions, TranspositionOptions};
-use crate::analysis::mtd_h_utils::MtdHParams;
+use crate::analysis::mtd_h_utils::{filter_mtd_h_params, MtdHParams, read_mtd_h_params};
+use crate::analysis::parallel_mtd::DEFAULT_MTD_PROBES;
+use crate::core::score::Centipawns;
+use crate::core::search::aspiration::AspirationWindows;
+use crate::core::search::extensions::ExtensionOptions;
+use crate::core::search::pruning::{LateMoveReductions, NullMovePruning, PruningOptions};
 use crate::tests::{epd, win_at_chess};
 use crate::tests::win_at_chess::EPD_PATH;
 
@@ -13,6 +18,7 @@ use crate::tests::win_at_chess::EPD_PATH;
 pub enum ChessDataset {
     WinAtChess,
     LichessOpenings,
+    Chess960, // The start positions, which need `UCI_Chess960` in a GUI
 }
 
 impl ChessDataset {
@@ -34,6 +40,11 @@ impl ChessDataset {
                     .map(|x| x.uci_position())
                     .collect()
             },
+            ChessDataset::Chess960 => {
+                chess960_dataset::get_start_positions().into_iter()
+                    .map(|x| x.uci_position())
+                    .collect()
+            },
         }
     }
 }
@@ -88,6 +99,50 @@ pub struct Args {
     #[arg(long, default_value_t = 2)]
     minimum_transposition_depth: u32,
 
+    /// Enables null-move pruning, which verifies every cutoff with a reduced search.
+    #[arg(long, default_value_t = false)]
+    null_move_pruning: bool,
+
+    /// How many plies shallower the null move and its verification are searched.
+    #[arg(long, default_value_t = 2)]
+    null_move_reduction: u32,
+
+    /// Enables late move reductions, which search late quiet moves shallower unless they fail high.
+    #[arg(long, default_value_t = false)]
+    late_move_reductions: bool,
+
+    /// The reduction every late move gets, in hundredths of a ply.
+    #[arg(long, default_value_t = LateMoveReductions::DEFAULT_BASE)]
+    lmr_base: u32,
+
+    /// Divides ln(depth left) * ln(move index) into the extra reduction, in hundredths.
+    #[arg(long, default_value_t = LateMoveReductions::DEFAULT_DIVISOR)]
+    lmr_divisor: u32,
+
+    /// Extends moves that give check, in quarter plies.
+    #[arg(long, default_value_t = 0)]
+    check_extension: u32,
+
+    /// Extends captures on the square the previous move captured on, in quarter plies.
+    #[arg(long, default_value_t = 0)]
+    recapture_extension: u32,
+
+    /// Enables aspiration windows, which search every iteration of alpha-beta and PVS around the previous score first.
+    #[arg(long, default_value_t = false)]
+    aspiration_windows: bool,
+
+    /// The initial width of the aspiration window on either side of the previous score, in centipawns.
+    #[arg(long, default_value_t = AspirationWindows::DEFAULT_WIDTH)]
+    aspiration_width: u32,
+
+    /// What the side of the aspiration window the score fell outside of is multiplied by.
+    #[arg(long, default_value_t = AspirationWindows::DEFAULT_WIDENING)]
+    aspiration_widening: u32,
+
+    /// The number of test values parallel MTD searches at once, each on its own thread.
+    #[arg(long, default_value_t = DEFAULT_MTD_PROBES)]
+    pub mtd_probes: usize,
+
     /// The path for the mtd-h parameters.
     #[arg(long, default_value = "./python/analysis_output/optimal_params.csv")]
     pub mtd_h_params_path: String,
@@ -121,39 +176,35 @@ impl Args {
         }
     }
 
-    pub fn mtd_h_params(&self) -> Vec<MtdHParams> {
-        let mut reader = csv::ReaderBuilder::new()
-            .delimiter(b',')
-            .has_headers(true)
-            .from_path(&self.mtd_h_params_path);
-
-        if reader.is_err() {
-            return vec![];
+    pub fn pruning_options(&self) -> PruningOptions {
+        PruningOptions {
+            null_move: self.null_move_pruning.then_some(NullMovePruning {
+                reduction: self.null_move_reduction,
+            }),
+            late_move_reductions: self.late_move_reductions
+                .then(|| LateMoveReductions::new(self.lmr_base, self.lmr_divisor)),
         }
+    }
 
-        let mut reader = reader.unwrap();
-
-        let mut result: Vec<MtdHParams> = Vec::new();
-        for record in reader.deserialize() {
-            let record_result: Result<MtdHParams, _> = record;
-
-            if record_result.is_ok() {
-                result.push(record_result.ok().unwrap());
-            }
-
-            // println!("{:?}", record);
+    pub fn extension_options(&self) -> ExtensionOptions {
+        ExtensionOptions {
+            check: self.check_extension,
+            recapture: self.recapture_extension,
         }
-
-        result
     }
 
-    pub fn filtered_mtd_h_params(&self) -> Vec<MtdHParams> {
-        let result = self.mtd_h_params();
+    pub fn aspiration_options(&self) -> Option<AspirationWindows> {
+        self.aspiration_windows.then_some(AspirationWindows {
+            width: Centipawns::new(self.aspiration_width as i64),
+            widening: self.aspiration_widening,
+        })
+    }
 
-        let filtered_result = result.into_iter()
-            .filter(|x| x.target_depth.saturating_sub(x.training_depth) == self.mtd_h_training_distance)
-            .collect();
+    pub fn mtd_h_params(&self) -> Vec<MtdHParams> {
+        read_mtd_h_params(&self.mtd_h_params_path)
+    }
 
-        filtered_result
+    pub fn filtered_mtd_h_params(&self) -> Vec<MtdHParams> {
+        filter_mtd_h_params(self.mtd_h_params(), self.mtd_h_training_distance)
     }
 }
@@ -0,0 +1,83 @@
+// The 960 start positions of Chess960, numbered as Scharnagl did: number 518 is the standard start position.
+
+use crate::analysis::chess_position::ChessPosition;
+
+pub const START_POSITION_COUNT: u32 = 960;
+
+// The squares of the knights, out of the five still empty once the bishops and the queen are placed
+const KNIGHT_PLACEMENTS: [(usize, usize); 10] = [(0, 1), (0, 2), (0, 3), (0, 4), (1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4)];
+
+pub struct Chess960StartPosition {
+    pub number: u32,
+}
+
+impl Chess960StartPosition {
+    /// The white pieces from the a-file to the h-file, like `RNBQKBNR`
+    pub fn back_rank(&self) -> String {
+        let mut pieces: [Option<char>; 8] = [None; 8];
+        let mut number = self.number as usize;
+
+        pieces[(number % 4) * 2 + 1] = Some('B');
+        number /= 4;
+        pieces[(number % 4) * 2] = Some('B');
+        number /= 4;
+
+        let empty_files = |pieces: &[Option<char>; 8]| (0..8).filter(|x| pieces[*x].is_none()).collect::<Vec<usize>>();
+        pieces[empty_files(&pieces)[number % 6]] = Some('Q');
+        number /= 6;
+
+        let empty = empty_files(&pieces);
+        let (first_knight, second_knight) = KNIGHT_PLACEMENTS[number];
+        pieces[empty[first_knight]] = Some('N');
+        pieces[empty[second_knight]] = Some('N');
+
+        // The king always ends up between the rooks
+        for (file, piece) in empty_files(&pieces).into_iter().zip(['R', 'K', 'R']) {
+            pieces[file] = Some(piece);
+        }
+
+        pieces.into_iter().flatten().collect()
+    }
+
+    /// The castling rights in Shredder-FEN: the files of the rooks
+    fn castling_rights(&self) -> String {
+        let rook_files: String = self.back_rank().char_indices()
+            .filter(|(_, piece)| *piece == 'R')
+            .map(|(file, _)| (b'A' + file as u8) as char)
+            .collect();
+
+        format!("{}{}", rook_files, rook_files.to_ascii_lowercase())
+    }
+}
+
+impl ChessPosition for Chess960StartPosition {
+    fn uci_position(&self) -> (Option<String>, String) {
+        let back_rank = self.back_rank();
+
+        (
+            Some(format!("Chess960 {}", self.number)),
+            format!("fen {}/pppppppp/8/8/8/8/PPPPPPPP/{} w {} - 0 1", back_rank.to_ascii_lowercase(), back_rank, self.castling_rights()),
+        )
+    }
+}
+
+pub fn get_start_positions() -> Vec<Chess960StartPosition> {
+    (0..START_POSITION_COUNT)
+        .map(|number| Chess960StartPosition { number })
+        .collect()
+}
+
+#[test]
+fn check_start_positions() {
+    use std::collections::HashSet;
+
+    assert_eq!(Chess960StartPosition { number: 518 }.back_rank(), "RNBQKBNR");
+    assert_eq!(Chess960StartPosition { number: 0 }.back_rank(), "BBQNNRKR");
+    assert_eq!(
+        Chess960StartPosition { number: 0 }.uci_position().1,
+        "fen bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w FHfh - 0 1",
+    );
+
+    let back_ranks: HashSet<String> = get_start_positions().iter().map(|x| x.back_rank()).collect();
+    assert_eq!(back_ranks.len(), START_POSITION_COUNT as usize);
+}
@@ -25,7 +25,7 @@ pub async fn create_db_if_not_exists(url: &str) -> SqlitePool {
 }
 
 pub async fn create_tables_if_not_exists(db: &SqlitePool) {
-    // id, max_search_depth, algorithm_used, conspiracy_search_used, bucket_size, num_buckets, conspiracy_merge_fn, transposition_table_used, minimum_transposition_depth, timestamp
+    // id, max_search_depth, algorithm_used, conspiracy_search_used, bucket_size, num_buckets, conspiracy_merge_fn, transposition_table_used, minimum_transposition_depth, null_move_pruning_used, null_move_reduction, late_move_reductions_used, lmr_base, lmr_divisor, check_extension, recapture_extension, aspiration_windows_used, aspiration_width, aspiration_widening, mtd_probes, timestamp
     let result = sqlx::query(r"
         CREATE TABLE IF NOT EXISTS config (
             id INTEGER PRIMARY KEY NOT NULL,
@@ -37,12 +37,39 @@ pub async fn create_tables_if_not_exists(db: &SqlitePool) {
             conspiracy_merge_fn TEXT,
             transposition_table_used INTEGER NOT NULL,
             minimum_transposition_depth INTEGER,
+            null_move_pruning_used INTEGER NOT NULL DEFAULT 0,
+            null_move_reduction INTEGER,
+            late_move_reductions_used INTEGER NOT NULL DEFAULT 0,
+            lmr_base INTEGER,
+            lmr_divisor INTEGER,
+            check_extension INTEGER NOT NULL DEFAULT 0,
+            recapture_extension INTEGER NOT NULL DEFAULT 0,
+            aspiration_windows_used INTEGER NOT NULL DEFAULT 0,
+            aspiration_width INTEGER,
+            aspiration_widening INTEGER,
+            mtd_probes INTEGER,
             timestamp INTEGER
         );
     ").execute(db).await.unwrap();
 
     println!("Created config table result: {:?}", result);
 
+    // DBs from before the forward pruning only lack its columns
+    add_column_if_not_exists(db, CONFIG_TABLE, "null_move_pruning_used", "INTEGER NOT NULL DEFAULT 0").await;
+    add_column_if_not_exists(db, CONFIG_TABLE, "null_move_reduction", "INTEGER").await;
+    add_column_if_not_exists(db, CONFIG_TABLE, "late_move_reductions_used", "INTEGER NOT NULL DEFAULT 0").await;
+    add_column_if_not_exists(db, CONFIG_TABLE, "lmr_base", "INTEGER").await;
+    add_column_if_not_exists(db, CONFIG_TABLE, "lmr_divisor", "INTEGER").await;
+    // And from before the search extensions
+    add_column_if_not_exists(db, CONFIG_TABLE, "check_extension", "INTEGER NOT NULL DEFAULT 0").await;
+    add_column_if_not_exists(db, CONFIG_TABLE, "recapture_extension", "INTEGER NOT NULL DEFAULT 0").await;
+    // And from before the aspiration windows
+    add_column_if_not_exists(db, CONFIG_TABLE, "aspiration_windows_used", "INTEGER NOT NULL DEFAULT 0").await;
+    add_column_if_not_exists(db, CONFIG_TABLE, "aspiration_width", "INTEGER").await;
+    add_column_if_not_exists(db, CONFIG_TABLE, "aspiration_widening", "INTEGER").await;
+    // And from before parallel MTD
+    add_column_if_not_exists(db, CONFIG_TABLE, "mtd_probes", "INTEGER").await;
+
     // id, foreign key Run config, uci_position (e.g. `startpos moves b1c3`), opening_name (optional), timestamp,
     let result = sqlx::query(r"
         CREATE TABLE IF NOT EXISTS run (
@@ -77,7 +104,7 @@ pub async fn create_tables_if_not_exists(db: &SqlitePool) {
 
     println!("Created search_position table result: {:?}", result);
 
-    // id, foreign key Position search, test_value, time_taken, nodes_evaluated, eval_boundary_type, evaluation, conspiracy_counter (optional), timestamp
+    // id, foreign key Position search, test_value, time_taken, nodes_evaluated, eval_boundary_type, evaluation, conspiracy_counter (optional), window_alpha and window_beta (aspiration searches only), timestamp
     let result = sqlx::query(r"
         CREATE TABLE IF NOT EXISTS mt_search (
             id INTEGER PRIMARY KEY NOT NULL,
@@ -88,14 +115,31 @@ pub async fn create_tables_if_not_exists(db: &SqlitePool) {
             eval_bound TEXT NOT NULL,
             conspiracy_counter TEXT,
             search_num INTEGER,
+            window_alpha TEXT,
+            window_beta TEXT,
             timestamp INTEGER,
             FOREIGN KEY(position_search_id) REFERENCES position_search(id)
         );
     ").execute(db).await.unwrap();
 
     println!("Created mt_search table result: {:?}", result);
-}
-
-
 
+    // DBs from before the aspiration windows only lack the windows
+    add_column_if_not_exists(db, MT_SEARCH_TABLE, "window_alpha", "TEXT").await;
+    add_column_if_not_exists(db, MT_SEARCH_TABLE, "window_beta", "TEXT").await;
+}
 
+async fn add_column_if_not_exists(db: &SqlitePool, table_name: &str, column_name: &str, column_type: &str) {
+    let (column_count,): (i64,) = sqlx::query_as(&format!("SELECT COUNT(*) FROM pragma_table_info('{table_name}') WHERE name = ?;"))
+        .bind(column_name)
+        .fetch_one(db)
+        .await
+        .unwrap();
+
+    if column_count == 0 {
+        sqlx::query(&format!("ALTER TABLE {table_name} ADD COLUMN {column_name} {column_type};"))
+            .execute(db)
+            .await
+            .unwrap();
+    }
+}
@@ -17,6 +17,8 @@ pub enum SearchAlgorithm {
     MtdBi,
     MtdF,
     MtdH,
+    Pvs,
+    ParallelMtd,
 }
 
 impl Display for SearchAlgorithm {
@@ -26,6 +28,8 @@ impl Display for SearchAlgorithm {
             SearchAlgorithm::MtdBi => write!(f, "MtdBi"),
             SearchAlgorithm::MtdF => write!(f, "MtdF"),
             SearchAlgorithm::MtdH => write!(f, "MtdH"),
+            SearchAlgorithm::Pvs => write!(f, "Pvs"),
+            SearchAlgorithm::ParallelMtd => write!(f, "ParallelMtd"),
         }
     }
 }
@@ -61,6 +65,17 @@ pub struct ConfigRow {
     pub conspiracy_merge_fn: Option<ConspiracyMergeFn>,
     pub transposition_table_used: bool,
     pub minimum_transposition_depth: Option<u32>,
+    pub null_move_pruning_used: bool,
+    pub null_move_reduction: Option<u32>,
+    pub late_move_reductions_used: bool,
+    pub lmr_base: Option<u32>,
+    pub lmr_divisor: Option<u32>,
+    pub check_extension: u32, // In quarter plies
+    pub recapture_extension: u32, // In quarter plies
+    pub aspiration_windows_used: bool,
+    pub aspiration_width: Option<u32>, // In centipawns
+    pub aspiration_widening: Option<u32>,
+    pub mtd_probes: Option<u32>, // Only for parallel MTD
     pub timestamp: i64,
 }
 
@@ -76,8 +91,30 @@ impl ConfigRow {
                 conspiracy_merge_fn,
                 transposition_table_used,
                 minimum_transposition_depth,
+                null_move_pruning_used,
+                null_move_reduction,
+                late_move_reductions_used,
+                lmr_base,
+                lmr_divisor,
+                check_extension,
+                recapture_extension,
+                aspiration_windows_used,
+                aspiration_width,
+                aspiration_widening,
+                mtd_probes,
                 timestamp
             ) VALUES (
+                ?,
+                ?,
+                ?,
+                ?,
+                ?,
+                ?,
+                ?,
+                ?,
+                ?,
+                ?,
+                ?,
                 ?,
                 ?,
                 ?,
@@ -97,6 +134,17 @@ impl ConfigRow {
             .bind(self.conspiracy_merge_fn.map(|x| x.to_string()))
             .bind(self.transposition_table_used as u32)
             .bind(self.minimum_transposition_depth)
+            .bind(self.null_move_pruning_used as u32)
+            .bind(self.null_move_reduction)
+            .bind(self.late_move_reductions_used as u32)
+            .bind(self.lmr_base)
+            .bind(self.lmr_divisor)
+            .bind(self.check_extension)
+            .bind(self.recapture_extension)
+            .bind(self.aspiration_windows_used as u32)
+            .bind(self.aspiration_width)
+            .bind(self.aspiration_widening)
+            .bind(self.mtd_probes)
             .bind(self.timestamp)
             .execute(db)
             .await
@@ -229,6 +277,7 @@ pub struct MTSearchRow {
     pub eval_bound: EvalBound,
     pub conspiracy_counter: Option<ConspiracyCounter>,
     pub search_num: u32,
+    pub window: Option<(BoardEvaluation, BoardEvaluation)>, // (alpha, beta) of an aspiration search, MT searches test `test_value`
     pub timestamp: i64,
 }
 
@@ -243,6 +292,8 @@ impl MTSearchRow {
                 eval_bound,
                 conspiracy_counter,
                 search_num,
+                window_alpha,
+                window_beta,
                 timestamp
             ) VALUES (
                 ?,
@@ -252,6 +303,8 @@ impl MTSearchRow {
                 ?,
                 ?,
                 ?,
+                ?,
+                ?,
                 ?
             );
         ", table_name))
@@ -274,6 +327,8 @@ impl MTSearchRow {
                 }
             })
             .bind(self.search_num)
+            .bind(self.window.map(|(alpha, _)| alpha.to_string()))
+            .bind(self.window.map(|(_, beta)| beta.to_string()))
             .bind(self.timestamp)
             .execute(db)
             .await
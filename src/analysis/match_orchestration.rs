@@ -5,28 +5,28 @@ use clap::ValueEnum;
 use serde::{Serialize, Deserialize};
 use sqlx::SqlitePool;
 use tokio::io::split;
+use tokio::runtime::Runtime;
 use crate::analysis::database;
 use crate::analysis::database::{CONFIG_TABLE, MT_SEARCH_TABLE, POSITION_SEARCH_TABLE, RUN_TABLE};
-use crate::analysis::database::rows::{ConfigRow, ConspiracyMergeFn, MTSearchRow, PositionSearchRow, RunRow};
-use crate::analysis::mtd_h::mtd_h_iterative_deepening_search;
+use crate::analysis::database::rows::{ConfigRow, ConspiracyMergeFn, RunRow};
 use crate::analysis::mtd_h_utils::MtdHParams;
 use crate::core::evaluation::game_status;
 use crate::core::score::BoardEvaluation;
 use crate::core::search;
+use crate::core::search::aspiration::AspirationWindows;
 use crate::core::search::conspiracy_counter::ConspiracyCounter;
 use crate::core::search::conspiracy_search::merging::{merge_remove_overwritten, MergeFn};
-use crate::core::search::conspiracy_search::mtd_w_conspiracy;
-use crate::core::search::iterative_deepening::iterative_deepening_search;
-use crate::core::search::mtdbi::{determine_mtdbi_step, mtdbi_iterative_deepening_search};
-use crate::core::search::mtdf::{determine_mtdf_step, mtdf_iterative_deepening_search};
-use crate::core::search::search_result::debug_search_result::DebugSearchResult;
+use crate::core::search::extensions::ExtensionOptions;
+use crate::core::search::pruning::PruningOptions;
+use crate::core::search::searcher::algorithm_entry;
 use crate::core::search::SearchDepth;
-use crate::core::search::transpositions::{EvalBound, TranspositionTable};
+use crate::core::search::transpositions::EvalBound;
 use crate::core::search::transpositions::high_depth_transposition::HighDepthTranspositionTable;
 use crate::core::search::transpositions::no_transposition::NoTranspositionTable;
 use crate::input;
 use crate::input::protocol_interpreter::{CalculateOptions, Command};
 use crate::input::uci_interpreter::UciInterpreter;
+use crate::engine::{Engine, SearchRows};
 
 /// For simple automatic match playing with DB logging
 
@@ -114,7 +114,7 @@ impl TranspositionOptions {
 }
 
 // TODO: keep track of the search algorithms that support DB logging
-#[derive(Copy, Clone, Debug, ValueEnum)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
 pub enum SearchAlgorithm {
     MTDBiIterativeDeepeningConspiracy,
     MTDFIterativeDeepeningConspiracy,
@@ -122,29 +122,17 @@ pub enum SearchAlgorithm {
     MTDFIterativeDeepening,
     AlphaBetaIterativeDeepening,
     MTDHIterativeDeepening,
+    PVSIterativeDeepening,
+    ParallelMTDIterativeDeepening,
 }
 
 impl SearchAlgorithm {
     pub fn is_conspiracy_search(&self) -> bool {
-        match self {
-            SearchAlgorithm::MTDBiIterativeDeepeningConspiracy => true,
-            SearchAlgorithm::MTDFIterativeDeepeningConspiracy => true,
-            SearchAlgorithm::MTDBiIterativeDeepening => false,
-            SearchAlgorithm::MTDFIterativeDeepening => false,
-            SearchAlgorithm::AlphaBetaIterativeDeepening => false,
-            SearchAlgorithm::MTDHIterativeDeepening => true,
-        }
+        algorithm_entry(*self).is_conspiracy_search
     }
 
     pub fn to_db_search_algorithm(&self) -> database::rows::SearchAlgorithm {
-        match self {
-            SearchAlgorithm::MTDBiIterativeDeepeningConspiracy => database::rows::SearchAlgorithm::MtdBi,
-            SearchAlgorithm::MTDFIterativeDeepeningConspiracy => database::rows::SearchAlgorithm::MtdF,
-            SearchAlgorithm::MTDBiIterativeDeepening => database::rows::SearchAlgorithm::MtdBi,
-            SearchAlgorithm::MTDFIterativeDeepening => database::rows::SearchAlgorithm::MtdF,
-            SearchAlgorithm::AlphaBetaIterativeDeepening => database::rows::SearchAlgorithm::AlphaBeta,
-            SearchAlgorithm::MTDHIterativeDeepening => database::rows::SearchAlgorithm::MtdH,
-        }
+        algorithm_entry(*self).db_algorithm
     }
 }
 
@@ -155,24 +143,22 @@ pub fn play_position(
     opening_name: Option<&str>,
     conspiracy_options: ConspiracySearchOptions,
     transposition_options: TranspositionOptions,
+    pruning_options: PruningOptions,
+    extension_options: ExtensionOptions,
+    aspiration_options: Option<AspirationWindows>,
+    mtd_probes: usize,
     mtd_h_params: &[MtdHParams],
     db: &SqlitePool,
     config_id: i64,
 ) {
-    let mut transposition_table: Box<dyn TranspositionTable> = match transposition_options {
-        TranspositionOptions::NoTransposition => Box::new(NoTranspositionTable::default()),
-        TranspositionOptions::WithTransposition {
-            minimum_transposition_depth
-        } => Box::new(HighDepthTranspositionTable::new(SearchDepth::Depth(minimum_transposition_depth))),
-    };
+    // Analysis runs are never interrupted
+    let engine = build_engine(algorithm_used, conspiracy_options, transposition_options, pruning_options, extension_options, aspiration_options, mtd_probes, mtd_h_params);
 
-    let mut current_position = position.to_string();
-    let mut split = position.split_whitespace();
-    let mut board_to_play = UciInterpreter::determine_board(split.clone().into_iter());
-    let pre_move_board = UciInterpreter::determine_pre_move_board(split.clone().into_iter());
-    let mut visited_board_hashes = UciInterpreter::determine_visited_boards(&pre_move_board, split.clone().into_iter());
+    let current_position = position.to_string();
+    let (board_to_play, castling, visited_board_hashes) = UciInterpreter::determine_position(position.split_whitespace())
+        .expect("invalid position");
 
-    let mut current_move = 0;
+    let current_move = 0;
 
     let mut move_gen = MoveGen::new_legal(&board_to_play);
     // let mut status = game_status(&board_to_play, move_gen.len() > 0);
@@ -192,97 +178,10 @@ pub fn play_position(
         .block_on(run_row.insert(db, RUN_TABLE));
     let run_id = run_db_result.last_insert_rowid();
 
-    let default_search_logging_fn = |mut position_row: PositionSearchRow, mut mt_rows: Vec<MTSearchRow>| {
-        position_row.run_id = run_id;
-        position_row.uci_position = current_position.clone();
-        position_row.move_num = current_move;
-
-        let position_db_result = tokio_runtime
-            .block_on(position_row.insert(db, POSITION_SEARCH_TABLE));
-
-        let position_id = position_db_result.last_insert_rowid();
-
-        for mt_row in mt_rows.iter_mut() {
-            mt_row.position_search_id = position_id;
+    engine.set_chess960_position(board_to_play, castling, visited_board_hashes.clone());
+    let outcome = engine.go(CalculateOptions::depth(calculate_depth)).wait();
 
-            tokio_runtime.block_on(mt_row.insert(db, MT_SEARCH_TABLE));
-        }
-    };
-
-    match algorithm_used {
-        SearchAlgorithm::MTDBiIterativeDeepeningConspiracy => {
-            let (bucket_size, num_buckets, merge_fn) = unwrap_conspiracy_options(conspiracy_options);
-
-            let _: (DebugSearchResult, _, _, _) = mtd_w_conspiracy::mtd_iterative_deepening_search(
-                &board_to_play,
-                &mut transposition_table,
-                visited_board_hashes.clone(),
-                CalculateOptions::Depth(calculate_depth),
-                determine_mtdbi_step,
-                bucket_size,
-                num_buckets,
-                merge_fn,
-                default_search_logging_fn,
-            );
-        },
-        SearchAlgorithm::MTDFIterativeDeepeningConspiracy => {
-            let (bucket_size, num_buckets, merge_fn) = unwrap_conspiracy_options(conspiracy_options);
-
-            let _: (DebugSearchResult, _, _, _) = mtd_w_conspiracy::mtd_iterative_deepening_search(
-                &board_to_play,
-                &mut transposition_table,
-                visited_board_hashes.clone(),
-                CalculateOptions::Depth(calculate_depth),
-                determine_mtdf_step,
-                bucket_size,
-                num_buckets,
-                merge_fn,
-                default_search_logging_fn,
-            );
-        },
-        SearchAlgorithm::MTDBiIterativeDeepening => {
-            let _: (DebugSearchResult, _, _) = mtdbi_iterative_deepening_search(
-                &board_to_play,
-                &mut transposition_table,
-                visited_board_hashes.clone(),
-                CalculateOptions::Depth(calculate_depth),
-                default_search_logging_fn,
-            );
-        },
-        SearchAlgorithm::MTDFIterativeDeepening => {
-            let _: (DebugSearchResult, _, _) = mtdf_iterative_deepening_search(
-                &board_to_play,
-                &mut transposition_table,
-                visited_board_hashes.clone(),
-                CalculateOptions::Depth(calculate_depth),
-                default_search_logging_fn,
-            );
-        },
-        SearchAlgorithm::AlphaBetaIterativeDeepening => {
-            let _: (DebugSearchResult, _, _) = iterative_deepening_search(
-                &board_to_play,
-                &mut transposition_table,
-                visited_board_hashes.clone(),
-                CalculateOptions::Depth(calculate_depth),
-                default_search_logging_fn,
-            );
-        },
-        SearchAlgorithm::MTDHIterativeDeepening => {
-            let (bucket_size, num_buckets, merge_fn) = unwrap_conspiracy_options(conspiracy_options);
-
-            let _: (DebugSearchResult, _, _, _) = mtd_h_iterative_deepening_search(
-                &board_to_play,
-                &mut transposition_table,
-                visited_board_hashes.clone(),
-                CalculateOptions::Depth(calculate_depth),
-                bucket_size,
-                num_buckets,
-                merge_fn,
-                default_search_logging_fn,
-                &mtd_h_params,
-            );
-        }
-    }
+    insert_search_rows(outcome.search_rows, run_id, &current_position, current_move, db, &tokio_runtime);
 }
 
 // TODO: don't forget the cache for visited positions
@@ -293,27 +192,24 @@ pub fn play_match(
     opening_name: Option<&str>,
     conspiracy_options: ConspiracySearchOptions,
     transposition_options: TranspositionOptions,
+    pruning_options: PruningOptions,
+    extension_options: ExtensionOptions,
+    aspiration_options: Option<AspirationWindows>,
+    mtd_probes: usize,
     mtd_h_params: &[MtdHParams],
     db: &SqlitePool,
     config_id: i64,
 ) {
-    let mut transposition_table: Box<dyn TranspositionTable> = match transposition_options {
-        TranspositionOptions::NoTransposition => Box::new(NoTranspositionTable::default()),
-        TranspositionOptions::WithTransposition {
-            minimum_transposition_depth
-        } => Box::new(HighDepthTranspositionTable::new(SearchDepth::Depth(minimum_transposition_depth))),
-    };
+    // Analysis runs are never interrupted
+    let engine = build_engine(algorithm_used, conspiracy_options, transposition_options, pruning_options, extension_options, aspiration_options, mtd_probes, mtd_h_params);
 
     let mut current_position = position.to_string();
-    let mut split = position.split_whitespace();
-    let mut board_to_play = UciInterpreter::determine_board(split.clone().into_iter());
-    let pre_move_board = UciInterpreter::determine_pre_move_board(split.clone().into_iter());
-    let mut visited_board_hashes = UciInterpreter::determine_visited_boards(&pre_move_board, split.clone().into_iter());
+    let (mut board_to_play, mut castling, mut visited_board_hashes) = UciInterpreter::determine_position(position.split_whitespace())
+        .expect("invalid position");
 
     let mut current_move = 0;
 
-    let mut move_gen = MoveGen::new_legal(&board_to_play);
-    let mut status = game_status(&board_to_play, move_gen.len() > 0);
+    let mut status = game_status(&board_to_play, !castling.legal_moves(&board_to_play).is_empty());
 
     let match_start = SystemTime::now();
 
@@ -333,185 +229,13 @@ pub fn play_match(
     let mut fifty_move_rule_counter = 0;
 
     while status == BoardStatus::Ongoing {
-        let search_result;
-        match algorithm_used {
-            SearchAlgorithm::MTDBiIterativeDeepeningConspiracy => {
-                let (bucket_size, num_buckets, merge_fn) = unwrap_conspiracy_options(conspiracy_options);
-
-                let result: (DebugSearchResult, _, _, _) = mtd_w_conspiracy::mtd_iterative_deepening_search(
-                    &board_to_play,
-                    &mut transposition_table,
-                    visited_board_hashes.clone(),
-                    CalculateOptions::Depth(calculate_depth),
-                    determine_mtdbi_step,
-                    bucket_size,
-                    num_buckets,
-                    merge_fn,
-                    |mut position_row: PositionSearchRow, mut mt_rows: Vec<MTSearchRow>| {
-                        position_row.run_id = run_id;
-                        position_row.uci_position = current_position.clone();
-                        position_row.move_num = current_move;
-
-                        let position_db_result = tokio_runtime
-                            .block_on(position_row.insert(db, POSITION_SEARCH_TABLE));
-
-                        let position_id = position_db_result.last_insert_rowid();
-
-                        for mt_row in mt_rows.iter_mut() {
-                            mt_row.position_search_id = position_id;
-
-                            tokio_runtime.block_on(mt_row.insert(db, MT_SEARCH_TABLE));
-                        }
-                    }
-                );
-
-                search_result = result.0;
-            },
-            SearchAlgorithm::MTDFIterativeDeepeningConspiracy => {
-                let (bucket_size, num_buckets, merge_fn) = unwrap_conspiracy_options(conspiracy_options);
-
-                let result: (DebugSearchResult, _, _, _) = mtd_w_conspiracy::mtd_iterative_deepening_search(
-                    &board_to_play,
-                    &mut transposition_table,
-                    visited_board_hashes.clone(),
-                    CalculateOptions::Depth(calculate_depth),
-                    determine_mtdf_step,
-                    bucket_size,
-                    num_buckets,
-                    merge_fn,
-                    |mut position_row: PositionSearchRow, mut mt_rows: Vec<MTSearchRow>| {
-                        position_row.run_id = run_id;
-                        position_row.uci_position = current_position.clone();
-                        position_row.move_num = current_move;
-
-                        let position_db_result = tokio_runtime
-                            .block_on(position_row.insert(db, POSITION_SEARCH_TABLE));
-
-                        let position_id = position_db_result.last_insert_rowid();
-
-                        for mt_row in mt_rows.iter_mut() {
-                            mt_row.position_search_id = position_id;
-
-                            tokio_runtime.block_on(mt_row.insert(db, MT_SEARCH_TABLE));
-                        }
-                    }
-                );
-
-                search_result = result.0;
-            },
-            SearchAlgorithm::MTDBiIterativeDeepening => {
-                let result: (DebugSearchResult, _, _) = mtdbi_iterative_deepening_search(
-                    &board_to_play,
-                    &mut transposition_table,
-                    visited_board_hashes.clone(),
-                    CalculateOptions::Depth(calculate_depth),
-                    |mut position_row: PositionSearchRow, mut mt_rows: Vec<MTSearchRow>| {
-                        position_row.run_id = run_id;
-                        position_row.uci_position = current_position.clone();
-                        position_row.move_num = current_move;
-
-                        let position_db_result = tokio_runtime
-                            .block_on(position_row.insert(db, POSITION_SEARCH_TABLE));
-
-                        let position_id = position_db_result.last_insert_rowid();
-
-                        for mt_row in mt_rows.iter_mut() {
-                            mt_row.position_search_id = position_id;
-
-                            tokio_runtime.block_on(mt_row.insert(db, MT_SEARCH_TABLE));
-                        }
-                    }
-                );
-
-                search_result = result.0;
-            },
-            SearchAlgorithm::MTDFIterativeDeepening => {
-                let result: (DebugSearchResult, _, _) = mtdf_iterative_deepening_search(
-                    &board_to_play,
-                    &mut transposition_table,
-                    visited_board_hashes.clone(),
-                    CalculateOptions::Depth(calculate_depth),
-                    |mut position_row: PositionSearchRow, mut mt_rows: Vec<MTSearchRow>| {
-                        position_row.run_id = run_id;
-                        position_row.uci_position = current_position.clone();
-                        position_row.move_num = current_move;
-
-                        let position_db_result = tokio_runtime
-                            .block_on(position_row.insert(db, POSITION_SEARCH_TABLE));
-
-                        let position_id = position_db_result.last_insert_rowid();
-
-                        for mt_row in mt_rows.iter_mut() {
-                            mt_row.position_search_id = position_id;
-
-                            tokio_runtime.block_on(mt_row.insert(db, MT_SEARCH_TABLE));
-                        }
-                    }
-                );
-
-                search_result = result.0;
-            },
-            SearchAlgorithm::AlphaBetaIterativeDeepening => {
-                let result: (DebugSearchResult, _, _) = iterative_deepening_search(
-                    &board_to_play,
-                    &mut transposition_table,
-                    visited_board_hashes.clone(),
-                    CalculateOptions::Depth(calculate_depth),
-                    |mut position_row: PositionSearchRow, mut mt_rows: Vec<MTSearchRow>| {
-                        position_row.run_id = run_id;
-                        position_row.uci_position = current_position.clone();
-                        position_row.move_num = current_move;
-
-                        let position_db_result = tokio_runtime
-                            .block_on(position_row.insert(db, POSITION_SEARCH_TABLE));
-
-                        let position_id = position_db_result.last_insert_rowid();
-
-                        for mt_row in mt_rows.iter_mut() {
-                            mt_row.position_search_id = position_id;
-
-                            tokio_runtime.block_on(mt_row.insert(db, MT_SEARCH_TABLE));
-                        }
-                    }
-                );
-
-                search_result = result.0;
-            },
-            SearchAlgorithm::MTDHIterativeDeepening => {
-                let (bucket_size, num_buckets, merge_fn) = unwrap_conspiracy_options(conspiracy_options);
-
-                let result: (DebugSearchResult, _, _, _) = mtd_h_iterative_deepening_search(
-                    &board_to_play,
-                    &mut transposition_table,
-                    visited_board_hashes.clone(),
-                    CalculateOptions::Depth(calculate_depth),
-                    bucket_size,
-                    num_buckets,
-                    merge_fn,
-                    |mut position_row: PositionSearchRow, mut mt_rows: Vec<MTSearchRow>| {
-                        position_row.run_id = run_id;
-                        position_row.uci_position = current_position.clone();
-                        position_row.move_num = current_move;
-
-                        let position_db_result = tokio_runtime
-                            .block_on(position_row.insert(db, POSITION_SEARCH_TABLE));
-
-                        let position_id = position_db_result.last_insert_rowid();
-
-                        for mt_row in mt_rows.iter_mut() {
-                            mt_row.position_search_id = position_id;
-
-                            tokio_runtime.block_on(mt_row.insert(db, MT_SEARCH_TABLE));
-                        }
-                    },
-                    &mtd_h_params,
-                );
-
-                search_result = result.0;
-            }
-        }
+        engine.set_chess960_position(board_to_play, castling, visited_board_hashes.clone());
+        let outcome = engine.go(CalculateOptions::depth(calculate_depth)).wait();
+        insert_search_rows(outcome.search_rows, run_id, &current_position, current_move, db, &tokio_runtime);
+        let search_result = outcome.search_result;
 
-        board_to_play = board_to_play.make_move_new(search_result.best_move);
+        board_to_play = castling.make_move(&board_to_play, search_result.best_move);
+        castling = castling.after_move(&board_to_play);
 
         if breaks_50_move_rule(&board_to_play, search_result.best_move) {
             fifty_move_rule_counter = 0;
@@ -522,8 +246,7 @@ pub fn play_match(
         current_move += 1;
         current_position.push_str(&format!(" {}", search_result.best_move));
 
-        move_gen = MoveGen::new_legal(&board_to_play);
-        status = game_status(&board_to_play, move_gen.len() > 0);
+        status = game_status(&board_to_play, !castling.legal_moves(&board_to_play).is_empty());
 
         fifty_move_rule_counter += 1;
         if fifty_move_rule_counter > 50 {
@@ -554,25 +277,77 @@ pub fn breaks_50_move_rule(board: &Board, chess_move: ChessMove) -> bool {
 
     let is_pawn_move = source_piece.map(|x| x == Piece::Pawn).unwrap_or(false);
 
-    // no need to check en passant: is a pawn move
-    board.piece_on(chess_move.get_dest()).is_some() || is_pawn_move
+    // no need to check en passant: is a pawn move. Castling takes the own rook in Chess960.
+    board.color_on(chess_move.get_dest()) == Some(!board.side_to_move()) || is_pawn_move
+}
+
+/// An engine that keeps the DB rows of its searches, for `insert_search_rows`
+fn build_engine(
+    algorithm_used: SearchAlgorithm,
+    conspiracy_options: ConspiracySearchOptions,
+    transposition_options: TranspositionOptions,
+    pruning_options: PruningOptions,
+    extension_options: ExtensionOptions,
+    aspiration_options: Option<AspirationWindows>,
+    mtd_probes: usize,
+    mtd_h_params: &[MtdHParams],
+) -> Engine {
+    let mut builder = Engine::builder()
+        .algorithm(algorithm_used)
+        .transposition_table(move |_| match transposition_options {
+            TranspositionOptions::NoTransposition => Box::new(NoTranspositionTable::default()),
+            TranspositionOptions::WithTransposition {
+                minimum_transposition_depth
+            } => Box::new(HighDepthTranspositionTable::new(SearchDepth::Depth(minimum_transposition_depth))),
+        })
+        .pruning(pruning_options)
+        .extensions(extension_options)
+        .aspiration(aspiration_options)
+        .mtd_probes(mtd_probes)
+        .mtd_h_params(mtd_h_params.to_vec())
+        .record_search_rows();
+
+    if algorithm_used.is_conspiracy_search() {
+        let (bucket_size, num_buckets, merge_fn_name) = unwrap_conspiracy_options(conspiracy_options);
+        builder = builder.conspiracy(bucket_size, num_buckets, merge_fn_name);
+    }
+
+    builder.build()
+}
+
+fn insert_search_rows(
+    search_rows: Vec<SearchRows>,
+    run_id: i64,
+    current_position: &str,
+    current_move: u32,
+    db: &SqlitePool,
+    tokio_runtime: &Runtime,
+) {
+    for (mut position_row, mut mt_rows) in search_rows {
+        position_row.run_id = run_id;
+        position_row.uci_position = current_position.to_string();
+        position_row.move_num = current_move;
+
+        let position_db_result = tokio_runtime
+            .block_on(position_row.insert(db, POSITION_SEARCH_TABLE));
+
+        let position_id = position_db_result.last_insert_rowid();
+
+        for mt_row in mt_rows.iter_mut() {
+            mt_row.position_search_id = position_id;
+
+            tokio_runtime.block_on(mt_row.insert(db, MT_SEARCH_TABLE));
+        }
+    }
 }
 
-fn unwrap_conspiracy_options(options: ConspiracySearchOptions) -> (u32, usize, MergeFn) {
+fn unwrap_conspiracy_options(options: ConspiracySearchOptions) -> (u32, usize, ConspiracyMergeFn) {
     match options{
         ConspiracySearchOptions::NoConspiracySearch => panic!("No conspiracy options set for conspiracy search"),
         ConspiracySearchOptions::WithConspiracySearch {
             bucket_size,
             num_buckets,
             merge_fn_name,
-        } => {
-            (
-                bucket_size,
-                num_buckets,
-                match merge_fn_name {
-                    ConspiracyMergeFn::MergeRemoveOverwritten => merge_remove_overwritten,
-                },
-            )
-        }
+        } => (bucket_size, num_buckets, merge_fn_name),
     }
 }
@@ -1,7 +1,9 @@
 pub mod openings_dataset;
+pub mod chess960_dataset;
 pub mod database;
 pub mod match_orchestration;
 pub mod args;
 pub mod chess_position;
 pub mod mtd_h_utils;
 pub mod mtd_h;
+pub mod parallel_mtd;
@@ -2,18 +2,22 @@ use std::cmp::{max, min};
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use chess::{Board, ChessMove, Color};
 use crate::analysis::database::rows::{MTSearchRow, PositionSearchRow};
-use crate::analysis::mtd_h_utils::{MtdHParams, select_test_point, select_test_point_w_mate, update_probability_distribution};
-use crate::core::evaluation::single_evaluation;
+use crate::analysis::mtd_h_utils::{MtdHParams, MAX_ROUNDS, select_test_point, select_test_point_w_mate, update_probability_distribution};
 use crate::core::score::{BoardEvaluation, Centipawns};
 use crate::core::search::conspiracy_counter::ConspiracyCounter;
 use crate::core::search::conspiracy_search::log_info_search_results;
 use crate::core::search::conspiracy_search::merging::MergeFn;
 use crate::core::search::conspiracy_search::mt_w_conspiracy::search_mt_w_conspiracy;
 use crate::core::search::conspiracy_search::mtd_w_conspiracy::mtd_search;
-use crate::core::search::iterative_deepening::is_still_searching;
+use crate::core::search::extensions::ExtensionState;
+use crate::core::search::iterative_deepening::{determine_critical_path_string, first_depth, is_still_searching, selective_depth};
 use crate::core::search::mtdbi::determine_mtdbi_step;
-use crate::core::search::search_result::SearchResult;
+use crate::core::search::multi_pv::search_multi_pv;
+use crate::core::search::position_history::PositionHistory;
+use crate::core::search::search_control::SearchControl;
+use crate::core::search::search_result::{with_selective_depth, SearchResult};
 use crate::core::search::transpositions::{EvalBound, TranspositionTable};
+use crate::debug_log;
 use crate::input::protocol_interpreter::CalculateOptions;
 
 
@@ -28,41 +32,45 @@ pub fn mtd_h_iterative_deepening_search<T: SearchResult + Default + Clone, L>(
     conspiracy_merge_fn: MergeFn,
     search_logging: L,
     probability_distribution_params: &[MtdHParams],
+    control: &SearchControl,
 ) -> (T, ConspiracyCounter, u32, u32) where
     L: Fn(PositionSearchRow, Vec<MTSearchRow>) { // (SearchResult, ConspiracyCounter, depth, selective_depth)
     let now = Instant::now();
 
     let mut conspiracy_counters = vec![];
 
-    let mut current_depth = 2;
+    let start_depth = first_depth(&options, control);
+    let mut current_depth = start_depth + 1;
     let first_result: (T, ConspiracyCounter, Vec<MTSearchRow>, PositionSearchRow) = mtd_search(
         // let mut search_result: T = mtd_search(
         board,
         transposition_table,
         visited_boards.clone(),
-        1,
+        start_depth,
         BoardEvaluation::PieceScore(Centipawns::new(0)),
         determine_mtdbi_step,
         bucket_size,
         num_buckets,
         conspiracy_merge_fn,
+        control,
     );
     let mut search_result = first_result.0;
     let mut conspiracy_counter = first_result.1;
     conspiracy_counters.push(conspiracy_counter.clone());
     search_logging(first_result.3, first_result.2);
 
-    while is_still_searching(options, board, now, current_depth) {
+    while is_still_searching(&options, board, control, current_depth, &search_result) {
         let applicable_probability_params = MtdHParams::find_applicable_param(probability_distribution_params, current_depth);
+        // The counters are kept from the first depth on, which is deeper for helper threads
         let applicable_conspiracy_counter = applicable_probability_params.map(|x| {
-            conspiracy_counters.get(x.training_depth.saturating_sub(1) as usize)
+            conspiracy_counters.get(x.training_depth.max(1).checked_sub(start_depth)? as usize)
         }).flatten();
 
         if applicable_probability_params.is_none() {
-            println!("no applicable param found for depth {}", current_depth);
+            debug_log!("no applicable param found for depth {}", current_depth);
         }
         if applicable_conspiracy_counter.is_none() {
-            println!("no applicable conspiracy_counter found for depth {}", current_depth);
+            debug_log!("no applicable conspiracy_counter found for depth {}", current_depth);
         }
 
 
@@ -81,6 +89,7 @@ pub fn mtd_h_iterative_deepening_search<T: SearchResult + Default + Clone, L>(
                 conspiracy_merge_fn,
                 applicable_probability_params.unwrap(),
                 applicable_conspiracy_counter.unwrap(),
+                control,
             );
         } else {
             temp_search_result = mtd_search(
@@ -93,8 +102,13 @@ pub fn mtd_h_iterative_deepening_search<T: SearchResult + Default + Clone, L>(
                 bucket_size,
                 num_buckets,
                 conspiracy_merge_fn,
+                control,
             );
         }
+        if control.is_stopped() {
+            // Keep the result of the last completed iteration
+            break;
+        }
         search_result = temp_search_result.0;
         conspiracy_counter = temp_search_result.1;
 
@@ -103,23 +117,41 @@ pub fn mtd_h_iterative_deepening_search<T: SearchResult + Default + Clone, L>(
         // OPTIONAL LOGGING TO DB
         search_logging(temp_search_result.3, temp_search_result.2);
 
+        // The other lines don't need the probability model to get their exact values
+        let lines = search_multi_pv(board, control, search_result.clone(), |line_control| {
+            mtd_search::<T>(
+                board,
+                transposition_table,
+                visited_boards.clone(),
+                current_depth,
+                search_result.eval_bound().board_evaluation(),
+                determine_mtdbi_step,
+                bucket_size,
+                num_buckets,
+                conspiracy_merge_fn,
+                line_control,
+            ).0
+        });
+
         let duration = now.elapsed();
         log_info_search_results(
-            &search_result,
-            board.side_to_move(),
+            &lines,
+            board,
             duration,
             current_depth,
-            current_depth, // TODO: Change to actual selective depth
+            selective_depth(&search_result, current_depth),
             &conspiracy_counter,
+            control,
         );
         current_depth += 1;
     }
 
+    let selective_depth = selective_depth(&search_result, current_depth);
     (
         search_result,
         conspiracy_counter,
         current_depth,
-        current_depth,
+        selective_depth,
     )
 }
 
@@ -137,6 +169,7 @@ pub fn mtd_h_search<T: SearchResult + Default + Clone>(
     conspiracy_merge_fn: MergeFn,
     probability_params: &MtdHParams,
     old_conspiracy_counter: &ConspiracyCounter,
+    control: &SearchControl,
 ) -> (T, ConspiracyCounter, Vec<MTSearchRow>, PositionSearchRow) {
     let mut probability_distribution = probability_params.generate_probability_distribution(
         old_conspiracy_counter,
@@ -151,7 +184,7 @@ pub fn mtd_h_search<T: SearchResult + Default + Clone>(
         start_point,
     );
 
-    let current_evaluation = single_evaluation(board, board.status());
+    let current_evaluation = control.evaluate(board, board.status());
 
     let simple_evaluation;
     match current_evaluation {
@@ -213,24 +246,48 @@ pub fn mtd_h_search<T: SearchResult + Default + Clone>(
     );
     let mut conspiracy_counter = None;
     let mut nodes_searched = 0;
+    let mut selective_depth = 0; // The deepest ply of all the MT searches
     // while lowerbound < upperbound {
+    let mut position_history = PositionHistory::new(&visited_boards, board).with_castling(control.castling());
     while !result.eval_bound().is_exact() {
         let time = SystemTime::now();
         let search_result = search_mt_w_conspiracy(
             board,
             transposition_table,
-            visited_boards.clone(),
+            &mut position_history,
             simple_evaluation,
             EvalBound::Exact(current_test_value),
             0,
             depth,
             bucket_size,
             num_buckets,
+            ExtensionState::default(),
+            true,
+            control,
         );
         result = search_result.0;
         let found_conspiracy_counter = search_result.1;
+        if control.is_stopped() {
+            return (
+                result,
+                found_conspiracy_counter,
+                mt_searches,
+                PositionSearchRow {
+                    run_id: 0,
+                    uci_position: "".to_string(),
+                    depth,
+                    time_taken: 0,
+                    nodes_evaluated: nodes_searched,
+                    evaluation: current_test_value,
+                    conspiracy_counter: None,
+                    move_num: 0,
+                    timestamp: 0,
+                },
+            );
+        }
 
         nodes_searched += result.nodes_searched().unwrap_or(1);
+        selective_depth = selective_depth.max(result.selective_depth().unwrap_or(depth));
 
         // Update the mt_searches log
         mt_searches.push(MTSearchRow {
@@ -241,6 +298,7 @@ pub fn mtd_h_search<T: SearchResult + Default + Clone>(
             eval_bound: result.eval_bound(),
             conspiracy_counter: Some(found_conspiracy_counter.clone()),
             search_num: mt_search_num,
+            window: None,
             timestamp: time.duration_since(UNIX_EPOCH).expect("time went backwards").as_secs() as i64,
         });
         mt_search_num += 1;
@@ -267,11 +325,14 @@ pub fn mtd_h_search<T: SearchResult + Default + Clone>(
                 };
 
                 return (
-                    T::make_search_result(
-                        result.best_move(),
-                        result.eval_bound(),
-                        Some(nodes_searched),
-                        result.critical_path(),
+                    with_selective_depth(
+                        T::make_search_result(
+                            result.best_move(),
+                            result.eval_bound(),
+                            Some(nodes_searched),
+                            result.critical_path(),
+                        ),
+                        selective_depth,
                     ),
                     conspiracy_counter.unwrap(),
                     mt_searches,
@@ -293,17 +354,14 @@ pub fn mtd_h_search<T: SearchResult + Default + Clone>(
 
             lowest_upperbound = min(upperbound, lowest_upperbound);
             highest_lowerbound = max(lowerbound, highest_lowerbound);
-            println!("UNSTABLE SEARCH: logging");
-            println!("lowerbound {:?}, upperbound {:?}", lowerbound, upperbound);
-            let path = result.critical_path().unwrap();
-            for chess_move in path.into_iter() {
-                print!("{} ", chess_move);
-            }
-            println!("");
-            // println!("result path: {}", result.critical_path());
-            println!("result best_move: {}", result.best_move());
-            println!("result nodes_searched: {:?}", result.nodes_searched());
-            println!("UNSTABLE SEARCH: end logging");
+            debug_log!(
+                "unstable search: lowerbound {:?}, upperbound {:?}, best_move {}, nodes {:?}, {}",
+                lowerbound,
+                upperbound,
+                result.best_move(),
+                result.nodes_searched(),
+                determine_critical_path_string(result.critical_path()),
+            );
 
             upperbound = newest_eval;
             lowerbound = newest_eval;
@@ -326,11 +384,14 @@ pub fn mtd_h_search<T: SearchResult + Default + Clone>(
                             timestamp: total_search_time.duration_since(UNIX_EPOCH).unwrap_or(Duration::from_secs(0)).as_secs() as i64,
                         };
                         return (
-                            T::make_search_result(
-                                result.best_move(),
-                                EvalBound::Exact(result.eval_bound().board_evaluation()),
-                                Some(nodes_searched),
-                                result.critical_path(),
+                            with_selective_depth(
+                                T::make_search_result(
+                                    result.best_move(),
+                                    result.eval_bound(),
+                                    Some(nodes_searched),
+                                    result.critical_path(),
+                                ),
+                                selective_depth,
                             ),
                             conspiracy_counter.unwrap(),
                             mt_searches,
@@ -350,11 +411,14 @@ pub fn mtd_h_search<T: SearchResult + Default + Clone>(
                             timestamp: total_search_time.duration_since(UNIX_EPOCH).unwrap_or(Duration::from_secs(0)).as_secs() as i64,
                         };
                         return (
-                            T::make_search_result(
-                                result.best_move(),
-                                EvalBound::Exact(result.eval_bound().board_evaluation()),
-                                Some(nodes_searched),
-                                result.critical_path(),
+                            with_selective_depth(
+                                T::make_search_result(
+                                    result.best_move(),
+                                    result.eval_bound(),
+                                    Some(nodes_searched),
+                                    result.critical_path(),
+                                ),
+                                selective_depth,
                             ),
                             conspiracy_counter.unwrap(),
                             mt_searches,
@@ -377,11 +441,14 @@ pub fn mtd_h_search<T: SearchResult + Default + Clone>(
                             };
 
                             return (
-                                T::make_search_result(
-                                    result.best_move(),
-                                    EvalBound::Exact(result.eval_bound().board_evaluation()),
-                                    Some(nodes_searched),
-                                    result.critical_path(),
+                                with_selective_depth(
+                                    T::make_search_result(
+                                        result.best_move(),
+                                        result.eval_bound(),
+                                        Some(nodes_searched),
+                                        result.critical_path(),
+                                    ),
+                                    selective_depth,
                                 ),
                                 conspiracy_counter.unwrap(),
                                 mt_searches,
@@ -406,10 +473,14 @@ pub fn mtd_h_search<T: SearchResult + Default + Clone>(
             result.eval_bound().board_evaluation(),
         );
 
-        // prevents the program from gobbling up all the system memory
-        // and destabilizing the OS (had to restart)
-        if mt_search_num > 100 {
-            panic!("already done more than 100 mt_searches, likely in infinite loop");
+        if mt_search_num >= MAX_ROUNDS {
+            debug_log!(
+                "no exact evaluation after {} MT searches: lowerbound {:?}, upperbound {:?}",
+                MAX_ROUNDS,
+                lowerbound,
+                upperbound,
+            );
+            break;
         }
     }
 
@@ -425,15 +496,16 @@ pub fn mtd_h_search<T: SearchResult + Default + Clone>(
         timestamp: total_search_time.duration_since(UNIX_EPOCH).unwrap_or(Duration::from_secs(0)).as_secs() as i64,
     };
 
-    // Accounts for instability in search due to transposition table
-    // Usually `lowerbound == upperbound` here
-    // But not always: just return anyways
+    // Only reached after `MAX_ROUNDS`: the bounds never met, so the result keeps its bound
     (
-        T::make_search_result(
-            result.best_move(),
-            EvalBound::Exact(result.eval_bound().board_evaluation()),
-            Some(nodes_searched),
-            result.critical_path(),
+        with_selective_depth(
+            T::make_search_result(
+                result.best_move(),
+                result.eval_bound(),
+                Some(nodes_searched),
+                result.critical_path(),
+            ),
+            selective_depth,
         ),
         conspiracy_counter.unwrap(),
         mt_searches,
@@ -5,6 +5,10 @@ use crate::core::search::conspiracy_counter::{ConspiracyCounter, ConspiracyValue
 use crate::core::search::mtd::avg_bounds;
 use crate::core::search::transpositions::EvalBound;
 
+/// The rounds of MT searches after which MTD-H gives up on the bounds meeting, and ends on the best bound found.
+/// Unstable bounds from the transposition table can keep them apart forever.
+pub const MAX_ROUNDS: u32 = 100;
+
 #[derive(Copy, Clone, Debug, Deserialize)]
 pub struct MtdHParams {
     pub training_depth: u32,
@@ -134,10 +138,47 @@ impl MtdHParams {
     }
 }
 
+/// Reads the MTD-H parameters from a csv file. Returns no parameters if the file can't be read.
+pub fn read_mtd_h_params(path: &str) -> Vec<MtdHParams> {
+    let reader = csv::ReaderBuilder::new()
+        .delimiter(b',')
+        .has_headers(true)
+        .from_path(path);
+
+    if reader.is_err() {
+        return vec![];
+    }
+
+    let mut reader = reader.unwrap();
+
+    let mut result: Vec<MtdHParams> = Vec::new();
+    for record in reader.deserialize() {
+        let record_result: Result<MtdHParams, _> = record;
+
+        if record_result.is_ok() {
+            result.push(record_result.ok().unwrap());
+        }
+    }
+
+    result
+}
+
+/// Only keeps the parameters trained `training_distance` plies below their target depth.
+pub fn filter_mtd_h_params(params: Vec<MtdHParams>, training_distance: u32) -> Vec<MtdHParams> {
+    params.into_iter()
+        .filter(|x| x.target_depth.saturating_sub(x.training_depth) == training_distance)
+        .collect()
+}
+
 pub fn select_test_point(probability_distribution: &[f64], bucket_size: u32, lowerbound: BoardEvaluation, upperbound: BoardEvaluation) -> BoardEvaluation {
+    select_quantile_test_point(probability_distribution, bucket_size, 0.5, lowerbound, upperbound)
+}
+
+/// The test point in the bucket where the cumulative distribution passes `quantile`, within the bounds
+pub fn select_quantile_test_point(probability_distribution: &[f64], bucket_size: u32, quantile: f64, lowerbound: BoardEvaluation, upperbound: BoardEvaluation) -> BoardEvaluation {
     let num_buckets = probability_distribution.len();
 
-    // get the index of the halfway point of the cumulative distribution
+    // get the index of the quantile of the cumulative distribution
     let cumulative_distribution = probability_distribution.into_iter()
         .scan(0.0, |accumulator, x| {
             *accumulator = *accumulator + *x;
@@ -147,7 +188,7 @@ pub fn select_test_point(probability_distribution: &[f64], bucket_size: u32, low
         .collect::<Vec<_>>();
 
     for (index, cumulative_score) in cumulative_distribution.into_iter().enumerate() {
-        if cumulative_score > 0.5 {
+        if cumulative_score > quantile {
             let (mut bucket_lowerbound, mut bucket_upperbound) = ConspiracyCounter::bucket_bounds(
                 index,
                 bucket_size,
@@ -177,7 +218,7 @@ pub fn select_test_point(probability_distribution: &[f64], bucket_size: u32, low
     }
 
     avg_bounds(lowerbound, upperbound)
-    // panic!("no bucket with cumulative score of > 0.5. {:?}", probability_distribution);
+    // panic!("no bucket with cumulative score of > quantile. {:?}", probability_distribution);
 }
 
 pub fn select_test_point_w_mate(
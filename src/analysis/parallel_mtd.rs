@@ -0,0 +1,496 @@
+// Parallel MTD: every round probes several test values at once, each MT search on its own thread over a shared table,
+// and merges the bounds and conspiracy counters they find before the test values of the next round are picked.
+
+use std::collections::VecDeque;
+use std::mem;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use chess::{Board, ChessMove, Color};
+use crate::analysis::database::rows::{MTSearchRow, PositionSearchRow};
+use crate::analysis::mtd_h_utils::{MAX_ROUNDS, MtdHParams, select_quantile_test_point, update_probability_distribution};
+use crate::core::score::{BoardEvaluation, Centipawns};
+use crate::core::search::conspiracy_counter::ConspiracyCounter;
+use crate::core::search::conspiracy_search::log_info_search_results;
+use crate::core::search::conspiracy_search::merging::MergeFn;
+use crate::core::search::conspiracy_search::mt_w_conspiracy::search_mt_w_conspiracy;
+use crate::core::search::conspiracy_search::mtd_w_conspiracy::mtd_search;
+use crate::core::search::extensions::ExtensionState;
+use crate::core::search::iterative_deepening::{determine_critical_path_string, first_depth, is_still_searching, selective_depth};
+use crate::core::search::mtd::avg_bounds;
+use crate::core::search::mtdbi::determine_mtdbi_step;
+use crate::core::search::move_ordering::OrderingState;
+use crate::core::search::multi_pv::search_multi_pv;
+use crate::core::search::position_history::PositionHistory;
+use crate::core::search::search_control::SearchControl;
+use crate::core::search::search_result::{with_selective_depth, SearchResult};
+use crate::core::search::transpositions::shared_transposition::{lend_transposition_table, return_transposition_table, ShardedTable, SharedTranspositionTable};
+use crate::core::search::transpositions::{EvalBound, TranspositionTable};
+use crate::debug_log;
+use crate::input::protocol_interpreter::CalculateOptions;
+
+pub const DEFAULT_MTD_PROBES: usize = 4;
+
+/// How a parallel MTD search is set up
+#[derive(Copy, Clone)]
+pub struct ParallelMtdParams<'a> {
+    pub probes: usize, // MT searches per round, at least 1
+    pub bucket_size: u32,
+    pub num_buckets: usize,
+    pub merge_fn: MergeFn, // Merges the conspiracy counters of the probes
+    pub mtd_h_params: &'a [MtdHParams], // The probability distributions the test values are picked from
+}
+
+/// An iteration of parallel MTD, to `depth`
+pub struct ParallelMtdIteration {
+    pub depth: u32,
+    pub start_point: BoardEvaluation, // The first test value, without a `probability_distribution`
+    pub probability_distribution: Option<Vec<f64>>,
+}
+
+pub fn parallel_mtd_iterative_deepening_search<T: SearchResult + Default + Clone + Send, L>(
+    board: &Board,
+    transposition_table: &mut Box<dyn TranspositionTable>,
+    visited_boards: Vec<u64>,
+    options: CalculateOptions,
+    params: ParallelMtdParams,
+    search_logging: L,
+    control: &SearchControl,
+) -> (T, ConspiracyCounter, u32, u32) where
+    L: Fn(PositionSearchRow, Vec<MTSearchRow>) { // (SearchResult, ConspiracyCounter, depth, selective_depth)
+    let now = Instant::now();
+    let ParallelMtdParams { probes, bucket_size, num_buckets, merge_fn, mtd_h_params } = params;
+    let shared_table = lend_transposition_table(transposition_table, probes.max(1) + 1);
+    // The other lines are searched on this thread, between the rounds of probes
+    let mut line_table: Box<dyn TranspositionTable> = Box::new(SharedTranspositionTable::new(shared_table.clone()));
+    // Every probe keeps its own move ordering over the iterations, though its threads are new every round
+    let mut probe_controls = (0..probes.max(1))
+        .map(|_| (control.helper(control.depth_offset()), OrderingState::new()))
+        .collect::<Vec<_>>();
+
+    let mut conspiracy_counters = vec![];
+
+    let start_depth = first_depth(&options, control);
+    let mut current_depth = start_depth + 1;
+    let first_result: (T, ConspiracyCounter, Vec<MTSearchRow>, PositionSearchRow) = parallel_mtd_search(
+        board,
+        &shared_table,
+        visited_boards.clone(),
+        ParallelMtdIteration {
+            depth: start_depth,
+            start_point: BoardEvaluation::PieceScore(Centipawns::new(0)),
+            probability_distribution: None,
+        },
+        &params,
+        &mut probe_controls,
+        control,
+    );
+    let mut search_result = first_result.0;
+    let mut conspiracy_counter = first_result.1;
+    conspiracy_counters.push(conspiracy_counter.clone());
+    search_logging(first_result.3, first_result.2);
+
+    while is_still_searching(&options, board, control, current_depth, &search_result) {
+        // The same distribution MTD-H picks its test points from
+        let probability_distribution = MtdHParams::find_applicable_param(mtd_h_params, current_depth)
+            .and_then(|x| Some((x, conspiracy_counters.get(x.training_depth.max(1).checked_sub(start_depth)? as usize)?)))
+            .map(|(params, old_conspiracy_counter)| params.generate_probability_distribution(
+                old_conspiracy_counter,
+                search_result.eval_bound().board_evaluation(),
+            ));
+
+        if probability_distribution.is_none() {
+            debug_log!("no probability distribution for depth {}, bisecting the bounds", current_depth);
+        }
+
+        let temp_search_result = parallel_mtd_search(
+            board,
+            &shared_table,
+            visited_boards.clone(),
+            ParallelMtdIteration {
+                depth: current_depth,
+                start_point: search_result.eval_bound().board_evaluation(),
+                probability_distribution,
+            },
+            &params,
+            &mut probe_controls,
+            control,
+        );
+        if control.is_stopped() {
+            // Keep the result of the last completed iteration
+            break;
+        }
+        search_result = temp_search_result.0;
+        conspiracy_counter = temp_search_result.1;
+
+        conspiracy_counters.push(conspiracy_counter.clone());
+
+        // OPTIONAL LOGGING TO DB
+        search_logging(temp_search_result.3, temp_search_result.2);
+
+        let lines = search_multi_pv(board, control, search_result.clone(), |line_control| {
+            mtd_search::<T>(
+                board,
+                &mut line_table,
+                visited_boards.clone(),
+                current_depth,
+                search_result.eval_bound().board_evaluation(),
+                determine_mtdbi_step,
+                bucket_size,
+                num_buckets,
+                merge_fn,
+                line_control,
+            ).0
+        });
+
+        let duration = now.elapsed();
+        log_info_search_results(
+            &lines,
+            board,
+            duration,
+            current_depth,
+            selective_depth(&search_result, current_depth),
+            &conspiracy_counter,
+            control,
+        );
+        current_depth += 1;
+    }
+
+    drop(line_table);
+    return_transposition_table(transposition_table, shared_table);
+
+    let selective_depth = selective_depth(&search_result, current_depth);
+    (
+        search_result,
+        conspiracy_counter,
+        current_depth,
+        selective_depth,
+    )
+}
+
+/// Searches to the `depth` of the `iteration` in rounds of MT searches, one thread per probe control, until the bounds meet.
+/// The test values are the quantiles of the `probability_distribution` if there is one, and bisection points of the bounds otherwise.
+pub fn parallel_mtd_search<T: SearchResult + Default + Clone + Send>(
+    board: &Board,
+    shared_table: &ShardedTable,
+    visited_boards: Vec<u64>,
+    iteration: ParallelMtdIteration,
+    params: &ParallelMtdParams,
+    probe_controls: &mut [(SearchControl, OrderingState)],
+    control: &SearchControl,
+) -> (T, ConspiracyCounter, Vec<MTSearchRow>, PositionSearchRow) {
+    let ParallelMtdIteration { depth, start_point, probability_distribution } = iteration;
+    let ParallelMtdParams { bucket_size, num_buckets, merge_fn, .. } = *params;
+    let current_evaluation = control.evaluate(board, board.status());
+
+    let simple_evaluation = match current_evaluation {
+        BoardEvaluation::PieceScore(x) => x,
+        x => {
+            return (
+                T::make_search_result(
+                    ChessMove::default(),
+                    EvalBound::Exact(current_evaluation),
+                    None,
+                    None,
+                ),
+                ConspiracyCounter::new(bucket_size, num_buckets),
+                vec![],
+                PositionSearchRow {
+                    run_id: 0,
+                    uci_position: "".to_string(),
+                    depth,
+                    time_taken: 0,
+                    nodes_evaluated: 1,
+                    evaluation: x,
+                    conspiracy_counter: None,
+                    move_num: 0,
+                    timestamp: 0,
+                }
+            );
+        },
+    };
+    let total_search_time = SystemTime::now();
+    let mut probability_distribution = probability_distribution;
+
+    let mut mt_searches = vec![];
+
+    // As in `mtd_search`, these are not exactly bounds,
+    // but nothing lies outside of them
+    let mut lowerbound = BoardEvaluation::BlackMate(0);
+    let mut upperbound = BoardEvaluation::WhiteMate(0);
+    let mut lowerbound_result: Option<T> = None; // The probe that found the lowerbound
+    let mut upperbound_result: Option<T> = None; // The probe that found the upperbound
+
+    let mut conspiracy_counter: Option<ConspiracyCounter> = None;
+    let mut nodes_searched = 0;
+    let mut selective_depth = 0; // The deepest ply of all the MT searches
+    let mut round = 0;
+    loop {
+        // Without a distribution, the first round starts at the start point, like `mtd_search`
+        let first_test_values = if round == 0 && probability_distribution.is_none() { vec![start_point] } else { vec![] };
+        let test_values = select_test_values(
+            &first_test_values,
+            probability_distribution.as_deref(),
+            bucket_size,
+            probe_controls.len(),
+            lowerbound,
+            upperbound,
+        );
+
+        let time = SystemTime::now();
+        let probes: Vec<(T, ConspiracyCounter, u32)> = thread::scope(|scope| {
+            let probe_threads = test_values.iter()
+                .zip(probe_controls.iter_mut())
+                .map(|(test_value, (probe_control, ordering))| {
+                    let mut probe_table: Box<dyn TranspositionTable> = Box::new(SharedTranspositionTable::new(shared_table.clone()));
+                    let mut position_history = PositionHistory::new(&visited_boards, board).with_castling(probe_control.castling());
+                    let test_value = *test_value;
+
+                    scope.spawn(move || {
+                        let time = SystemTime::now();
+                        probe_control.resume_ordering(mem::take(ordering));
+                        let (result, found_conspiracy_counter) = search_mt_w_conspiracy(
+                            board,
+                            &mut probe_table,
+                            &mut position_history,
+                            simple_evaluation,
+                            EvalBound::Exact(test_value),
+                            0,
+                            depth,
+                            bucket_size,
+                            num_buckets,
+                            ExtensionState::default(),
+                            true,
+                            probe_control,
+                        );
+                        *ordering = probe_control.take_ordering();
+                        (result, found_conspiracy_counter, time.elapsed().expect("time went backwards").as_millis() as u32)
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            probe_threads.into_iter()
+                .map(|x| x.join().expect("probe thread panicked"))
+                .collect()
+        });
+
+        if control.is_stopped() {
+            let (result, found_conspiracy_counter, _) = probes.into_iter().next().expect("every round has a probe");
+            return (
+                result,
+                found_conspiracy_counter,
+                mt_searches,
+                PositionSearchRow {
+                    run_id: 0,
+                    uci_position: "".to_string(),
+                    depth,
+                    time_taken: 0,
+                    nodes_evaluated: nodes_searched,
+                    evaluation: start_point,
+                    conspiracy_counter: None,
+                    move_num: 0,
+                    timestamp: 0,
+                },
+            );
+        }
+
+        // The counters of a round are merged with the bounds from before it, like the consecutive ones of `mtd_search`
+        let (round_lowerbound, round_upperbound) = (lowerbound, upperbound);
+        let mut exact_result = None;
+        let mut found_bounds = vec![];
+        for (test_value, (result, found_conspiracy_counter, time_taken)) in test_values.into_iter().zip(probes) {
+            nodes_searched += result.nodes_searched().unwrap_or(1);
+            selective_depth = selective_depth.max(result.selective_depth().unwrap_or(depth));
+
+            // Update the mt_searches log
+            mt_searches.push(MTSearchRow {
+                position_search_id: 0, // THIS NEEDS TO BE OVERWRITTEN ON POSITION_SEARCH INSERT
+                test_value,
+                time_taken,
+                nodes_evaluated: result.nodes_searched().unwrap_or(0),
+                eval_bound: result.eval_bound(),
+                conspiracy_counter: Some(found_conspiracy_counter.clone()),
+                search_num: mt_searches.len() as u32,
+                window: None,
+                timestamp: time.duration_since(UNIX_EPOCH).expect("time went backwards").as_secs() as i64,
+            });
+
+            match conspiracy_counter.as_mut() {
+                None => conspiracy_counter = Some(found_conspiracy_counter),
+                Some(x) => merge_fn(x, &found_conspiracy_counter, &EvalBound::LowerBound(round_lowerbound), &EvalBound::UpperBound(round_upperbound)),
+            }
+
+            found_bounds.push(result.eval_bound());
+            match result.eval_bound() {
+                EvalBound::Exact(_) => {
+                    exact_result = exact_result.or(Some(result));
+                },
+                EvalBound::LowerBound(x) => {
+                    if lowerbound_result.is_none() || x > lowerbound {
+                        lowerbound = x;
+                        lowerbound_result = Some(result);
+                    }
+                },
+                EvalBound::UpperBound(x) => {
+                    if upperbound_result.is_none() || x < upperbound {
+                        upperbound = x;
+                        upperbound_result = Some(result);
+                    }
+                },
+            }
+        }
+
+        let finished_result = if exact_result.is_some() {
+            exact_result
+        } else if lowerbound == upperbound {
+            // The lowerbound comes with a move that reaches it
+            lowerbound_result.clone().or(upperbound_result.clone())
+        } else if upperbound < lowerbound {
+            // The probes disagree through the shared table:
+            // - if white: end on lowerbound
+            // - if black: end on upperbound
+            debug_log!(
+                "unstable parallel search: lowerbound {:?}, upperbound {:?}, {}",
+                lowerbound,
+                upperbound,
+                determine_critical_path_string(lowerbound_result.as_ref().and_then(|x| x.critical_path())),
+            );
+            match board.side_to_move() {
+                Color::White => lowerbound_result.clone(),
+                Color::Black => upperbound_result.clone(),
+            }
+        } else if round >= MAX_ROUNDS {
+            // Likely in an infinite loop: end on the best bound found so far
+            debug_log!(
+                "no exact evaluation after {} rounds: lowerbound {:?}, upperbound {:?}",
+                MAX_ROUNDS,
+                lowerbound,
+                upperbound,
+            );
+            lowerbound_result.clone().or(upperbound_result.clone())
+        } else {
+            None
+        };
+
+        if let Some(result) = finished_result {
+            // Unless the bounds met, the search ends on a one-sided bound
+            let eval_bound = match lowerbound == upperbound {
+                true => EvalBound::Exact(result.eval_bound().board_evaluation()),
+                false => result.eval_bound(),
+            };
+            let position_search = PositionSearchRow {
+                run_id: 0, // NEEDS TO BE CHANGED HIGHER UP
+                uci_position: "".to_string(), // NEEDS TO BE CHANGED HIGHER UP
+                depth,
+                time_taken: total_search_time.elapsed().unwrap_or(Duration::from_secs(0)).as_millis() as u32,
+                nodes_evaluated: nodes_searched,
+                evaluation: result.eval_bound().board_evaluation(),
+                conspiracy_counter: conspiracy_counter.clone(),
+                move_num: 0, // NEEDS TO BE CHANGED HIGHER UP
+                timestamp: total_search_time.duration_since(UNIX_EPOCH).unwrap_or(Duration::from_secs(0)).as_secs() as i64,
+            };
+
+            return (
+                with_selective_depth(
+                    T::make_search_result(
+                        result.best_move(),
+                        eval_bound,
+                        Some(nodes_searched),
+                        result.critical_path(),
+                    ),
+                    selective_depth,
+                ),
+                conspiracy_counter.unwrap(),
+                mt_searches,
+                position_search,
+            );
+        }
+
+        if let Some(probability_distribution) = probability_distribution.as_mut() {
+            for found_bound in found_bounds {
+                update_probability_distribution(probability_distribution, found_bound, bucket_size);
+            }
+        }
+
+        round += 1;
+    }
+}
+
+/// Up to `probes` different test values within the bounds: the `first_test_values`,
+/// then the quantiles of the `probability_distribution`, then the bisection points of the bounds
+pub fn select_test_values(
+    first_test_values: &[BoardEvaluation],
+    probability_distribution: Option<&[f64]>,
+    bucket_size: u32,
+    probes: usize,
+    lowerbound: BoardEvaluation,
+    upperbound: BoardEvaluation,
+) -> Vec<BoardEvaluation> {
+    let quantiles = probability_distribution.into_iter()
+        .flat_map(|distribution| (1..=probes).map(move |x| select_quantile_test_point(
+            distribution,
+            bucket_size,
+            x as f64 / (probes + 1) as f64,
+            lowerbound,
+            upperbound,
+        )));
+    let candidates = first_test_values.iter().copied()
+        .chain(quantiles)
+        .chain(bisection_test_points(lowerbound, upperbound, probes));
+
+    let mut test_values = vec![];
+    for candidate in candidates {
+        if test_values.len() < probes && !test_values.contains(&candidate) {
+            test_values.push(candidate);
+        }
+    }
+
+    test_values
+}
+
+/// Up to `count` different test values splitting the bounds ever finer, the one `determine_mtdbi_step` picks first
+pub fn bisection_test_points(lowerbound: BoardEvaluation, upperbound: BoardEvaluation, count: usize) -> Vec<BoardEvaluation> {
+    let mut test_points = vec![];
+    let mut intervals = VecDeque::from([(lowerbound, upperbound)]);
+
+    while let Some((lower, upper)) = intervals.pop_front() {
+        if test_points.len() >= count {
+            break;
+        }
+
+        let middle = avg_bounds(lower, upper);
+        if test_points.contains(&middle) {
+            continue;
+        }
+        test_points.push(middle);
+        intervals.push_back((lower, middle));
+        intervals.push_back((middle, upper));
+    }
+
+    test_points
+}
+
+#[test]
+fn check_bisection_test_points() {
+    let score = |x| BoardEvaluation::PieceScore(Centipawns::new(x));
+
+    assert_eq!(bisection_test_points(score(-100), score(100), 3), vec![score(0), score(-50), score(50)]);
+    assert_eq!(bisection_test_points(score(0), score(1), 4), vec![score(0)]);
+    assert_eq!(
+        bisection_test_points(BoardEvaluation::BlackMate(0), BoardEvaluation::WhiteMate(0), 3),
+        vec![score(0), score(-20000), score(20000)],
+    );
+}
+
+#[test]
+fn check_select_test_values() {
+    let score = |x| BoardEvaluation::PieceScore(Centipawns::new(x));
+    // Everything in the middle bucket of three
+    let probability_distribution = [0.0, 1.0, 0.0];
+
+    let test_values = select_test_values(&[score(7)], Some(&probability_distribution), 20, 3, score(-100), score(100));
+    assert_eq!(test_values.len(), 3);
+    assert_eq!(test_values[0], score(7));
+    assert!(test_values.iter().all(|x| score(-100) <= *x && *x <= score(100)));
+
+    assert_eq!(select_test_values(&[], None, 20, 2, score(-100), score(100)), vec![score(0), score(-50)]);
+}
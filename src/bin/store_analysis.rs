@@ -7,7 +7,7 @@ use sn0l::analysis::args::{Args, PlayOptions};
 use sn0l::analysis::database::{CONFIG_TABLE, create_db_if_not_exists, create_tables_if_not_exists, DB_URL, MT_SEARCH_TABLE, POSITION_SEARCH_TABLE, RUN_TABLE};
 use sn0l::analysis::database::rows::{ConfigRow, ConspiracyMergeFn, MTSearchRow, PositionSearchRow, RunRow};
 use sn0l::analysis::match_orchestration;
-use sn0l::analysis::match_orchestration::{ConspiracySearchOptions, MatchResult, play_match, play_position, TranspositionOptions};
+use sn0l::analysis::match_orchestration::{ConspiracySearchOptions, MatchResult, play_match, play_position, SearchAlgorithm, TranspositionOptions};
 use sn0l::analysis::mtd_h_utils::{select_test_point, update_probability_distribution};
 use sn0l::core::score::{BoardEvaluation, Centipawns};
 use sn0l::core::search::conspiracy_counter::{ConspiracyCounter, ConspiracyValue};
@@ -77,6 +77,9 @@ fn main() {
     let algorithm = args.algorithm;
     let conspiracy_search_options = args.conspiracy_options();
     let transposition_options = args.transposition_options();
+    let pruning_options = args.pruning_options();
+    let extension_options = args.extension_options();
+    let aspiration_options = args.aspiration_options();
 
     let tokio_runtime = tokio::runtime::Runtime::new().expect("failed to create tokio runtime");
     let db = tokio_runtime.block_on(create_db_if_not_exists(db_path));
@@ -95,6 +98,17 @@ fn main() {
         conspiracy_merge_fn: conspiracy_search_options.merge_fn_name(),
         transposition_table_used: transposition_options != TranspositionOptions::NoTransposition,
         minimum_transposition_depth: transposition_options.minimum_transposition_depth(),
+        null_move_pruning_used: pruning_options.null_move.is_some(),
+        null_move_reduction: pruning_options.null_move.map(|x| x.reduction),
+        late_move_reductions_used: pruning_options.late_move_reductions.is_some(),
+        lmr_base: pruning_options.late_move_reductions.map(|x| x.base()),
+        lmr_divisor: pruning_options.late_move_reductions.map(|x| x.divisor()),
+        check_extension: extension_options.check,
+        recapture_extension: extension_options.recapture,
+        aspiration_windows_used: aspiration_options.is_some(),
+        aspiration_width: aspiration_options.map(|x| x.width.0 as u32),
+        aspiration_widening: aspiration_options.map(|x| x.widening),
+        mtd_probes: (algorithm == SearchAlgorithm::ParallelMTDIterativeDeepening).then_some(args.mtd_probes as u32),
         timestamp: time.duration_since(UNIX_EPOCH).expect("time went backwards").as_secs() as i64,
     };
 
@@ -112,6 +126,10 @@ fn main() {
                     opening_name.as_deref(),
                     conspiracy_search_options,
                     transposition_options,
+                    pruning_options,
+                    extension_options,
+                    aspiration_options,
+                    args.mtd_probes,
                     &mtd_params,
                     &db,
                     config_db_result.last_insert_rowid(),
@@ -128,6 +146,10 @@ fn main() {
                     opening_name.as_deref(),
                     conspiracy_search_options,
                     transposition_options,
+                    pruning_options,
+                    extension_options,
+                    aspiration_options,
+                    args.mtd_probes,
                     &mtd_params,
                     &db,
                     config_db_result.last_insert_rowid(),
@@ -0,0 +1,264 @@
+// Castling beyond what the move generator of the `chess` crate knows: it castles with the king on the e-file and the rooks in the corners.
+// Chess960 writes a castle as the king taking its own rook, which is how the castles of `Chess960Castling` are generated and searched.
+
+use chess::{between, get_bishop_moves, get_king_moves, get_knight_moves, get_pawn_attacks, get_rank, get_rook_moves, BitBoard, Board, BoardBuilder, ChessMove, Color, File, MoveGen, Piece, Square, ALL_COLORS, EMPTY};
+
+/// The castling rights of a Chess960 position, which the board can't hold: the king and the rooks may start on any file.
+/// The board then has no castling rights of its own, and these are kept along with it, see `PositionHistory`.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Chess960Castling {
+    kings: BitBoard, // The kings that can still castle, which haven't left their start square
+    rooks: BitBoard, // The rooks they can castle with
+}
+
+impl Chess960Castling {
+    /// The rights to castle with the rooks on `rooks`, each on the back rank of its king
+    pub fn new(board: &Board, rooks: BitBoard) -> Self {
+        let kings = ALL_COLORS.iter()
+            .map(|x| BitBoard::from_square(board.king_square(*x)))
+            .fold(EMPTY, |x, y| x | y);
+
+        Chess960Castling { kings, rooks }.after_move(board)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rooks == EMPTY
+    }
+
+    /// The rooks of `color` it can still castle with
+    pub fn rooks(&self, color: Color) -> BitBoard {
+        self.rooks & get_rank(color.to_my_backrank())
+    }
+
+    /// The rights left in `board`, reached by a move from the position these rights are for.
+    /// A moved king gives up both its castles, a moved or captured rook its own.
+    pub fn after_move(&self, board: &Board) -> Self {
+        if self.is_empty() {
+            return *self;
+        }
+
+        let mut kings = EMPTY;
+        let mut rooks = EMPTY;
+        for color in ALL_COLORS {
+            let own_pieces = board.color_combined(color) & get_rank(color.to_my_backrank());
+            let king = self.kings & own_pieces & board.pieces(Piece::King);
+            let color_rooks = self.rooks & own_pieces & board.pieces(Piece::Rook);
+
+            if king != EMPTY && color_rooks != EMPTY {
+                kings |= king;
+                rooks |= color_rooks;
+            }
+        }
+
+        Chess960Castling { kings, rooks }
+    }
+
+    /// The legal castles of the side to move, as the king taking its own rook
+    pub fn castles(&self, board: &Board) -> Vec<ChessMove> {
+        let color = board.side_to_move();
+        let king = board.king_square(color);
+        if self.rooks(color) == EMPTY || self.kings & BitBoard::from_square(king) == EMPTY || *board.checkers() != EMPTY {
+            return vec![];
+        }
+
+        self.rooks(color)
+            .filter(|rook| can_castle(board, king, *rook))
+            .map(|rook| ChessMove::new(king, rook, None))
+            .collect()
+    }
+
+    /// The moves of the move generator, then the castles
+    pub fn legal_moves(&self, board: &Board) -> Vec<ChessMove> {
+        let mut moves: Vec<ChessMove> = MoveGen::new_legal(board).collect();
+        moves.extend(self.castles(board));
+        moves
+    }
+
+    pub fn is_legal(&self, board: &Board, chess_move: ChessMove) -> bool {
+        board.legal(chess_move) || (!self.is_empty() && self.castles(board).contains(&chess_move))
+    }
+
+    /// `board` after `chess_move`, which may be one of these castles
+    pub fn make_move(&self, board: &Board, chess_move: ChessMove) -> Board {
+        match self.is_empty() {
+            true => board.make_move_new(chess_move),
+            false => make_move(board, chess_move),
+        }
+    }
+}
+
+/// `board` after `chess_move`, where the king taking its own rook castles: the board can't make those itself
+pub fn make_move(board: &Board, chess_move: ChessMove) -> Board {
+    if !is_king_taking_rook(board, chess_move) {
+        return board.make_move_new(chess_move);
+    }
+
+    let (king_move, rook_move) = castle_destinations(chess_move.get_source(), chess_move.get_dest());
+    let color = board.side_to_move();
+    let mut builder = BoardBuilder::from(board);
+    builder.clear_square(king_move.get_source())
+        .clear_square(rook_move.get_source())
+        .piece(king_move.get_dest(), Piece::King, color)
+        .piece(rook_move.get_dest(), Piece::Rook, color)
+        .side_to_move(!color)
+        .en_passant(None);
+
+    Board::try_from(builder).expect("castling leaves a valid board")
+}
+
+/// The king move and the rook move of a castle, written either as the king going two files or as the king taking its own rook
+pub fn castle_moves(board: &Board, chess_move: ChessMove) -> Option<(ChessMove, ChessMove)> {
+    let source = chess_move.get_source();
+    if board.piece_on(source) != Some(Piece::King) {
+        return None;
+    }
+
+    if is_king_taking_rook(board, chess_move) {
+        return Some(castle_destinations(source, chess_move.get_dest()));
+    }
+
+    // Any other king move goes at most one file
+    let rank = source.get_rank();
+    let rook_move = match (source.get_file(), chess_move.get_dest().get_file()) {
+        (File::E, File::G) => ChessMove::new(Square::make_square(rank, File::H), Square::make_square(rank, File::F), None),
+        (File::E, File::C) => ChessMove::new(Square::make_square(rank, File::A), Square::make_square(rank, File::D), None),
+        _ => return None,
+    };
+    Some((chess_move, rook_move))
+}
+
+/// The rook move that goes along with `chess_move`, if it castles
+pub fn castling_rook_move(board: &Board, chess_move: ChessMove) -> Option<ChessMove> {
+    castle_moves(board, chess_move).map(|(_, rook_move)| rook_move)
+}
+
+/// Writes a castle as the king taking its rook, as Chess960 does
+pub fn to_king_takes_rook(board: &Board, chess_move: ChessMove) -> ChessMove {
+    match castling_rook_move(board, chess_move) {
+        Some(rook_move) => ChessMove::new(chess_move.get_source(), rook_move.get_source(), None),
+        None => chess_move,
+    }
+}
+
+/// Reads a king taking its own rook as a castle of the move generator; that is never a legal move otherwise, so other moves are left alone
+pub fn from_king_takes_rook(board: &Board, chess_move: ChessMove) -> ChessMove {
+    match is_king_taking_rook(board, chess_move) {
+        true => castle_destinations(chess_move.get_source(), chess_move.get_dest()).0,
+        false => chess_move,
+    }
+}
+
+fn is_king_taking_rook(board: &Board, chess_move: ChessMove) -> bool {
+    let (source, dest) = (chess_move.get_source(), chess_move.get_dest());
+
+    board.piece_on(source) == Some(Piece::King)
+        && board.piece_on(dest) == Some(Piece::Rook)
+        && board.color_on(source) == board.color_on(dest)
+}
+
+/// The king and rook moves of castling with the rook on `rook`: they always end up on the g- and f-file, or the c- and d-file,
+/// whichever side the rook is on
+fn castle_destinations(king: Square, rook: Square) -> (ChessMove, ChessMove) {
+    let (king_file, rook_file) = match rook.get_file() > king.get_file() {
+        true => (File::G, File::F),
+        false => (File::C, File::D),
+    };
+    let rank = king.get_rank();
+
+    (
+        ChessMove::new(king, Square::make_square(rank, king_file), None),
+        ChessMove::new(rook, Square::make_square(rank, rook_file), None),
+    )
+}
+
+/// The squares from `from` to `to`, both included
+fn span(from: Square, to: Square) -> BitBoard {
+    between(from, to) | BitBoard::from_square(from) | BitBoard::from_square(to)
+}
+
+/// Whether the king on `king` can castle with the rook on `rook`, not being in check.
+/// Both have to reach their square over empty ones, and the king can't cross an attacked square.
+/// The attacks are found without the king and the rook, which may have been blocking them on the back rank.
+fn can_castle(board: &Board, king: Square, rook: Square) -> bool {
+    let (king_move, rook_move) = castle_destinations(king, rook);
+    let occupied = board.combined() ^ BitBoard::from_square(king) ^ BitBoard::from_square(rook);
+
+    let king_path = span(king, king_move.get_dest());
+    if (king_path | span(rook, rook_move.get_dest())) & occupied != EMPTY {
+        return false;
+    }
+
+    !king_path.into_iter().any(|x| is_attacked(board, x, !board.side_to_move(), occupied))
+}
+
+fn is_attacked(board: &Board, square: Square, attacker: Color, occupied: BitBoard) -> bool {
+    let attackers = board.color_combined(attacker);
+    let pieces = |piece: Piece| board.pieces(piece) & attackers;
+    let straight = pieces(Piece::Rook) | pieces(Piece::Queen);
+    let diagonal = pieces(Piece::Bishop) | pieces(Piece::Queen);
+
+    get_rook_moves(square, occupied) & straight != EMPTY
+        || get_bishop_moves(square, occupied) & diagonal != EMPTY
+        || get_knight_moves(square) & pieces(Piece::Knight) != EMPTY
+        || get_king_moves(square) & pieces(Piece::King) != EMPTY
+        || get_pawn_attacks(square, !attacker, pieces(Piece::Pawn)) != EMPTY
+}
+
+#[test]
+fn check_king_takes_rook() {
+    use std::str::FromStr;
+
+    let board = Board::from_str("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+    let short_castle = ChessMove::from_str("e1g1").unwrap();
+    let long_castle = ChessMove::from_str("e1c1").unwrap();
+
+    assert_eq!(to_king_takes_rook(&board, short_castle), ChessMove::from_str("e1h1").unwrap());
+    assert_eq!(to_king_takes_rook(&board, long_castle), ChessMove::from_str("e1a1").unwrap());
+    assert_eq!(from_king_takes_rook(&board, ChessMove::from_str("e1h1").unwrap()), short_castle);
+    assert_eq!(from_king_takes_rook(&board, ChessMove::from_str("e1a1").unwrap()), long_castle);
+
+    // Not castles
+    let king_move = ChessMove::from_str("e1f1").unwrap();
+    assert_eq!(to_king_takes_rook(&board, king_move), king_move);
+    let rook_move = ChessMove::from_str("h1g1").unwrap();
+    assert_eq!(castling_rook_move(&board, rook_move), None);
+    assert_eq!(from_king_takes_rook(&board, rook_move), rook_move);
+
+    let black_board = board.null_move().unwrap();
+    assert_eq!(
+        castling_rook_move(&black_board, ChessMove::from_str("e8g8").unwrap()),
+        Some(ChessMove::from_str("h8f8").unwrap()),
+    );
+}
+
+#[test]
+fn check_chess960_castles() {
+    use std::str::FromStr;
+
+    let board = Board::from_str("1k6/8/8/8/8/8/8/RK5R w - - 0 1").unwrap();
+    let castling = Chess960Castling::new(&board, BitBoard::from_square(Square::A1) | BitBoard::from_square(Square::H1));
+    let long_castle = ChessMove::from_str("b1a1").unwrap();
+    let short_castle = ChessMove::from_str("b1h1").unwrap();
+    assert_eq!(castling.castles(&board), vec![long_castle, short_castle]);
+
+    let after_long_castle = castling.make_move(&board, long_castle);
+    assert_eq!(after_long_castle, Board::from_str("1k6/8/8/8/8/8/8/2KR3R b - - 1 1").unwrap());
+    assert!(castling.after_move(&after_long_castle).is_empty());
+    assert_eq!(
+        castling.make_move(&board, short_castle),
+        Board::from_str("1k6/8/8/8/8/8/8/R4RK1 b - - 1 1").unwrap(),
+    );
+
+    // A rook that moves gives up its own castle only
+    let rook_moved = castling.make_move(&board, ChessMove::from_str("a1a2").unwrap());
+    assert_eq!(castling.after_move(&rook_moved).rooks(Color::White), BitBoard::from_square(Square::H1));
+
+    // The king can't cross d1
+    let attacked = Board::from_str("1k6/8/8/8/8/5b2/8/RK5R w - - 0 1").unwrap();
+    assert_eq!(castling.castles(&attacked), vec![long_castle]);
+
+    // The king stays on c1, where the rook on b1 no longer blocks the one on a1
+    let pinned_rook = Board::from_str("7k/8/8/8/8/8/8/rRK5 w - - 0 1").unwrap();
+    let castling = Chess960Castling::new(&pinned_rook, BitBoard::from_square(Square::B1));
+    assert!(castling.castles(&pinned_rook).is_empty());
+}
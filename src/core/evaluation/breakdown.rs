@@ -0,0 +1,58 @@
+use chess::{Board, Color, Piece, Square};
+use crate::core::score::Centipawns;
+use crate::core::score::score_tables::determine_piece_score;
+
+/// What a single piece adds to `single_evaluation`
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct PieceContribution {
+    pub square: Square,
+    pub color: Color,
+    pub piece: Piece,
+    pub score: Centipawns, // Positive for white, negative for black
+}
+
+/// Every piece on the board, from a1 to h8.
+/// Unless the game is over, the scores add up to the `single_evaluation`.
+pub fn evaluation_breakdown(board: &Board) -> Vec<PieceContribution> {
+    chess::ALL_SQUARES.iter()
+        .filter_map(|square| {
+            let piece = board.piece_on(*square)?;
+            let color = board.color_on(*square)?;
+            let score = determine_piece_score(*square, color, piece);
+
+            Some(PieceContribution {
+                square: *square,
+                color,
+                piece,
+                score: match color {
+                    Color::White => score,
+                    Color::Black => -score,
+                },
+            })
+        })
+        .collect()
+}
+
+#[test]
+fn check_breakdown_adds_up() {
+    use std::str::FromStr;
+    use chess::{BoardStatus, MoveGen};
+    use crate::core::evaluation::{game_status, single_evaluation};
+    use crate::core::score::BoardEvaluation;
+
+    for fen in [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    ] {
+        let board = Board::from_str(fen).unwrap();
+        let status = game_status(&board, MoveGen::new_legal(&board).len() > 0);
+        assert_eq!(status, BoardStatus::Ongoing);
+
+        let breakdown = evaluation_breakdown(&board);
+        assert_eq!(breakdown.len(), board.combined().popcnt() as usize);
+
+        let total = breakdown.iter().fold(Centipawns::new(0), |total, x| total + x.score);
+        assert_eq!(BoardEvaluation::PieceScore(total), single_evaluation(&board, status));
+    }
+}
@@ -1,4 +1,5 @@
 use chess::{Board, ChessMove, Color, Piece, Rank, Square};
+use crate::core::castling::castle_moves;
 use crate::core::score::{Centipawns};
 use crate::core::score::score_tables::determine_piece_score;
 
@@ -14,47 +15,19 @@ pub fn incremental_evaluation(
     let source_square = chess_move.get_source();
     let to_square = chess_move.get_dest();
     // let source_piece = board.piece_on(source_square).expect(&format!("Move needs to have a piece on source square, source: {source_square:?}, to: {to_square:?}"));
-    let source_piece = board.piece_on(source_square);
-    if let None = source_piece {
-        println!("failed move {chess_move:?}, color: {our_color:?}");
-        println!("failed board: {board}")
-    }
-    let source_piece = source_piece.expect(&format!("Move needs to have a piece on source square, source: {source_square:?}, to: {to_square:?}"));
-
+    let source_piece = board.piece_on(source_square)
+        .unwrap_or_else(|| panic!("Move needs to have a piece on source square, move: {chess_move}, color: {our_color:?}, board: {board}"));
 
-    result += incremental_move_diff(chess_move, source_piece, our_color);
 
-    // Castling check
-    if source_piece == Piece::King {
-        result += match (source_square, to_square) {
-            (Square::E1, Square::G1) =>
-                incremental_move_diff(
-                    &ChessMove::new(Square::H1, Square::F1, None),
-                    Piece::Rook,
-                    our_color,
-                ),
-            (Square::E1, Square::C1) =>
-                incremental_move_diff(
-                    &ChessMove::new(Square::A1, Square::D1, None),
-                    Piece::Rook,
-                    our_color,
-                ),
-            (Square::E8, Square::G8) =>
-                incremental_move_diff(
-                    &ChessMove::new(Square::H8, Square::E8, None),
-                    Piece::Rook,
-                    our_color,
-                ),
-            (Square::E8, Square::C8) =>
-                incremental_move_diff(
-                    &ChessMove::new(Square::A8, Square::D8, None),
-                    Piece::Rook,
-                    our_color,
-                ),
-            (_, _) => Centipawns::new(0),
-        }
+    // Castling check: in Chess960 the king takes its own rook, which captures nothing
+    if let Some((king_move, rook_move)) = castle_moves(board, *chess_move) {
+        result += incremental_move_diff(&king_move, Piece::King, our_color);
+        result += incremental_move_diff(&rook_move, Piece::Rook, our_color);
+        return result;
     }
 
+    result += incremental_move_diff(chess_move, source_piece, our_color);
+
     // Check capture
     if let Some(opponent_piece) = board.piece_on(to_square) {
         // Positive, since removing from opposing color
@@ -3,6 +3,11 @@ use chess::{BitBoard, Board, BoardStatus, Color, EMPTY};
 use crate::core::score::{BoardEvaluation, Centipawns, score_tables};
 
 pub mod incremental;
+pub mod breakdown;
+pub mod see;
+
+/// Scores a position for the search, see `EngineBuilder::evaluator`
+pub type Evaluator = fn(&Board, BoardStatus) -> BoardEvaluation;
 
 
 
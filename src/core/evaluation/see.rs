@@ -0,0 +1,111 @@
+// Static exchange evaluation: the material outcome of all captures on a single square, without searching.
+
+use chess::{get_bishop_moves, get_king_moves, get_knight_moves, get_pawn_attacks, get_rook_moves, BitBoard, Board, ChessMove, Color, Piece, Square, ALL_PIECES, EMPTY};
+use crate::core::score::{piece_value, Centipawns};
+
+/// The material `chess_move` wins for the side to move, if both sides keep capturing on its destination
+/// with their least valuable piece, and stop as soon as that no longer pays off.
+/// Pins are ignored, and only the first move is counted as a promotion.
+pub fn static_exchange_evaluation(board: &Board, chess_move: ChessMove) -> Centipawns {
+    let source = chess_move.get_source();
+    let dest = chess_move.get_dest();
+    let moving_piece = board.piece_on(source).expect("move has no source piece");
+
+    let mut occupied = *board.combined() ^ BitBoard::from_square(source);
+    let mut first_gain = board.piece_on(dest).map_or(Centipawns::new(0), piece_value);
+    if moving_piece == Piece::Pawn && source.get_file() != dest.get_file() && board.piece_on(dest).is_none() {
+        // En passant: the captured pawn is next to the destination
+        let captured_square = Square::make_square(source.get_rank(), dest.get_file());
+        occupied ^= BitBoard::from_square(captured_square);
+        first_gain = piece_value(Piece::Pawn);
+    }
+
+    let mut attacker_value = piece_value(moving_piece);
+    if let Some(promotion) = chess_move.get_promotion() {
+        first_gain += piece_value(promotion) - piece_value(Piece::Pawn);
+        attacker_value = piece_value(promotion);
+    }
+
+    // gains[i]: what the side making capture `i` has won, if the opponent recaptures next
+    let mut gains = vec![first_gain];
+    let mut side = !board.side_to_move();
+    loop {
+        let attackers = attackers_to(board, dest, occupied) & board.color_combined(side);
+        let Some((square, piece)) = least_valuable_attacker(board, attackers) else {
+            break;
+        };
+
+        gains.push(attacker_value - *gains.last().unwrap());
+        occupied ^= BitBoard::from_square(square);
+        attacker_value = piece_value(piece);
+        side = !side;
+    }
+
+    // Each side may also stop capturing
+    for i in (1..gains.len()).rev() {
+        gains[i - 1] = -(-gains[i - 1]).max(gains[i]);
+    }
+
+    gains[0]
+}
+
+/// Whether `chess_move` loses material on its destination
+pub fn is_losing_exchange(board: &Board, chess_move: ChessMove) -> bool {
+    static_exchange_evaluation(board, chess_move) < Centipawns::new(0)
+}
+
+/// The pieces of both colors attacking `square`, where only the pieces in `occupied` are on the board.
+/// Sliders behind a removed piece are included, which is what uncovers the x-rays of an exchange.
+pub fn attackers_to(board: &Board, square: Square, occupied: BitBoard) -> BitBoard {
+    let pieces = |piece: Piece| *board.pieces(piece);
+    let diagonal_sliders = pieces(Piece::Bishop) | pieces(Piece::Queen);
+    let straight_sliders = pieces(Piece::Rook) | pieces(Piece::Queen);
+    let pawns_of = |color: Color| pieces(Piece::Pawn) & board.color_combined(color);
+
+    let attackers = (get_knight_moves(square) & pieces(Piece::Knight))
+        | (get_king_moves(square) & pieces(Piece::King))
+        | (get_bishop_moves(square, occupied) & diagonal_sliders)
+        | (get_rook_moves(square, occupied) & straight_sliders)
+        // A pawn attacks `square` from where a pawn of the other color on `square` would attack
+        | get_pawn_attacks(square, Color::Black, pawns_of(Color::White))
+        | get_pawn_attacks(square, Color::White, pawns_of(Color::Black));
+
+    attackers & occupied
+}
+
+fn least_valuable_attacker(board: &Board, attackers: BitBoard) -> Option<(Square, Piece)> {
+    ALL_PIECES.into_iter()
+        .map(|piece| (attackers & board.pieces(piece), piece))
+        .find(|(x, _)| *x != EMPTY)
+        .map(|(x, piece)| (x.to_square(), piece))
+}
+
+#[test]
+fn check_static_exchange_evaluation() {
+    use std::str::FromStr;
+
+    let see = |fen: &str, chess_move: &str| {
+        static_exchange_evaluation(&Board::from_str(fen).expect(fen), ChessMove::from_str(chess_move).unwrap()).0
+    };
+
+    // Undefended, and defended by a pawn
+    assert_eq!(see("4k3/8/8/3p4/8/8/8/3RK3 w - - 0 1", "d1d5"), 100);
+    assert_eq!(see("4k3/8/4p3/3p4/8/8/8/3RK3 w - - 0 1", "d1d5"), -400);
+    assert_eq!(see("4k3/8/4p3/3n4/4P3/8/8/4K3 w - - 0 1", "e4d5"), 200);
+    // The rook behind the rook joins in through the x-ray
+    assert_eq!(see("3rk3/8/8/3p4/8/8/3R4/3RK3 w - - 0 1", "d2d5"), 100);
+    assert_eq!(see("3rk3/3r4/8/3p4/8/8/3R4/3RK3 w - - 0 1", "d2d5"), -400);
+    assert_eq!(see("7k/8/2p5/3p4/4Q3/8/8/4K3 w - - 0 1", "e4d5"), -800);
+    // The queen doesn't recapture, as the bishop would take it
+    assert_eq!(see("3qk3/8/8/3p4/8/5B2/8/3RK3 w - - 0 1", "d1d5"), 100);
+    // En passant and promotion
+    assert_eq!(see("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6"), 100);
+    assert_eq!(see("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7b8q"), 1300);
+    assert_eq!(see("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7a8q"), -100);
+    // The king only recaptures on an undefended square
+    assert_eq!(see("4k3/8/8/8/8/8/3r4/3QK3 b - - 0 1", "d2d1"), 900 - 500);
+    assert_eq!(see("4k3/8/8/7b/8/8/3r4/3QK3 b - - 0 1", "d2d1"), 900);
+    // Quiet moves only lose what is left hanging
+    assert_eq!(see("4k3/8/8/8/8/8/8/3QK3 w - - 0 1", "d1d7"), -900);
+    assert_eq!(see("4k3/8/8/8/8/8/8/3QK3 w - - 0 1", "d1d4"), 0);
+}
@@ -6,6 +6,8 @@ pub mod score;
 pub mod evaluation_old;
 pub mod evaluation;
 pub mod search;
+pub mod perft;
+pub mod castling;
 
 pub fn is_default_move(chess_move: &ChessMove) -> bool {
     chess_move.get_source() == Square::default() && chess_move.get_dest() == Square::default()
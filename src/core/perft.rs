@@ -0,0 +1,47 @@
+// Counting the leaf nodes of the legal move tree, to check the move generation against known counts.
+
+use chess::{Board, ChessMove, MoveGen};
+use crate::core::castling::Chess960Castling;
+
+/// The number of move sequences of exactly `depth` plies from `board`
+pub fn perft(board: &Board, depth: u32) -> u64 {
+    match depth {
+        0 => 1,
+        // The leaves don't have to be made
+        1 => MoveGen::new_legal(board).len() as u64,
+        _ => MoveGen::new_legal(board)
+            .map(|x| perft(&board.make_move_new(x), depth - 1))
+            .sum(),
+    }
+}
+
+/// `perft` for a position that may castle as Chess960 does
+pub fn chess960_perft(board: &Board, castling: Chess960Castling, depth: u32) -> u64 {
+    if castling.is_empty() {
+        return perft(board, depth);
+    }
+
+    match depth {
+        0 => 1,
+        _ => castling.legal_moves(board).into_iter()
+            .map(|x| {
+                let new_board = castling.make_move(board, x);
+                chess960_perft(&new_board, castling.after_move(&new_board), depth - 1)
+            })
+            .sum(),
+    }
+}
+
+/// The `perft` count below every legal move, in move generation order, followed by the Chess960 castles
+pub fn perft_divide(board: &Board, castling: Chess960Castling, depth: u32) -> Vec<(ChessMove, u64)> {
+    if depth == 0 {
+        return vec![];
+    }
+
+    castling.legal_moves(board).into_iter()
+        .map(|x| {
+            let new_board = castling.make_move(board, x);
+            (x, chess960_perft(&new_board, castling.after_move(&new_board), depth - 1))
+        })
+        .collect()
+}
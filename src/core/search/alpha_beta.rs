@@ -1,16 +1,60 @@
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use chess::{Board, BoardStatus, ChessMove, Color, MoveGen};
 use crate::analysis::database::rows::PositionSearchRow;
-use crate::core::evaluation::{bubble_evaluation, game_status, single_evaluation};
+use crate::core::evaluation::{bubble_evaluation, game_status};
 use crate::core::evaluation::incremental::incremental_evaluation;
 use crate::core::score::{BoardEvaluation, Centipawns};
 use crate::core::search::common::check_game_over;
-use crate::core::search::draw_detection::detect_draw_incremental;
+use crate::core::search::extensions::{ExtensionState, MAX_PLY};
 use crate::core::search::move_ordering::order_moves;
-use crate::core::search::search_result::SearchResult;
+use crate::core::search::position_history::PositionHistory;
+use crate::core::search::pruning::{is_late_move_fail_high, is_null_move_cutoff};
+use crate::core::search::search_control::SearchControl;
+use crate::core::search::search_result::{reached_ply, SearchResult};
 use crate::core::search::SearchDepth;
 use crate::core::search::transpositions::{EvalBound, TranspositionTable};
 
+/// The window that tells every evaluation apart
+pub const FULL_WINDOW: (EvalBound, EvalBound) = (
+    EvalBound::Exact(BoardEvaluation::BlackMate(0)),
+    EvalBound::Exact(BoardEvaluation::WhiteMate(0)),
+);
+
+/// The root of a search to `depth`, that only tells the evaluation apart within the window from `alpha` to `beta`
+#[derive(Copy, Clone, Debug)]
+pub struct SearchWindow {
+    pub depth: u32,
+    pub selective_depth: Option<u32>, // `depth` if not given
+    pub alpha: EvalBound,
+    pub beta: EvalBound,
+}
+
+impl SearchWindow {
+    pub fn full(depth: u32, selective_depth: Option<u32>) -> Self {
+        let (alpha, beta) = FULL_WINDOW;
+        SearchWindow {
+            depth,
+            selective_depth,
+            alpha,
+            beta,
+        }
+    }
+}
+
+/// Where a node of `search_alpha_beta` is in the search, and how it is searched
+#[derive(Copy, Clone, Debug)]
+pub struct AlphaBetaNode {
+    pub simple_evaluation: Centipawns,
+    pub alpha: EvalBound,
+    pub beta: EvalBound,
+    pub current_depth: u32,
+    pub max_depth: u32,
+    pub max_selective_depth: u32,
+    pub extension: ExtensionState, // What the line has been extended by so far
+    pub null_move_allowed: bool, // False right after a null move
+    pub pvs: bool, // Scouts every move after the first with a null window, see `pvs.rs`
+}
+
 /// The module for alpha-beta search;
 
 
@@ -21,10 +65,37 @@ pub fn search_depth_pruned<T: SearchResult + Default>(
     visited_boards: Vec<u64>,
     depth: u32,
     selective_depth: Option<u32>,
+    control: &SearchControl,
 ) -> (T, PositionSearchRow) {
+    search_window_pruned(board, transposition_table, visited_boards, SearchWindow::full(depth, selective_depth), control)
+}
+
+/// A search that only tells the evaluation apart within the `window`
+pub fn search_window_pruned<T: SearchResult + Default>(
+    board: &Board,
+    // transposition_table: &mut impl TranspositionTable,
+    transposition_table: &mut Box<dyn TranspositionTable>,
+    visited_boards: Vec<u64>,
+    window: SearchWindow,
+    control: &SearchControl,
+) -> (T, PositionSearchRow) {
+    search_window(board, transposition_table, visited_boards, window, false, control)
+}
+
+/// `search_window_pruned`, or with `pvs` its principal variation search
+pub fn search_window<T: SearchResult + Default>(
+    board: &Board,
+    // transposition_table: &mut impl TranspositionTable,
+    transposition_table: &mut Box<dyn TranspositionTable>,
+    visited_boards: Vec<u64>,
+    window: SearchWindow,
+    pvs: bool,
+    control: &SearchControl,
+) -> (T, PositionSearchRow) {
+    let SearchWindow { depth, selective_depth, alpha, beta } = window;
     // The base evaluation used for move ordering, and static board scoring
     let selective_depth = selective_depth.unwrap_or(depth);
-    let simple_eval = single_evaluation(&board, board.status());
+    let simple_eval = control.evaluate(board, board.status());
 
     let simple_score;
     match simple_eval {
@@ -37,13 +108,19 @@ pub fn search_depth_pruned<T: SearchResult + Default>(
     let search_result: T = search_alpha_beta(
         board,
         transposition_table,
-        visited_boards,
-        simple_score,
-        EvalBound::Exact(BoardEvaluation::BlackMate(0)),
-        EvalBound::Exact(BoardEvaluation::WhiteMate(0)),
-        0,
-        depth,
-        selective_depth,
+        &mut PositionHistory::new(&visited_boards, board).with_castling(control.castling()),
+        AlphaBetaNode {
+            simple_evaluation: simple_score,
+            alpha,
+            beta,
+            current_depth: 0,
+            max_depth: depth,
+            max_selective_depth: selective_depth,
+            extension: ExtensionState::default(),
+            null_move_allowed: true,
+            pvs,
+        },
+        control,
     );
 
     let position_search = PositionSearchRow {
@@ -64,19 +141,50 @@ pub fn search_depth_pruned<T: SearchResult + Default>(
     )
 }
 
+/// The depth a move is first searched to with a null window, if at all
+fn probe_depth(
+    board: &Board,
+    new_board: &Board,
+    chess_move: ChessMove,
+    move_index: usize,
+    node: &AlphaBetaNode,
+    control: &SearchControl,
+) -> Option<u32> {
+    let late_move_depth = control.pruning().late_move_depth(board, new_board, chess_move, move_index, node.current_depth, node.max_depth);
+
+    match node.pvs {
+        // The first move sets the window, the others only have to prove they don't beat it
+        true => (move_index > 0).then(|| late_move_depth.unwrap_or(node.max_depth)),
+        false => late_move_depth,
+    }
+}
+
 pub fn search_alpha_beta<T: SearchResult + Default>(
     board: &Board,
     // transposition_table: &mut impl TranspositionTable,
     transposition_table: &mut Box<dyn TranspositionTable>,
-    mut visited_boards: Vec<u64>,
-    simple_evaluation: Centipawns,
-    alpha: EvalBound,
-    beta: EvalBound,
-    current_depth: u32,
-    max_depth: u32,
-    max_selective_depth: u32,
+    position_history: &mut PositionHistory, // Ends with `board`
+    node: AlphaBetaNode,
+    control: &SearchControl,
 ) -> T { // (_, eval, nodes)
+    let AlphaBetaNode {
+        simple_evaluation,
+        alpha,
+        beta,
+        current_depth,
+        max_depth,
+        max_selective_depth,
+        extension,
+        null_move_allowed,
+        ..
+    } = node;
+    control.count_node();
+    if control.is_stopped() {
+        return T::default();
+    }
+
     let mut nodes_searched = 1;
+    let mut deepest_ply = current_depth;
 
     let mut alpha = alpha;
     let mut beta = beta;
@@ -85,8 +193,10 @@ pub fn search_alpha_beta<T: SearchResult + Default>(
     // unless the game is over
     let mut best_move = ChessMove::default();
 
+    let castling = position_history.castling();
+    let castles = castling.castles(board);
     let mut move_gen = MoveGen::new_legal(board);
-    let board_status = game_status(&board, move_gen.len() != 0);
+    let board_status = game_status(&board, move_gen.len() != 0 || !castles.is_empty());
 
     // let current_evaluation = BoardEvaluation::PieceScore(simple_evaluation);
 
@@ -94,10 +204,8 @@ pub fn search_alpha_beta<T: SearchResult + Default>(
     // => No draw detection necessary when only capturing
     // But still need draw detection on last move before quiescence search
 
-    let been_here_before = visited_boards.contains(&board.get_hash());
-    visited_boards.push(board.get_hash());
-    let visited_boards = visited_boards;
-    if let Some(search_result) = check_game_over(board, board_status, &visited_boards) {
+    let been_here_before = position_history.repetitions() > 1;
+    if let Some(search_result) = check_game_over(board, board_status, position_history) {
         return search_result;
     }
 
@@ -109,7 +217,8 @@ pub fn search_alpha_beta<T: SearchResult + Default>(
     ) {
         already_found_move = Some(solution.best_move); // register best move for re-use in move ordering
 
-        if solution.depth_searched >= SearchDepth::Depth(max_depth - current_depth) && !been_here_before {
+        // The root is always searched: its moves (multi-PV) or its window (aspiration) may differ from the stored search
+        if solution.depth_searched >= SearchDepth::Depth(max_depth - current_depth) && !been_here_before && current_depth > 0 {
             // Already found something deep enough, so no need to recalculate
             match board.side_to_move() {
                 Color::White => {
@@ -169,7 +278,7 @@ pub fn search_alpha_beta<T: SearchResult + Default>(
     }
 
 
-    if current_depth >= max_depth {
+    if current_depth >= max_depth || current_depth >= MAX_PLY {
         return quiescence_alpha_beta(
             board,
             transposition_table,
@@ -177,15 +286,86 @@ pub fn search_alpha_beta<T: SearchResult + Default>(
             alpha,
             beta,
             current_depth + 1,
-            max_selective_depth
+            max_selective_depth,
+            control,
         );
     }
 
-    let all_moves: Vec<ChessMove> = order_moves(
-        board,
-        already_found_move,
-        &mut move_gen,
-        false,
+    if let (true, Some(null_move_depth), Some(null_board)) = (
+        null_move_allowed,
+        control.pruning().null_move_depth(board, current_depth, max_depth),
+        board.null_move(),
+    ) {
+        control.with_ordering(|ordering| ordering.play_move(current_depth, None));
+        position_history.push_null(&null_board);
+        let null_result: T = search_alpha_beta(
+            &null_board,
+            transposition_table,
+            position_history,
+            AlphaBetaNode {
+                simple_evaluation,
+                alpha,
+                beta,
+                current_depth: current_depth + 1,
+                max_depth: null_move_depth,
+                max_selective_depth,
+                extension: extension.after_null_move(),
+                null_move_allowed: false,
+                ..node
+            },
+            control,
+        );
+        position_history.pop();
+        if control.is_stopped() {
+            return null_result;
+        }
+        nodes_searched += null_result.nodes_searched().unwrap_or(1);
+        deepest_ply = deepest_ply.max(reached_ply(current_depth, &null_result));
+
+        if is_null_move_cutoff(board.side_to_move(), null_result.eval_bound(), alpha, beta) {
+            // Passing could be the only move that doesn't lose: only trust the cutoff if a real move gets it too
+            let mut verification_result: T = search_alpha_beta(
+                board,
+                transposition_table,
+                position_history,
+                AlphaBetaNode {
+                    simple_evaluation,
+                    alpha,
+                    beta,
+                    current_depth,
+                    max_depth: null_move_depth,
+                    max_selective_depth,
+                    extension,
+                    null_move_allowed: false,
+                    ..node
+                },
+                control,
+            );
+            if control.is_stopped() {
+                return verification_result;
+            }
+            nodes_searched += verification_result.nodes_searched().unwrap_or(1);
+            deepest_ply = deepest_ply.max(reached_ply(current_depth, &verification_result));
+
+            if is_null_move_cutoff(board.side_to_move(), verification_result.eval_bound(), alpha, beta) {
+                verification_result.set_nodes_searched(Some(nodes_searched));
+                verification_result.set_selective_depth(Some(deepest_ply));
+                return verification_result;
+            }
+        }
+    }
+
+    let all_moves: Vec<ChessMove> = control.restrict_root_moves(
+        control.with_ordering(|ordering| order_moves(
+            board,
+            already_found_move,
+            &mut move_gen,
+            &castles,
+            false,
+            Some(ordering),
+            current_depth,
+        )),
+        current_depth,
     );
 
     let mut best_eval;
@@ -197,27 +377,74 @@ pub fn search_alpha_beta<T: SearchResult + Default>(
     if board.side_to_move() == Color::White {
         best_eval = EvalBound::UpperBound(BoardEvaluation::BlackMate(0));
 
-        for chess_move in all_moves.into_iter() {
-            let new_board = &board.make_move_new(chess_move);
+        for (move_index, chess_move) in all_moves.into_iter().enumerate() {
+            let new_board = &castling.make_move(board, chess_move);
+            position_history.push(new_board);
+            let (extension_plies, new_extension) = control.extensions().extend(board, new_board, chess_move, extension, max_depth);
+            control.with_ordering(|ordering| ordering.play_move(current_depth, Some(chess_move)));
             let improvement = incremental_evaluation(
                 &board,
                 &chess_move,
                 board.side_to_move(),
             );
 
-            let search_result: T = search_alpha_beta(
-                new_board,
-                transposition_table,
-                visited_boards.clone(),
-                simple_evaluation + improvement,  // + because white
-                alpha,
-                beta,
-                current_depth + 1,
-                max_depth,
-                max_selective_depth,
-            );
+            // Late quiet moves are first probed shallower, with a null window at alpha, and under PVS every move after the first.
+            // Only the moves that beat the window there are searched again with the full window.
+            let late_move_probe: Option<T> = probe_depth(board, new_board, chess_move, move_index, &node, control)
+                .map(|reduced_depth| search_alpha_beta(
+                    new_board,
+                    transposition_table,
+                    position_history,
+                    AlphaBetaNode {
+                        simple_evaluation: simple_evaluation + improvement,
+                        alpha,
+                        beta: alpha,
+                        current_depth: current_depth + 1,
+                        max_depth: reduced_depth + extension_plies,
+                        max_selective_depth: max_selective_depth + extension_plies,
+                        extension: new_extension,
+                        null_move_allowed: true,
+                        ..node
+                    },
+                    control,
+                ));
+            if let Some(probe_result) = &late_move_probe {
+                deepest_ply = deepest_ply.max(reached_ply(current_depth, probe_result));
+            }
+
+            let search_result: T = match late_move_probe {
+                Some(probe_result) if control.is_stopped()
+                    || !is_late_move_fail_high(Color::White, probe_result.eval_bound(), alpha, beta) => probe_result,
+                late_move_probe => {
+                    nodes_searched += late_move_probe.and_then(|x| x.nodes_searched()).unwrap_or(0);
+
+                    search_alpha_beta(
+                        new_board,
+                        transposition_table,
+                        position_history,
+                        AlphaBetaNode {
+                            simple_evaluation: simple_evaluation + improvement,  // + because white
+                            alpha,
+                            beta,
+                            current_depth: current_depth + 1,
+                            max_depth: max_depth + extension_plies,
+                            max_selective_depth: max_selective_depth + extension_plies,
+                            extension: new_extension,
+                            null_move_allowed: true,
+                            ..node
+                        },
+                        control,
+                    )
+                },
+            };
+            position_history.pop();
+            if control.is_stopped() {
+                // The search was interrupted: the partial result is discarded higher up
+                return search_result;
+            }
 
             nodes_searched += search_result.nodes_searched().unwrap_or(1);
+            deepest_ply = deepest_ply.max(reached_ply(current_depth, &search_result));
             if search_result.eval_bound() >= best_eval {
                 best_eval = search_result.eval_bound();
                 best_move = chess_move;
@@ -235,27 +462,74 @@ pub fn search_alpha_beta<T: SearchResult + Default>(
     } else { // black to play
         best_eval = EvalBound::LowerBound(BoardEvaluation::WhiteMate(0));
 
-        for chess_move in all_moves.into_iter() {
-            let new_board = &board.make_move_new(chess_move);
+        for (move_index, chess_move) in all_moves.into_iter().enumerate() {
+            let new_board = &castling.make_move(board, chess_move);
+            position_history.push(new_board);
+            let (extension_plies, new_extension) = control.extensions().extend(board, new_board, chess_move, extension, max_depth);
+            control.with_ordering(|ordering| ordering.play_move(current_depth, Some(chess_move)));
             let improvement = incremental_evaluation(
                 &board,
                 &chess_move,
                 board.side_to_move(),
             );
 
-            let search_result: T = search_alpha_beta(
-                new_board,
-                transposition_table,
-                visited_boards.clone(),
-                simple_evaluation - improvement,  // - because black
-                alpha,
-                beta,
-                current_depth + 1,
-                max_depth,
-                max_selective_depth,
-            );
+            // Late quiet moves are first probed shallower, with a null window at beta, and under PVS every move after the first.
+            // Only the moves that beat the window there are searched again with the full window.
+            let late_move_probe: Option<T> = probe_depth(board, new_board, chess_move, move_index, &node, control)
+                .map(|reduced_depth| search_alpha_beta(
+                    new_board,
+                    transposition_table,
+                    position_history,
+                    AlphaBetaNode {
+                        simple_evaluation: simple_evaluation - improvement,
+                        alpha: beta,
+                        beta,
+                        current_depth: current_depth + 1,
+                        max_depth: reduced_depth + extension_plies,
+                        max_selective_depth: max_selective_depth + extension_plies,
+                        extension: new_extension,
+                        null_move_allowed: true,
+                        ..node
+                    },
+                    control,
+                ));
+            if let Some(probe_result) = &late_move_probe {
+                deepest_ply = deepest_ply.max(reached_ply(current_depth, probe_result));
+            }
+
+            let search_result: T = match late_move_probe {
+                Some(probe_result) if control.is_stopped()
+                    || !is_late_move_fail_high(Color::Black, probe_result.eval_bound(), alpha, beta) => probe_result,
+                late_move_probe => {
+                    nodes_searched += late_move_probe.and_then(|x| x.nodes_searched()).unwrap_or(0);
+
+                    search_alpha_beta(
+                        new_board,
+                        transposition_table,
+                        position_history,
+                        AlphaBetaNode {
+                            simple_evaluation: simple_evaluation - improvement,  // - because black
+                            alpha,
+                            beta,
+                            current_depth: current_depth + 1,
+                            max_depth: max_depth + extension_plies,
+                            max_selective_depth: max_selective_depth + extension_plies,
+                            extension: new_extension,
+                            null_move_allowed: true,
+                            ..node
+                        },
+                        control,
+                    )
+                },
+            };
+            position_history.pop();
+            if control.is_stopped() {
+                // The search was interrupted: the partial result is discarded higher up
+                return search_result;
+            }
 
             nodes_searched += search_result.nodes_searched().unwrap_or(1);
+            deepest_ply = deepest_ply.max(reached_ply(current_depth, &search_result));
 
             if search_result.eval_bound() <= best_eval {
                 best_eval = search_result.eval_bound();
@@ -273,6 +547,10 @@ pub fn search_alpha_beta<T: SearchResult + Default>(
         }
     }
 
+    if beta < alpha {
+        control.with_ordering(|ordering| ordering.record_cutoff(board, best_move, current_depth, max_depth - current_depth));
+    }
+
     best_eval.set_board_evaluation(bubble_evaluation(best_eval.board_evaluation()));
 
     let eval_bound = match (board.side_to_move(), beta < alpha) {
@@ -282,14 +560,17 @@ pub fn search_alpha_beta<T: SearchResult + Default>(
     };
 
     best_search_result.prepend_move(best_move);
-    transposition_table.update(
-        board,
-        SearchDepth::Depth(max_depth - current_depth),
-        eval_bound,
-        best_move,
-        best_search_result.critical_path(),
-    );
+    if !control.is_restricted_root(current_depth) {
+        transposition_table.update(
+            board,
+            SearchDepth::Depth(max_depth - current_depth),
+            eval_bound,
+            best_move,
+            best_search_result.critical_path(),
+        );
+    }
     best_search_result.set_nodes_searched(Some(nodes_searched));
+    best_search_result.set_selective_depth(Some(deepest_ply));
     best_search_result.set_best_move(best_move);
     best_search_result.set_eval_bound(eval_bound);
     best_search_result
@@ -305,17 +586,24 @@ pub fn quiescence_alpha_beta<T: SearchResult + Default>(
     beta: EvalBound,
     current_depth: u32,
     max_selective_depth: u32,
+    control: &SearchControl,
 ) -> T { // (_, eval, nodes)
+    control.count_node();
+    if control.is_stopped() {
+        return T::default();
+    }
+
     let mut alpha = alpha;
     let mut beta = beta;
 
     let mut nodes_searched = 1;
+    let mut deepest_ply = current_depth;
     let mut best_move = ChessMove::default();
 
     let mut move_gen = MoveGen::new_legal(&board);
     let board_status = game_status(&board, move_gen.len() != 0);
 
-    let current_evaluation = BoardEvaluation::PieceScore(simple_evaluation);
+    let current_evaluation = control.evaluate_leaf(board, board_status, simple_evaluation);
 
     if board_status == BoardStatus::Checkmate {
         return T::make_search_result(
@@ -344,7 +632,7 @@ pub fn quiescence_alpha_beta<T: SearchResult + Default>(
     // but quiescent won't be put in TT.
     // IF WANT TT: CHECK TT HERE
 
-    if current_depth >= max_selective_depth {
+    if current_depth >= max_selective_depth.min(MAX_PLY) {
         return T::make_search_result(
             best_move,
             EvalBound::Exact(current_evaluation),
@@ -365,7 +653,10 @@ pub fn quiescence_alpha_beta<T: SearchResult + Default>(
         // transposition_table,
         already_found_move,
         &mut move_gen,
+        &[],
         true,
+        None,
+        current_depth,
     );
 
     if moves.is_empty() {
@@ -399,8 +690,13 @@ pub fn quiescence_alpha_beta<T: SearchResult + Default>(
                 beta,
                 current_depth + 1,
                 max_selective_depth,
+                control,
             );
+            if control.is_stopped() {
+                return search_result;
+            }
             nodes_searched += search_result.nodes_searched().unwrap_or(1);
+            deepest_ply = deepest_ply.max(reached_ply(current_depth, &search_result));
 
             if search_result.eval_bound() >= best_eval {
                 best_eval = search_result.eval_bound();
@@ -448,8 +744,13 @@ pub fn quiescence_alpha_beta<T: SearchResult + Default>(
                 beta,
                 current_depth + 1,
                 max_selective_depth - 1, // Quiescence should cut off at even depth, and we're skipping a move
+                control,
             );
+            if control.is_stopped() {
+                return search_result;
+            }
             nodes_searched += search_result.nodes_searched().unwrap_or(1);
+            deepest_ply = deepest_ply.max(reached_ply(current_depth, &search_result));
 
             if search_result.eval_bound() >= best_eval {
                 best_eval = search_result.eval_bound();
@@ -499,8 +800,13 @@ pub fn quiescence_alpha_beta<T: SearchResult + Default>(
                 beta,
                 current_depth + 1,
                 max_selective_depth,
+                control,
             );
+            if control.is_stopped() {
+                return search_result;
+            }
             nodes_searched += search_result.nodes_searched().unwrap_or(1);
+            deepest_ply = deepest_ply.max(reached_ply(current_depth, &search_result));
 
             if search_result.eval_bound() <= best_eval {
                 best_eval = search_result.eval_bound();
@@ -545,8 +851,13 @@ pub fn quiescence_alpha_beta<T: SearchResult + Default>(
                 beta,
                 current_depth + 1,
                 max_selective_depth - 1, // Quiescence should cut off at even depth, and we're skipping a move
+                control,
             );
+            if control.is_stopped() {
+                return search_result;
+            }
             nodes_searched += search_result.nodes_searched().unwrap_or(1);
+            deepest_ply = deepest_ply.max(reached_ply(current_depth, &search_result));
 
             if search_result.eval_bound() <= best_eval {
                 best_eval = search_result.eval_bound();
@@ -597,6 +908,7 @@ pub fn quiescence_alpha_beta<T: SearchResult + Default>(
     //     best_move,
     // );
     best_search_result.set_nodes_searched(Some(nodes_searched));
+    best_search_result.set_selective_depth(Some(deepest_ply));
     best_search_result.set_best_move(best_move);
     best_search_result.set_eval_bound(eval_bound);
     best_search_result
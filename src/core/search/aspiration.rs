@@ -0,0 +1,161 @@
+// Aspiration windows: every iteration is first searched with a narrow window around the score of the one before,
+// and searched again with a wider window whenever its score falls outside.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use chess::Board;
+use crate::analysis::database::rows::{MTSearchRow, PositionSearchRow};
+use crate::core::score::{BoardEvaluation, Centipawns};
+use crate::core::search::alpha_beta::SearchWindow;
+use crate::core::search::iterative_deepening::WindowSearchFn;
+use crate::core::search::search_control::SearchControl;
+use crate::core::search::search_result::SearchResult;
+use crate::core::search::transpositions::{EvalBound, TranspositionTable};
+
+/// A side of the window that would get wider than this is opened up to mate instead
+pub const MAX_ASPIRATION_WIDTH: Centipawns = Centipawns::new(1000);
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct AspirationWindows {
+    pub width: Centipawns, // On either side of the previous score
+    pub widening: u32, // What the side the score fell outside of is multiplied by, at least 2
+}
+
+impl Default for AspirationWindows {
+    fn default() -> Self {
+        AspirationWindows {
+            width: Centipawns::new(AspirationWindows::DEFAULT_WIDTH as i64),
+            widening: AspirationWindows::DEFAULT_WIDENING,
+        }
+    }
+}
+
+impl AspirationWindows {
+    pub const DEFAULT_WIDTH: u32 = 50;
+    pub const DEFAULT_WIDENING: u32 = 2;
+
+    /// The side of the window after `evaluation` fell outside it; `None` is open up to mate, which a mate always gets
+    fn widen(&self, width: Option<Centipawns>, evaluation: BoardEvaluation) -> Option<Centipawns> {
+        if !matches!(evaluation, BoardEvaluation::PieceScore(_)) {
+            return None;
+        }
+
+        width
+            .map(|x| Centipawns::new(x.0.max(1) * self.widening.max(2) as i64))
+            .filter(|x| *x <= MAX_ASPIRATION_WIDTH)
+    }
+}
+
+/// An iteration of iterative deepening, searched with aspiration windows
+#[derive(Copy, Clone, Debug)]
+pub struct AspirationIteration {
+    pub depth: u32,
+    pub previous_evaluation: BoardEvaluation, // The score of the iteration before, which the windows are around
+    pub aspiration: AspirationWindows,
+}
+
+/// Searches to `depth` with windows around `previous_evaluation`, until the score falls inside one.
+/// Every search is logged as an `MTSearchRow`, where the eval bound tells whether it failed low or high.
+pub fn aspiration_search<T: SearchResult + Default>(
+    board: &Board,
+    transposition_table: &mut Box<dyn TranspositionTable>,
+    visited_boards: Vec<u64>,
+    iteration: AspirationIteration,
+    window_search: WindowSearchFn<T>,
+    control: &SearchControl,
+) -> (T, Vec<MTSearchRow>, PositionSearchRow) {
+    let AspirationIteration { depth, previous_evaluation, aspiration } = iteration;
+    let total_search_time = SystemTime::now();
+    let mut mt_searches = vec![];
+    let mut mt_search_num = 0;
+
+    let (mut lower_width, mut upper_width) = match previous_evaluation {
+        BoardEvaluation::PieceScore(_) => (Some(aspiration.width), Some(aspiration.width)),
+        _ => (None, None), // Mates aren't searched with a window
+    };
+
+    let mut nodes_searched = 0;
+    let mut selective_depth = 0; // The deepest ply of all the searches
+    let mut result: T = loop {
+        let alpha = lower_width.map_or(BoardEvaluation::BlackMate(0), |x| previous_evaluation.change_centipawns(-x));
+        let beta = upper_width.map_or(BoardEvaluation::WhiteMate(0), |x| previous_evaluation.change_centipawns(x));
+
+        let time = SystemTime::now();
+        let (result, _): (T, PositionSearchRow) = window_search(
+            board,
+            transposition_table,
+            visited_boards.clone(),
+            SearchWindow {
+                depth,
+                selective_depth: None,
+                alpha: EvalBound::Exact(alpha),
+                beta: EvalBound::Exact(beta),
+            },
+            control,
+        );
+        nodes_searched += result.nodes_searched().unwrap_or(1);
+        selective_depth = selective_depth.max(result.selective_depth().unwrap_or(depth));
+        if control.is_stopped() {
+            break result;
+        }
+
+        // Outside the window, the score only bounds the real one
+        let evaluation = result.eval_bound().board_evaluation();
+        let eval_bound = if evaluation < alpha {
+            EvalBound::UpperBound(evaluation)
+        } else if evaluation > beta {
+            EvalBound::LowerBound(evaluation)
+        } else {
+            EvalBound::Exact(evaluation)
+        };
+
+        mt_searches.push(MTSearchRow {
+            position_search_id: 0, // THIS NEEDS TO BE OVERWRITTEN ON POSITION_SEARCH INSERT
+            test_value: previous_evaluation,
+            time_taken: time.elapsed().expect("time went backwards").as_millis() as u32,
+            nodes_evaluated: result.nodes_searched().unwrap_or(0),
+            eval_bound,
+            conspiracy_counter: None,
+            search_num: mt_search_num,
+            window: Some((alpha, beta)),
+            timestamp: time.duration_since(UNIX_EPOCH).expect("time went backwards").as_secs() as i64,
+        });
+        mt_search_num += 1;
+
+        match eval_bound {
+            EvalBound::UpperBound(_) => lower_width = aspiration.widen(lower_width, evaluation),
+            EvalBound::LowerBound(_) => upper_width = aspiration.widen(upper_width, evaluation),
+            EvalBound::Exact(_) => break result,
+        }
+    };
+    result.set_nodes_searched(Some(nodes_searched));
+    result.set_selective_depth(Some(selective_depth));
+
+    let position_search = PositionSearchRow {
+        run_id: 0, // NEEDS TO BE CHANGED HIGHER UP
+        uci_position: "".to_string(), // NEEDS TO BE CHANGED HIGHER UP
+        depth,
+        time_taken: total_search_time.elapsed().unwrap_or(Duration::from_secs(0)).as_millis() as u32,
+        nodes_evaluated: nodes_searched,
+        evaluation: result.eval_bound().board_evaluation(),
+        conspiracy_counter: None,
+        move_num: 0, // NEEDS TO BE CHANGED HIGHER UP
+        timestamp: total_search_time.duration_since(UNIX_EPOCH).unwrap_or(Duration::from_secs(0)).as_secs() as i64,
+    };
+
+    (
+        result,
+        mt_searches,
+        position_search,
+    )
+}
+
+#[test]
+fn check_widen() {
+    let aspiration = AspirationWindows { width: Centipawns::new(50), widening: 4 };
+    let score = BoardEvaluation::PieceScore(Centipawns::new(120));
+
+    assert_eq!(aspiration.widen(Some(Centipawns::new(50)), score), Some(Centipawns::new(200)));
+    assert_eq!(aspiration.widen(Some(Centipawns::new(400)), score), None);
+    assert_eq!(aspiration.widen(Some(Centipawns::new(50)), BoardEvaluation::WhiteMate(3)), None);
+    assert_eq!(aspiration.widen(None, score), None);
+}
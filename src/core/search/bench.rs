@@ -0,0 +1,89 @@
+// A fixed search over fixed positions: its node count changes with every change to the search.
+
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use chess::Board;
+use crate::analysis::mtd_h_utils::MtdHParams;
+use crate::core::search::info::InfoCallback;
+use crate::core::search::search_control::SearchControl;
+use crate::core::search::search_position;
+use crate::core::search::searcher::SearchSetup;
+use crate::core::search::transpositions::TranspositionTable;
+use crate::input::engine_options::EngineOptions;
+use crate::input::protocol_interpreter::CalculateOptions;
+
+pub const BENCH_DEPTH: u32 = 5;
+
+pub const BENCH_POSITIONS: [&str; 8] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+    "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    "r1bqkbnr/pppp1ppp/2n5/4p3/3PP3/5N2/PPP2PPP/RNBQKB1R b KQkq - 0 3",
+    "6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "8/8/4k3/8/8/3PK3/8/8 w - - 0 1",
+];
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct BenchResult {
+    pub nodes: u64,
+    pub time: Duration,
+}
+
+impl BenchResult {
+    pub fn nodes_per_second(&self) -> u64 {
+        match self.time.as_millis() {
+            0 => 0,
+            millis => (self.nodes as u128 * 1000 / millis) as u64,
+        }
+    }
+}
+
+/// Searches every bench position to `depth` with the algorithm of the `engine_options`.
+/// Every position gets a new table from `transposition_table`, so the positions don't influence each other.
+/// It searches on one thread whatever the `Threads`, as helper threads make the node count differ from run to run.
+pub fn bench<F>(
+    depth: u32,
+    engine_options: &EngineOptions,
+    mtd_h_params: &[MtdHParams],
+    transposition_table: F,
+) -> BenchResult where
+    F: Fn() -> Box<dyn TranspositionTable> {
+    let engine_options = &EngineOptions {
+        threads: 1,
+        ..engine_options.clone()
+    };
+    let start = Instant::now();
+    let mut nodes = 0;
+
+    for fen in BENCH_POSITIONS {
+        let board = Board::from_str(fen).expect("invalid bench position");
+        // Only the totals are reported
+        let control = SearchControl::new()
+            .with_info_callback(Some(InfoCallback::new(|_| {})))
+            .with_multi_pv(1)
+            .with_pruning(engine_options.pruning_options())
+            .with_extensions(engine_options.extension_options())
+            .with_aspiration(engine_options.aspiration_options());
+
+        search_position(
+            &board,
+            &mut transposition_table(),
+            vec![],
+            CalculateOptions::depth(depth),
+            SearchSetup {
+                engine_options,
+                mtd_h_params,
+            },
+            |_, _| {},
+            &control,
+        );
+        nodes += control.nodes_searched();
+    }
+
+    BenchResult {
+        nodes,
+        time: start.elapsed(),
+    }
+}
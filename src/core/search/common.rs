@@ -1,13 +1,14 @@
 use chess::{Board, BoardStatus, ChessMove, Color};
 use crate::core::score::{BoardEvaluation, Centipawns};
 use crate::core::search::draw_detection::detect_draw_incremental;
+use crate::core::search::position_history::PositionHistory;
 use crate::core::search::search_result::SearchResult;
 use crate::core::search::transpositions::EvalBound;
 
 pub fn check_game_over<T: SearchResult>(
     board: &Board,
     board_status: BoardStatus,
-    visited_boards: &Vec<u64>,
+    position_history: &PositionHistory,
 ) -> Option<T> {
     if board_status == BoardStatus::Checkmate {
         return Some(T::make_search_result(
@@ -32,7 +33,7 @@ pub fn check_game_over<T: SearchResult>(
         ));
     }
 
-    if detect_draw_incremental(visited_boards) {
+    if detect_draw_incremental(position_history) {
         return Some(T::make_search_result(
             ChessMove::default(),
             EvalBound::Exact(BoardEvaluation::PieceScore(Centipawns::new(0))),
@@ -1,26 +1,30 @@
 use std::time::Duration;
-use chess::Color;
+use chess::Board;
 use crate::core::search::conspiracy_counter::ConspiracyCounter;
+use crate::core::search::search_control::SearchControl;
 use crate::core::search::search_result::SearchResult;
+use crate::debug_log;
 
 pub mod mt_w_conspiracy;
 pub mod mtd_w_conspiracy;
 pub mod merging;
 
 pub fn log_info_search_results<T: SearchResult>(
-    search_result: &T,
-    side_to_move: Color,
+    search_results: &[T],
+    board: &Board,
     duration: Duration,
     depth: u32,
     selective_depth: u32,
     conspiracy_counter: &ConspiracyCounter,
+    control: &SearchControl,
 ) {
     super::iterative_deepening::log_info_search_results(
-        search_result,
-        side_to_move,
+        search_results,
+        board,
         duration,
         depth,
         selective_depth,
+        control,
     );
 
     log_conspiracy_counter(conspiracy_counter);
@@ -28,5 +32,5 @@ pub fn log_info_search_results<T: SearchResult>(
 
 fn log_conspiracy_counter(conspiracy_counter: &ConspiracyCounter) {
     // Just the debug representation for now
-    println!("{:?}", conspiracy_counter);
+    debug_log!("{:?}", conspiracy_counter);
 }
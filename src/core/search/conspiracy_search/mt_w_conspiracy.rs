@@ -5,8 +5,12 @@ use crate::core::evaluation::incremental::incremental_evaluation;
 use crate::core::score::{BoardEvaluation, Centipawns};
 use crate::core::search::common::check_game_over;
 use crate::core::search::conspiracy_counter::ConspiracyCounter;
+use crate::core::search::extensions::{ExtensionState, MAX_PLY};
 use crate::core::search::move_ordering::order_moves;
-use crate::core::search::search_result::SearchResult;
+use crate::core::search::position_history::PositionHistory;
+use crate::core::search::pruning::{is_late_move_test_fail_high, is_null_move_test_cutoff};
+use crate::core::search::search_control::SearchControl;
+use crate::core::search::search_result::{reached_ply, SearchResult};
 use crate::core::search::SearchDepth;
 use crate::core::search::transpositions::{EvalBound, TranspositionTable};
 
@@ -18,25 +22,34 @@ pub fn search_mt_w_conspiracy<T: SearchResult + Default + Clone> (
     board: &Board,
     // transposition_table: &mut impl TranspositionTable,
     transposition_table: &mut Box<dyn TranspositionTable>,
-    mut visited_boards: Vec<u64>,
+    position_history: &mut PositionHistory, // Ends with `board`
     simple_evaluation: Centipawns,
     test_value: EvalBound, // The value to test
     current_depth: u32,
     max_depth: u32,
-    // max_selective_depth: u32,
     bucket_size: u32,
     num_buckets: usize,
+    extension: ExtensionState, // What the line has been extended by so far
+    null_move_allowed: bool, // False right after a null move
+    control: &SearchControl,
 ) -> (T, ConspiracyCounter) {
+    control.count_node();
+    if control.is_stopped() {
+        return (T::default(), ConspiracyCounter::new(bucket_size, num_buckets));
+    }
+
     let mut test_value = test_value;
 
     let mut nodes_searched: u32 = 1;
+    let mut deepest_ply = current_depth;
 
+    let castling = position_history.castling();
+    let castles = castling.castles(board);
     let mut move_gen = MoveGen::new_legal(board);
-    let board_status = game_status(board, move_gen.len() != 0);
+    let board_status = game_status(board, move_gen.len() != 0 || !castles.is_empty());
 
-    let been_here_before = visited_boards.contains(&board.get_hash());
-    visited_boards.push(board.get_hash());
-    if let Some(search_result) = check_game_over::<T>(board, board_status, &visited_boards) {
+    let been_here_before = position_history.repetitions() > 1;
+    if let Some(search_result) = check_game_over::<T>(board, board_status, position_history) {
         let search_eval = search_result.eval_bound().board_evaluation();
         return (search_result, ConspiracyCounter::from_terminal_node(bucket_size, num_buckets, search_eval));
     }
@@ -51,7 +64,7 @@ pub fn search_mt_w_conspiracy<T: SearchResult + Default + Clone> (
         // We don't want to find a TT value if this position has already been played.
         // Prevents moving upper- and lowerbounds on checkmates to infinity.
         // And possibly helps with draw detection.
-        if solution.depth_searched >= SearchDepth::Depth(max_depth - current_depth) && !been_here_before {
+        if solution.depth_searched >= SearchDepth::Depth(max_depth - current_depth) && !been_here_before && !control.is_restricted_root(current_depth) {
             // CAN BE FALSE: even though seems like would always be true
             // solution.evaluation > test_value || solution.evaluation < test_value || solution.evaluation == test_value
             // EvalBound is PartialOrd, but NOT Ord
@@ -90,9 +103,9 @@ pub fn search_mt_w_conspiracy<T: SearchResult + Default + Clone> (
         }
     }
 
-    if current_depth >= max_depth {
+    if current_depth >= max_depth || current_depth >= MAX_PLY {
         // TODO: if want to add in quiescence search add that in
-        let current_evaluation = BoardEvaluation::PieceScore(simple_evaluation);
+        let current_evaluation = control.evaluate_leaf(board, board_status, simple_evaluation);
 
         return (
             T::make_search_result(
@@ -106,11 +119,76 @@ pub fn search_mt_w_conspiracy<T: SearchResult + Default + Clone> (
         );
     }
 
-    let all_moves = order_moves(
-        board,
-        transposition_move,
-        &mut move_gen,
-        false,
+    if let (true, Some(null_move_depth), Some(null_board)) = (
+        null_move_allowed,
+        control.pruning().null_move_depth(board, current_depth, max_depth),
+        board.null_move(),
+    ) {
+        control.with_ordering(|ordering| ordering.play_move(current_depth, None));
+        position_history.push_null(&null_board);
+        let (null_result, _): (T, ConspiracyCounter) = search_mt_w_conspiracy(
+            &null_board,
+            transposition_table,
+            position_history,
+            simple_evaluation,
+            test_value,
+            current_depth + 1,
+            null_move_depth,
+            bucket_size,
+            num_buckets,
+            extension.after_null_move(),
+            false,
+            control,
+        );
+        position_history.pop();
+        if control.is_stopped() {
+            return (null_result, ConspiracyCounter::new(bucket_size, num_buckets));
+        }
+        nodes_searched += null_result.nodes_searched().unwrap_or(1);
+        deepest_ply = deepest_ply.max(reached_ply(current_depth, &null_result));
+
+        if is_null_move_test_cutoff(board.side_to_move(), null_result.eval_bound(), test_value) {
+            // Passing could be the only move that doesn't lose: only trust the cutoff if a real move gets it too.
+            // The pruned node conspires like the verification search.
+            let (mut verification_result, verification_counter): (T, ConspiracyCounter) = search_mt_w_conspiracy(
+                board,
+                transposition_table,
+                position_history,
+                simple_evaluation,
+                test_value,
+                current_depth,
+                null_move_depth,
+                bucket_size,
+                num_buckets,
+                extension,
+                false,
+                control,
+            );
+            if control.is_stopped() {
+                return (verification_result, verification_counter);
+            }
+            nodes_searched += verification_result.nodes_searched().unwrap_or(1);
+            deepest_ply = deepest_ply.max(reached_ply(current_depth, &verification_result));
+
+            if is_null_move_test_cutoff(board.side_to_move(), verification_result.eval_bound(), test_value) {
+                verification_result.set_nodes_searched(Some(nodes_searched));
+                verification_result.set_selective_depth(Some(deepest_ply));
+                return (verification_result, verification_counter);
+            }
+        }
+    }
+
+    let all_moves = control.restrict_root_moves(
+        control.with_ordering(|ordering| order_moves(
+            board,
+            transposition_move,
+            &mut move_gen,
+            &castles,
+            false,
+            Some(ordering),
+            current_depth,
+        )),
+        current_depth,
     );
 
     let mut best_eval: EvalBound;
@@ -124,8 +202,12 @@ pub fn search_mt_w_conspiracy<T: SearchResult + Default + Clone> (
     let mut best_move = ChessMove::default();
     let mut best_search_result= T::default();
     let mut conspiracy_counter = None;
-    for chess_move in all_moves.into_iter() {
-        let new_board = &board.make_move_new(chess_move);
+    for (move_index, chess_move) in all_moves.into_iter().enumerate() {
+        let new_board = &castling.make_move(board, chess_move);
+        position_history.push(new_board);
+        let late_move_depth = control.pruning().late_move_depth(board, new_board, chess_move, move_index, current_depth, max_depth);
+        let (extension_plies, new_extension) = control.extensions().extend(board, new_board, chess_move, extension, max_depth);
+        control.with_ordering(|ordering| ordering.play_move(current_depth, Some(chess_move)));
         let improvement = incremental_evaluation(
             &board,
             &chess_move,
@@ -136,18 +218,52 @@ pub fn search_mt_w_conspiracy<T: SearchResult + Default + Clone> (
             let mut new_test_value = test_value.clone();
             new_test_value.set_board_evaluation(unbubble_evaluation(new_test_value.board_evaluation()));
 
-            let (search_result, counter_result): (T, ConspiracyCounter) = search_mt_w_conspiracy(
+            // Late quiet moves are first probed shallower, and only searched fully if they fail high there
+            let late_move_probe: Option<(T, ConspiracyCounter)> = late_move_depth.map(|reduced_depth| search_mt_w_conspiracy(
                 new_board,
                 transposition_table,
-                visited_boards.clone(),
-                simple_evaluation + improvement,  // + because white
+                position_history,
+                simple_evaluation + improvement,
                 new_test_value,
                 current_depth + 1,
-                max_depth,
-                // max_selective_depth,
+                reduced_depth + extension_plies,
                 bucket_size,
                 num_buckets,
-            );
+                new_extension,
+                true,
+                control,
+            ));
+            if let Some(probe_result) = &late_move_probe {
+                deepest_ply = deepest_ply.max(reached_ply(current_depth, &probe_result.0));
+            }
+
+            let (search_result, counter_result): (T, ConspiracyCounter) = match late_move_probe {
+                Some(probe_result) if control.is_stopped()
+                    || !is_late_move_test_fail_high(Color::White, probe_result.0.eval_bound(), test_value) => probe_result,
+                late_move_probe => {
+                    nodes_searched += late_move_probe.and_then(|x| x.0.nodes_searched()).unwrap_or(0);
+
+                    search_mt_w_conspiracy(
+                        new_board,
+                        transposition_table,
+                        position_history,
+                        simple_evaluation + improvement,  // + because white
+                        new_test_value,
+                        current_depth + 1,
+                        max_depth + extension_plies,
+                        bucket_size,
+                        num_buckets,
+                        new_extension,
+                        true,
+                        control,
+                    )
+                },
+            };
+            position_history.pop();
+            if control.is_stopped() {
+                // The search was interrupted: the partial result is discarded higher up
+                return (search_result, counter_result);
+            }
 
             // Update the Conspiracy Counter
             if conspiracy_counter.is_none() {
@@ -173,45 +289,82 @@ pub fn search_mt_w_conspiracy<T: SearchResult + Default + Clone> (
                 best_search_result.prepend_move(chess_move);
             }
             nodes_searched += search_result.nodes_searched().unwrap_or(1);
+            deepest_ply = deepest_ply.max(reached_ply(current_depth, &search_result));
 
             if best_eval > test_value && best_eval.board_evaluation() > test_value.board_evaluation() {
                 let eval_bound = EvalBound::LowerBound(best_eval.board_evaluation());
+                control.with_ordering(|ordering| ordering.record_cutoff(board, best_move, current_depth, max_depth - current_depth));
 
-                transposition_table.update(
-                    board,
-                    SearchDepth::Depth(max_depth - current_depth),
-                    eval_bound,
+                if !control.is_restricted_root(current_depth) {
+                    transposition_table.update(
+                        board,
+                        SearchDepth::Depth(max_depth - current_depth),
+                        eval_bound,
+                        best_move,
+                        best_search_result.critical_path(),
+                    );
+                }
+
+                // println!("returning {:?}", best_eval);
+                let mut search_result = T::make_search_result(
                     best_move,
+                    EvalBound::LowerBound(eval_bound.board_evaluation()),
+                    Some(nodes_searched),
                     best_search_result.critical_path(),
                 );
-
-                // println!("returning {:?}", best_eval);
-                return (
-                    T::make_search_result(
-                        best_move,
-                        EvalBound::LowerBound(eval_bound.board_evaluation()),
-                        Some(nodes_searched),
-                        best_search_result.critical_path(),
-                    ),
-                    conspiracy_counter.unwrap(),
-                )
+                search_result.set_selective_depth(Some(deepest_ply));
+                return (search_result, conspiracy_counter.unwrap());
             }
         } else { // Black to move
             let mut new_test_value = test_value.clone();
             new_test_value.set_board_evaluation(unbubble_evaluation(new_test_value.board_evaluation()));
 
-            let (search_result, counter_result): (T, ConspiracyCounter) = search_mt_w_conspiracy(
+            // Late quiet moves are first probed shallower, and only searched fully if they fail high there
+            let late_move_probe: Option<(T, ConspiracyCounter)> = late_move_depth.map(|reduced_depth| search_mt_w_conspiracy(
                 new_board,
                 transposition_table,
-                visited_boards.clone(),
-                simple_evaluation - improvement,  // - because black
+                position_history,
+                simple_evaluation - improvement,
                 new_test_value,
                 current_depth + 1,
-                max_depth,
-                // max_selective_depth,
+                reduced_depth + extension_plies,
                 bucket_size,
                 num_buckets,
-            );
+                new_extension,
+                true,
+                control,
+            ));
+            if let Some(probe_result) = &late_move_probe {
+                deepest_ply = deepest_ply.max(reached_ply(current_depth, &probe_result.0));
+            }
+
+            let (search_result, counter_result): (T, ConspiracyCounter) = match late_move_probe {
+                Some(probe_result) if control.is_stopped()
+                    || !is_late_move_test_fail_high(Color::Black, probe_result.0.eval_bound(), test_value) => probe_result,
+                late_move_probe => {
+                    nodes_searched += late_move_probe.and_then(|x| x.0.nodes_searched()).unwrap_or(0);
+
+                    search_mt_w_conspiracy(
+                        new_board,
+                        transposition_table,
+                        position_history,
+                        simple_evaluation - improvement,  // - because black
+                        new_test_value,
+                        current_depth + 1,
+                        max_depth + extension_plies,
+                        bucket_size,
+                        num_buckets,
+                        new_extension,
+                        true,
+                        control,
+                    )
+                },
+            };
+            position_history.pop();
+            if control.is_stopped() {
+                // The search was interrupted: the partial result is discarded higher up
+                return (search_result, counter_result);
+            }
 
             if conspiracy_counter.is_none() {
                 conspiracy_counter = Some(counter_result);
@@ -236,27 +389,30 @@ pub fn search_mt_w_conspiracy<T: SearchResult + Default + Clone> (
                 best_search_result.prepend_move(chess_move);
             }
             nodes_searched += search_result.nodes_searched().unwrap_or(1);
+            deepest_ply = deepest_ply.max(reached_ply(current_depth, &search_result));
 
             if best_eval < test_value && best_eval.board_evaluation() < test_value.board_evaluation() {
                 let eval_bound = EvalBound::UpperBound(best_eval.board_evaluation());
+                control.with_ordering(|ordering| ordering.record_cutoff(board, best_move, current_depth, max_depth - current_depth));
 
-                transposition_table.update(
-                    board,
-                    SearchDepth::Depth(max_depth - current_depth),
-                    EvalBound::UpperBound(eval_bound.board_evaluation()),
-                    best_move,
-                    best_search_result.critical_path(),
-                );
-
-                return (
-                        T::make_search_result(
+                if !control.is_restricted_root(current_depth) {
+                    transposition_table.update(
+                        board,
+                        SearchDepth::Depth(max_depth - current_depth),
+                        EvalBound::UpperBound(eval_bound.board_evaluation()),
                         best_move,
-                        eval_bound,
-                        Some(nodes_searched),
                         best_search_result.critical_path(),
-                    ),
-                    conspiracy_counter.unwrap()
+                    );
+                }
+
+                let mut search_result = T::make_search_result(
+                    best_move,
+                    eval_bound,
+                    Some(nodes_searched),
+                    best_search_result.critical_path(),
                 );
+                search_result.set_selective_depth(Some(deepest_ply));
+                return (search_result, conspiracy_counter.unwrap());
             }
         }
     }
@@ -270,22 +426,23 @@ pub fn search_mt_w_conspiracy<T: SearchResult + Default + Clone> (
         eval_bound = EvalBound::Exact(best_eval.board_evaluation());
     }
 
-    transposition_table.update(
-        board,
-        SearchDepth::Depth(max_depth - current_depth),
-        eval_bound,
+    if !control.is_restricted_root(current_depth) {
+        transposition_table.update(
+            board,
+            SearchDepth::Depth(max_depth - current_depth),
+            eval_bound,
+            best_move,
+            best_search_result.critical_path(),
+        );
+    }
+
+    let mut search_result = T::make_search_result(
         best_move,
+        eval_bound,
+        Some(nodes_searched),
         best_search_result.critical_path(),
     );
-
-    (
-        T::make_search_result(
-            best_move,
-            eval_bound,
-            Some(nodes_searched),
-            best_search_result.critical_path(),
-        ),
-        conspiracy_counter.unwrap()
-    )
+    search_result.set_selective_depth(Some(deepest_ply));
+    (search_result, conspiracy_counter.unwrap())
 }
 
@@ -3,17 +3,21 @@ use std::time::{Instant, Duration, SystemTime, UNIX_EPOCH};
 use chess::{Board, ChessMove, Color};
 use sqlx::SqlitePool;
 use crate::analysis::database::rows::{MTSearchRow, PositionSearchRow};
-use crate::core::evaluation::single_evaluation;
 use crate::core::score::{BoardEvaluation, Centipawns};
 use crate::core::search::conspiracy_counter::ConspiracyCounter;
 use crate::core::search::conspiracy_search::log_info_search_results;
 use crate::core::search::conspiracy_search::merging::MergeFn;
 use crate::core::search::conspiracy_search::mt_w_conspiracy::search_mt_w_conspiracy;
-use crate::core::search::iterative_deepening::is_still_searching;
+use crate::core::search::extensions::ExtensionState;
+use crate::core::search::iterative_deepening::{determine_critical_path_string, first_depth, is_still_searching, selective_depth};
 use crate::core::search::mt::search_mt;
 use crate::core::search::mtdf::mtdf_search;
-use crate::core::search::search_result::SearchResult;
+use crate::core::search::multi_pv::search_multi_pv;
+use crate::core::search::position_history::PositionHistory;
+use crate::core::search::search_control::SearchControl;
+use crate::core::search::search_result::{with_selective_depth, SearchResult};
 use crate::core::search::transpositions::{EvalBound, TranspositionTable};
+use crate::debug_log;
 use crate::input::protocol_interpreter::CalculateOptions;
 
 /// The base implementation of the mtd framework but with conspiracy counters
@@ -30,27 +34,30 @@ pub fn mtd_iterative_deepening_search<T: SearchResult + Default + Clone, L>(
     num_buckets: usize,
     conspiracy_merge_fn: MergeFn,
     search_logging: L,
+    control: &SearchControl,
 ) -> (T, ConspiracyCounter, u32, u32) where
     L: Fn(PositionSearchRow, Vec<MTSearchRow>) { // (SearchResult, ConspiracyCounter, depth, selective_depth)
     let now = Instant::now();
-    let mut current_depth = 2;
+    let start_depth = first_depth(&options, control);
+    let mut current_depth = start_depth + 1;
     let first_result: (T, ConspiracyCounter, Vec<MTSearchRow>, PositionSearchRow) = mtd_search(
     // let mut search_result: T = mtd_search(
         board,
         transposition_table,
         visited_boards.clone(),
-        1,
+        start_depth,
         BoardEvaluation::PieceScore(Centipawns::new(0)),
         step_fn.clone(),
         bucket_size,
         num_buckets,
         conspiracy_merge_fn,
+        control,
     );
     let mut search_result = first_result.0;
     let mut conspiracy_counter = first_result.1;
     search_logging(first_result.3, first_result.2);
 
-    while is_still_searching(options, board, now, current_depth) {
+    while is_still_searching(&options, board, control, current_depth, &search_result) {
         let temp_search_result = mtd_search(
             board,
             transposition_table,
@@ -61,30 +68,52 @@ pub fn mtd_iterative_deepening_search<T: SearchResult + Default + Clone, L>(
             bucket_size,
             num_buckets,
             conspiracy_merge_fn,
+            control,
         );
+        if control.is_stopped() {
+            // Keep the result of the last completed iteration
+            break;
+        }
         search_result = temp_search_result.0;
         conspiracy_counter = temp_search_result.1;
 
         // OPTIONAL LOGGING TO DB
         search_logging(temp_search_result.3, temp_search_result.2);
 
+        let lines = search_multi_pv(board, control, search_result.clone(), |line_control| {
+            mtd_search::<T>(
+                board,
+                transposition_table,
+                visited_boards.clone(),
+                current_depth,
+                search_result.eval_bound().board_evaluation(),
+                step_fn,
+                bucket_size,
+                num_buckets,
+                conspiracy_merge_fn,
+                line_control,
+            ).0
+        });
+
         let duration = now.elapsed();
         log_info_search_results(
-            &search_result,
-            board.side_to_move(),
+            &lines,
+            board,
             duration,
             current_depth,
-            current_depth, // TODO: Change to actual selective depth
+            selective_depth(&search_result, current_depth),
             &conspiracy_counter,
+            control,
         );
         current_depth += 1;
     }
 
+    let selective_depth = selective_depth(&search_result, current_depth);
     (
         search_result,
         conspiracy_counter,
         current_depth,
-        current_depth,
+        selective_depth,
     )
 }
 
@@ -100,9 +129,10 @@ pub fn mtd_search<T: SearchResult + Default + Clone>(
     bucket_size: u32,
     num_buckets: usize,
     conspiracy_merge_fn: MergeFn,
+    control: &SearchControl,
 ) -> (T, ConspiracyCounter, Vec<MTSearchRow>, PositionSearchRow) {
     let mut current_test_value = start_point;
-    let current_evaluation = single_evaluation(board, board.status());
+    let current_evaluation = control.evaluate(board, board.status());
 
     let simple_evaluation;
     match current_evaluation {
@@ -164,24 +194,48 @@ pub fn mtd_search<T: SearchResult + Default + Clone>(
     );
     let mut conspiracy_counter = None;
     let mut nodes_searched = 0;
+    let mut selective_depth = 0; // The deepest ply of all the MT searches
     // while lowerbound < upperbound {
+    let mut position_history = PositionHistory::new(&visited_boards, board).with_castling(control.castling());
     while !result.eval_bound().is_exact() {
         let time = SystemTime::now();
         let search_result = search_mt_w_conspiracy(
             board,
             transposition_table,
-            visited_boards.clone(),
+            &mut position_history,
             simple_evaluation,
             EvalBound::Exact(current_test_value),
             0,
             depth,
             bucket_size,
             num_buckets,
+            ExtensionState::default(),
+            true,
+            control,
         );
         result = search_result.0;
         let found_conspiracy_counter = search_result.1;
+        if control.is_stopped() {
+            return (
+                result,
+                found_conspiracy_counter,
+                mt_searches,
+                PositionSearchRow {
+                    run_id: 0,
+                    uci_position: "".to_string(),
+                    depth,
+                    time_taken: 0,
+                    nodes_evaluated: nodes_searched,
+                    evaluation: current_test_value,
+                    conspiracy_counter: None,
+                    move_num: 0,
+                    timestamp: 0,
+                },
+            );
+        }
 
         nodes_searched += result.nodes_searched().unwrap_or(1);
+        selective_depth = selective_depth.max(result.selective_depth().unwrap_or(depth));
 
         // Update the mt_searches log
         mt_searches.push(MTSearchRow {
@@ -192,6 +246,7 @@ pub fn mtd_search<T: SearchResult + Default + Clone>(
             eval_bound: result.eval_bound(),
             conspiracy_counter: Some(found_conspiracy_counter.clone()),
             search_num: mt_search_num,
+            window: None,
             timestamp: time.duration_since(UNIX_EPOCH).expect("time went backwards").as_secs() as i64,
         });
         mt_search_num += 1;
@@ -218,11 +273,14 @@ pub fn mtd_search<T: SearchResult + Default + Clone>(
                 };
 
                 return (
-                    T::make_search_result(
-                        result.best_move(),
-                        result.eval_bound(),
-                        Some(nodes_searched),
-                        result.critical_path(),
+                    with_selective_depth(
+                        T::make_search_result(
+                            result.best_move(),
+                            result.eval_bound(),
+                            Some(nodes_searched),
+                            result.critical_path(),
+                        ),
+                        selective_depth,
                     ),
                     conspiracy_counter.unwrap(),
                     mt_searches,
@@ -244,17 +302,14 @@ pub fn mtd_search<T: SearchResult + Default + Clone>(
 
             lowest_upperbound = min(upperbound, lowest_upperbound);
             highest_lowerbound = max(lowerbound, highest_lowerbound);
-            println!("UNSTABLE SEARCH: logging");
-            println!("lowerbound {:?}, upperbound {:?}", lowerbound, upperbound);
-            let path = result.critical_path().unwrap();
-            for chess_move in path.into_iter() {
-                print!("{} ", chess_move);
-            }
-            println!("");
-            // println!("result path: {}", result.critical_path());
-            println!("result best_move: {}", result.best_move());
-            println!("result nodes_searched: {:?}", result.nodes_searched());
-            println!("UNSTABLE SEARCH: end logging");
+            debug_log!(
+                "unstable search: lowerbound {:?}, upperbound {:?}, best_move {}, nodes {:?}, {}",
+                lowerbound,
+                upperbound,
+                result.best_move(),
+                result.nodes_searched(),
+                determine_critical_path_string(result.critical_path()),
+            );
 
             upperbound = newest_eval;
             lowerbound = newest_eval;
@@ -277,11 +332,14 @@ pub fn mtd_search<T: SearchResult + Default + Clone>(
                             timestamp: total_search_time.duration_since(UNIX_EPOCH).unwrap_or(Duration::from_secs(0)).as_secs() as i64,
                         };
                         return (
-                            T::make_search_result(
-                                result.best_move(),
-                                EvalBound::Exact(result.eval_bound().board_evaluation()),
-                                Some(nodes_searched),
-                                result.critical_path(),
+                            with_selective_depth(
+                                T::make_search_result(
+                                    result.best_move(),
+                                    EvalBound::Exact(result.eval_bound().board_evaluation()),
+                                    Some(nodes_searched),
+                                    result.critical_path(),
+                                ),
+                                selective_depth,
                             ),
                             conspiracy_counter.unwrap(),
                             mt_searches,
@@ -301,11 +359,14 @@ pub fn mtd_search<T: SearchResult + Default + Clone>(
                             timestamp: total_search_time.duration_since(UNIX_EPOCH).unwrap_or(Duration::from_secs(0)).as_secs() as i64,
                         };
                         return (
-                            T::make_search_result(
-                                result.best_move(),
-                                EvalBound::Exact(result.eval_bound().board_evaluation()),
-                                Some(nodes_searched),
-                                result.critical_path(),
+                            with_selective_depth(
+                                T::make_search_result(
+                                    result.best_move(),
+                                    EvalBound::Exact(result.eval_bound().board_evaluation()),
+                                    Some(nodes_searched),
+                                    result.critical_path(),
+                                ),
+                                selective_depth,
                             ),
                             conspiracy_counter.unwrap(),
                             mt_searches,
@@ -335,11 +396,14 @@ pub fn mtd_search<T: SearchResult + Default + Clone>(
     // Usually `lowerbound == upperbound` here
     // But not always: just return anyways
     (
-        T::make_search_result(
-            result.best_move(),
-            EvalBound::Exact(result.eval_bound().board_evaluation()),
-            Some(nodes_searched),
-            result.critical_path(),
+        with_selective_depth(
+            T::make_search_result(
+                result.best_move(),
+                EvalBound::Exact(result.eval_bound().board_evaluation()),
+                Some(nodes_searched),
+                result.critical_path(),
+            ),
+            selective_depth,
         ),
         conspiracy_counter.unwrap(),
         mt_searches,
@@ -0,0 +1,107 @@
+// Search extensions: searching forcing moves deeper than the nominal depth.
+
+use chess::{Board, ChessMove, Square, EMPTY};
+
+/// Extensions are counted in fractions of a ply, so small ones add up over a line
+pub const ONE_PLY: u32 = 4;
+/// No line is searched deeper than this, whatever the extensions
+pub const MAX_PLY: u32 = 100;
+
+/// How far to extend the forcing moves, in fractions of a `ONE_PLY`; none by default, which keeps every search exact
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct ExtensionOptions {
+    pub check: u32, // For moves that give check
+    pub recapture: u32, // For captures on the square the previous move captured on
+}
+
+/// What a line has been extended by so far
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct ExtensionState {
+    fraction: u32, // The part of a ply left over from earlier extensions
+    extended_plies: u32, // The plies added to the nominal depth
+    capture_square: Option<Square>, // Where the previous move captured
+}
+
+impl ExtensionState {
+    /// The state after a null move: passing is not a capture
+    pub fn after_null_move(&self) -> Self {
+        ExtensionState {
+            capture_square: None,
+            ..*self
+        }
+    }
+
+    pub fn extended_plies(&self) -> u32 {
+        self.extended_plies
+    }
+}
+
+impl ExtensionOptions {
+    pub fn is_enabled(&self) -> bool {
+        self.check > 0 || self.recapture > 0
+    }
+
+    /// The plies to extend `chess_move` by, and the state to search `new_board` with.
+    /// A line is never extended by more plies than its nominal depth, nor beyond `MAX_PLY`.
+    pub fn extend(
+        &self,
+        board: &Board,
+        new_board: &Board,
+        chess_move: ChessMove,
+        state: ExtensionState,
+        max_depth: u32,
+    ) -> (u32, ExtensionState) {
+        let is_capture = board.color_on(chess_move.get_dest()) == Some(!board.side_to_move());
+        let mut extension = 0;
+        if *new_board.checkers() != EMPTY {
+            extension += self.check;
+        }
+        if is_capture && state.capture_square == Some(chess_move.get_dest()) {
+            extension += self.recapture;
+        }
+
+        let fraction = state.fraction + extension;
+        let nominal_depth = max_depth.saturating_sub(state.extended_plies);
+        let plies = (fraction / ONE_PLY)
+            .min(nominal_depth.saturating_sub(state.extended_plies))
+            .min(MAX_PLY.saturating_sub(max_depth));
+
+        let new_state = ExtensionState {
+            fraction: fraction % ONE_PLY,
+            extended_plies: state.extended_plies + plies,
+            capture_square: is_capture.then(|| chess_move.get_dest()),
+        };
+
+        (plies, new_state)
+    }
+}
+
+#[test]
+fn check_extend() {
+    use std::str::FromStr;
+
+    let extensions = ExtensionOptions { check: ONE_PLY, recapture: ONE_PLY / 2 };
+    let extend = |fen: &str, chess_move: &str, state: ExtensionState, max_depth: u32| {
+        let board = Board::from_str(fen).expect(fen);
+        let chess_move = ChessMove::from_str(chess_move).unwrap();
+        extensions.extend(&board, &board.make_move_new(chess_move), chess_move, state, max_depth)
+    };
+
+    // A check is extended by a full ply, a quiet move isn't
+    let check = "4k3/8/8/8/8/8/8/R3K3 w - - 0 1";
+    assert_eq!(extend(check, "a1a8", ExtensionState::default(), 4).0, 1);
+    assert_eq!(extend(check, "a1a7", ExtensionState::default(), 4), (0, ExtensionState::default()));
+
+    // Two half-ply recaptures make up one ply
+    let recapture = "4k3/8/8/3p4/8/8/3R4/3RK3 w - - 0 1";
+    let (plies, state) = extend(recapture, "d2d5", ExtensionState { capture_square: Some(Square::D5), ..Default::default() }, 4);
+    assert_eq!((plies, state.fraction, state.capture_square), (0, 2, Some(Square::D5)));
+    let (plies, state) = extend(recapture, "d2d5", state, 4);
+    assert_eq!((plies, state.fraction, state.extended_plies), (1, 0, 1));
+
+    // Never more extended plies than nominal ones, and never beyond the max ply
+    let extended = ExtensionState { extended_plies: 2, ..Default::default() };
+    assert_eq!(extend(check, "a1a8", extended, 4).0, 0);
+    assert_eq!(extend(check, "a1a8", extended, 5).0, 1);
+    assert_eq!(extend(check, "a1a8", ExtensionState::default(), MAX_PLY).0, 0);
+}
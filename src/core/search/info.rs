@@ -0,0 +1,58 @@
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+use std::time::Duration;
+use chess::ChessMove;
+use crate::core::score::BoardEvaluation;
+use crate::core::search::search_result::SearchResult;
+
+/// What a completed iteration found, as handed to an info callback instead of being printed.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IterationInfo {
+    pub depth: u32,
+    pub selective_depth: u32,
+    pub time: Duration,
+    pub lines: Vec<InfoLine>, // The MultiPV lines, best first
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InfoLine {
+    pub evaluation: BoardEvaluation, // From white's perspective, like everywhere in the search
+    pub nodes: Option<u32>,
+    pub principal_variation: Vec<ChessMove>, // In the order the moves are played
+}
+
+impl InfoLine {
+    pub fn from_search_result<T: SearchResult>(search_result: &T) -> Self {
+        InfoLine {
+            evaluation: search_result.eval_bound().board_evaluation(),
+            nodes: search_result.nodes_searched(),
+            // The critical path is stored in reverse
+            principal_variation: search_result.critical_path()
+                .unwrap_or_default()
+                .into_iter()
+                .rev()
+                .collect(),
+        }
+    }
+}
+
+/// Receives the info of every completed iteration, on the thread running the search.
+#[derive(Clone)]
+pub struct InfoCallback(Arc<dyn Fn(&IterationInfo) + Send + Sync>);
+
+impl InfoCallback {
+    pub fn new<F>(callback: F) -> Self where
+        F: Fn(&IterationInfo) + Send + Sync + 'static {
+        InfoCallback(Arc::new(callback))
+    }
+
+    pub fn call(&self, info: &IterationInfo) {
+        (self.0)(info)
+    }
+}
+
+impl Debug for InfoCallback {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "InfoCallback")
+    }
+}
@@ -1,119 +1,230 @@
 use std::time::{Duration, Instant};
 use chess::{Board, ChessMove, Color};
+use itertools::Itertools;
 use crate::analysis::database::rows::{MTSearchRow, PositionSearchRow};
 use crate::core::score::{BoardEvaluation, Centipawns};
-use crate::core::search::alpha_beta::search_depth_pruned;
+use crate::core::search::alpha_beta::{search_window_pruned, SearchWindow};
+use crate::core::search::aspiration::{aspiration_search, AspirationIteration};
+use crate::core::search::info::{InfoLine, IterationInfo};
+use crate::core::search::multi_pv::search_multi_pv;
+use crate::core::search::search_control::SearchControl;
 use crate::core::search::search_result::SearchResult;
 
 use crate::core::search::transpositions::TranspositionTable;
-use crate::input::protocol_interpreter::CalculateOptions;
+use crate::input::protocol_interpreter::{CalculateOptions, SupportedProtocols};
+use crate::output;
 
 
-/// Determines whether the next depth should be searched
-pub fn is_still_searching(
-    calculate_options: CalculateOptions,
+/// Determines whether the next depth should be searched, after `last_result` completed.
+/// The time limits are left to the time manager of the `control`, which counts from the ponderhit when pondering.
+pub fn is_still_searching<T: SearchResult>(
+    calculate_options: &CalculateOptions,
     board: &Board,
-    search_start: Instant,
+    control: &SearchControl,
     depth_to_search: u32,
+    last_result: &T,
 ) -> bool {
-    match calculate_options {
-        CalculateOptions::Depth(x) => depth_to_search <= x,
-        CalculateOptions::Infinite => true,
-        CalculateOptions::Game {
-            white_time,
-            white_increment,
-            black_time,
-            black_increment,
-        } => {
-            match board.side_to_move() {
-                Color::White => {
-                    let already_searched = search_start.elapsed().as_millis() as u64;
-                    let extra_calc_time = 5 * already_searched + 10;
-
-                    (already_searched + extra_calc_time).saturating_sub(white_increment) < white_time / 50
-                },
-                Color::Black => {
-                    let already_searched = search_start.elapsed().as_millis() as u64;
-                    let extra_calc_time = 5 * already_searched;
+    control.update_best_move(last_result.best_move());
 
-                    (already_searched + extra_calc_time).saturating_sub(black_increment) < black_time / 50
-                },
-            }
-        },
-        CalculateOptions::MoveTime(x) => {
-            // TODO: engine actually should abort as soon as the ms passed
-            // This will go to the next depth as long as MoveTime x hasn't passed yet.
-            (search_start.elapsed().as_millis() as u64) < x
-        },
+    if calculate_options.infinite || control.is_pondering() {
+        return true;
+    }
+
+    if let Some(x) = calculate_options.depth {
+        if depth_to_search > x {
+            return false;
+        }
+    }
+
+    if let Some(x) = calculate_options.mate {
+        // A mate in `x` moves takes at most `2x - 1` plies
+        let last_evaluation = last_result.eval_bound().board_evaluation();
+        if depth_to_search > (2 * x).saturating_sub(1) || is_mate_found(x, board.side_to_move(), last_evaluation) {
+            return false;
+        }
+    }
+
+    control.has_time_for_iteration()
+}
+
+/// The depth of the first iteration: deeper for the helper threads of a parallel search, but never beyond the depth limit
+pub fn first_depth(calculate_options: &CalculateOptions, control: &SearchControl) -> u32 {
+    let first_depth = 1 + control.depth_offset();
+    match calculate_options.depth {
+        Some(x) => first_depth.min(x.max(1)),
+        None => first_depth,
+    }
+}
+
+/// Whether `evaluation` is a mate in at most `mate` moves for `side_to_move`
+fn is_mate_found(mate: u32, side_to_move: Color, evaluation: BoardEvaluation) -> bool {
+    match (side_to_move, evaluation) {
+        (Color::White, BoardEvaluation::WhiteMate(x)) => x / 2 <= mate,
+        (Color::Black, BoardEvaluation::BlackMate(x)) => x / 2 <= mate,
+        _ => false,
     }
 }
 
-pub fn iterative_deepening_search<T: SearchResult + Default, L>(
+/// A search of the whole tree within a `SearchWindow`, as `search_window_pruned`
+pub type WindowSearchFn<T> = fn(&Board, &mut Box<dyn TranspositionTable>, Vec<u64>, SearchWindow, &SearchControl) -> (T, PositionSearchRow);
+
+pub fn iterative_deepening_search<T: SearchResult + Default + Clone, L>(
     board: &Board,
     // transposition_table: &mut impl TranspositionTable,
     transposition_table: &mut Box<dyn TranspositionTable>,
     visited_boards: Vec<u64>,
     options: CalculateOptions,
     search_logging: L,
+    control: &SearchControl,
 ) -> (T, u32, u32) where
     L: Fn(PositionSearchRow, Vec<MTSearchRow>) { // (SearchResult, depth, selective_depth)
-    // let mut max_search_depth: u32 = 1;
-
-    // let selective_depth: u32 = min(10, max_search_depth); // TODO
-    // let selective_depth: u32 = 10;
-    // TODO: Set this up
-    // for max_depth in 1..max_search_depth {
-    //
+    depth_first_iterative_deepening_search(
+        board,
+        transposition_table,
+        visited_boards,
+        options,
+        search_window_pruned,
+        search_logging,
+        control,
+    )
+}
 
-    // }
+/// Iterative deepening over `window_search`, which searches every iteration from scratch, apart from the table.
+/// After the first iteration, the aspiration windows of the `control` narrow the window when set.
+pub fn depth_first_iterative_deepening_search<T: SearchResult + Default + Clone, L>(
+    board: &Board,
+    transposition_table: &mut Box<dyn TranspositionTable>,
+    visited_boards: Vec<u64>,
+    options: CalculateOptions,
+    window_search: WindowSearchFn<T>,
+    search_logging: L,
+    control: &SearchControl,
+) -> (T, u32, u32) where
+    L: Fn(PositionSearchRow, Vec<MTSearchRow>) { // (SearchResult, depth, selective_depth)
     let now = Instant::now();
-    let mut current_depth = 2;
-    let (mut search_result, position_row): (T, PositionSearchRow) = search_depth_pruned(
+    let start_depth = first_depth(&options, control);
+    let mut current_depth = start_depth + 1;
+    let (mut search_result, position_row): (T, PositionSearchRow) = window_search(
         board,
         transposition_table,
         visited_boards.clone(),
-        1,
-        None,
+        SearchWindow::full(start_depth, None),
+        control,
     );
     search_logging(position_row, vec![]);
 
-    while is_still_searching(options, board, now, current_depth) {
-        let temp_search_result = search_depth_pruned(
-            board,
-            transposition_table,
-            visited_boards.clone(),
-            current_depth,
-            None,
-        );
-        search_result = temp_search_result.0;
-        search_logging(temp_search_result.1, vec![]);
+    while is_still_searching(&options, board, control, current_depth, &search_result) {
+        let (temp_search_result, mt_searches, position_row) = match control.aspiration() {
+            Some(aspiration) => aspiration_search(
+                board,
+                transposition_table,
+                visited_boards.clone(),
+                AspirationIteration {
+                    depth: current_depth,
+                    previous_evaluation: search_result.eval_bound().board_evaluation(),
+                    aspiration,
+                },
+                window_search,
+                control,
+            ),
+            None => {
+                let (temp_search_result, position_row) = window_search(
+                    board,
+                    transposition_table,
+                    visited_boards.clone(),
+                    SearchWindow::full(current_depth, None),
+                    control,
+                );
+                (temp_search_result, vec![], position_row)
+            },
+        };
+        if control.is_stopped() {
+            // Keep the result of the last completed iteration
+            break;
+        }
+        search_result = temp_search_result;
+        search_logging(position_row, mt_searches);
+
+        let lines = search_multi_pv(board, control, search_result.clone(), |line_control| {
+            window_search(
+                board,
+                transposition_table,
+                visited_boards.clone(),
+                SearchWindow::full(current_depth, None),
+                line_control,
+            ).0
+        });
 
         let duration = now.elapsed();
         log_info_search_results(
-            &search_result,
-            board.side_to_move(),
+            &lines,
+            board,
             duration,
             current_depth,
-            current_depth, // TODO: Change to actual selective depth
+            selective_depth(&search_result, current_depth),
+            control,
         );
         current_depth += 1;
     }
 
+    let selective_depth = selective_depth(&search_result, current_depth);
     (
         search_result,
         current_depth,
-        current_depth,
+        selective_depth,
     )
 }
 
+/// The seldepth to report for `search_result`: the deepest ply it reached, and never less than the nominal `depth`
+pub fn selective_depth<T: SearchResult>(search_result: &T, depth: u32) -> u32 {
+    search_result.selective_depth().unwrap_or(depth).max(depth)
+}
+
+/// Logs the lines from `search_multi_pv`, best first, to the info callback of the `control` or else the GUI
 pub fn log_info_search_results<T: SearchResult>(
+    search_results: &[T],
+    board: &Board,
+    duration: Duration,
+    depth: u32,
+    selective_depth: u32,
+    control: &SearchControl,
+) {
+    let info = IterationInfo {
+        depth,
+        selective_depth,
+        time: duration,
+        lines: search_results.iter().map(InfoLine::from_search_result).collect(),
+    };
+    if control.report_info(&info) {
+        return;
+    }
+
+    match output::protocol() {
+        SupportedProtocols::UCI => {
+            for (index, search_result) in search_results.iter().enumerate() {
+                // The rank is only given when there are several lines
+                let multi_pv = (search_results.len() > 1).then_some(index + 1);
+                log_uci_search_results(search_result, multi_pv, board, duration, depth, selective_depth);
+            }
+        },
+        SupportedProtocols::XBoard => {
+            // xboard has no notion of several lines
+            if let Some(search_result) = search_results.first() {
+                log_xboard_search_results(search_result, board.side_to_move(), duration, depth);
+            }
+        },
+    }
+}
+
+fn log_uci_search_results<T: SearchResult>(
     search_result: &T,
-    side_to_move: Color,
+    multi_pv: Option<usize>,
+    board: &Board,
     duration: Duration,
     depth: u32,
     selective_depth: u32
 ) {
-    let score_string = match (side_to_move, search_result.eval_bound().board_evaluation()) {
+    let score_string = match (board.side_to_move(), search_result.eval_bound().board_evaluation()) {
         (Color::White, BoardEvaluation::PieceScore(Centipawns(x))) => {
             format!("cp {}", x)
         },
@@ -139,19 +250,72 @@ pub fn log_info_search_results<T: SearchResult>(
         Some(x) => format!("nodes {x}"),
     };
 
-    let critical_path_string = determine_critical_path_string(search_result.critical_path());
+    let critical_path_string = match search_result.critical_path() {
+        Some(critical_path) => {
+            let line = critical_path.into_iter().rev().collect::<Vec<_>>();
+            format!("pv {}", output::line_string(board, &line))
+        },
+        None => "".to_string(),
+    };
     let millis = duration.as_millis();
 
-    if millis > 0 && search_result.nodes_searched().is_some() {
+    if millis > 0 && search_result.nodes_searched().is_some() && multi_pv.unwrap_or(1) == 1 {
         let nodes_per_second = search_result.nodes_searched().unwrap() as u128 / duration.as_millis() * 1000;
         println!("info nps {nodes_per_second}");
     }
+
+    let multi_pv_string = match multi_pv {
+        None => "".to_string(),
+        Some(x) => format!("multipv {x} "),
+    };
     println!(
-        "info score {score_string} depth {depth} seldepth {selective_depth} {nodes_string} time {} {critical_path_string}",
+        "info {multi_pv_string}score {score_string} depth {depth} seldepth {selective_depth} {nodes_string} time {} {critical_path_string}",
         duration.as_millis(),
     );
 }
 
+/// The thinking output of xboard: `ply score time nodes pv`, with the time in centiseconds
+fn log_xboard_search_results<T: SearchResult>(
+    search_result: &T,
+    side_to_move: Color,
+    duration: Duration,
+    depth: u32,
+) {
+    if !output::is_showing_thinking() {
+        return;
+    }
+
+    let critical_path = search_result.critical_path()
+        .unwrap_or_default()
+        .into_iter()
+        .rev()
+        .map(|x| x.to_string())
+        .join(" ");
+
+    println!(
+        "{depth} {} {} {} {critical_path}",
+        determine_xboard_score(side_to_move, search_result.eval_bound().board_evaluation()),
+        duration.as_millis() / 10,
+        search_result.nodes_searched().unwrap_or(0),
+    );
+}
+
+/// The score in centipawns for `side_to_move`, where xboard reads a mate in `x` moves as 100000 + `x`
+fn determine_xboard_score(side_to_move: Color, evaluation: BoardEvaluation) -> i64 {
+    const MATE_SCORE: i64 = 100_000;
+
+    let white_score = match evaluation {
+        BoardEvaluation::PieceScore(Centipawns(x)) => x,
+        BoardEvaluation::WhiteMate(x) => MATE_SCORE + (x / 2) as i64,
+        BoardEvaluation::BlackMate(x) => -MATE_SCORE - (x / 2) as i64,
+    };
+
+    match side_to_move {
+        Color::White => white_score,
+        Color::Black => -white_score,
+    }
+}
+
 pub fn determine_critical_path_string(critical_path: Option<Vec<ChessMove>>) -> String {
     let critical_path_string;
     if critical_path.is_some() {
@@ -0,0 +1,85 @@
+// Lazy SMP: helper threads run the same iterative deepening as the main thread, at staggered depths,
+// and only cooperate through the transposition table they share.
+
+use std::thread;
+use chess::Board;
+use crate::analysis::database::rows::{MTSearchRow, PositionSearchRow};
+use crate::core::is_default_move;
+use crate::core::search::search_control::SearchControl;
+use crate::core::search::search_result::debug_search_result::DebugSearchResult;
+use crate::core::search::search_result::SearchResult;
+use crate::core::search::searcher::{SearchOutput, SearchSetup};
+use crate::core::search::transpositions::shared_transposition::{lend_transposition_table, return_transposition_table, SharedTranspositionTable};
+use crate::core::search::transpositions::TranspositionTable;
+use crate::input::protocol_interpreter::CalculateOptions;
+
+/// How many plies deeper helper `helper` (counting from 1) starts: half of them one ply, the other half two
+pub fn helper_depth_offset(helper: usize) -> u32 {
+    1 + (helper as u32 + 1) % 2
+}
+
+/// Searches with the `Threads` of the engine options of the `setup`, sharing the `transposition_table`, which is lent out for the duration.
+/// Only the main thread logs its iterations and decides when to stop; the helpers stop along with it.
+pub fn lazy_smp_search<L>(
+    board: &Board,
+    transposition_table: &mut Box<dyn TranspositionTable>,
+    visited_boards: Vec<u64>,
+    options: CalculateOptions,
+    setup: SearchSetup,
+    search_logging: L,
+    control: &SearchControl,
+) -> SearchOutput<DebugSearchResult> where
+    L: Fn(PositionSearchRow, Vec<MTSearchRow>) {
+    let threads = setup.engine_options.threads as usize;
+    let shared_table = lend_transposition_table(transposition_table, threads);
+
+    let search_output = thread::scope(|scope| {
+        let helpers = (1..threads)
+            .map(|helper| {
+                let mut helper_table: Box<dyn TranspositionTable> = Box::new(SharedTranspositionTable::new(shared_table.clone()));
+                let helper_control = control.helper(helper_depth_offset(helper));
+                let visited_boards = visited_boards.clone();
+                let options = options.clone();
+
+                scope.spawn(move || {
+                    setup.searcher().search(
+                        board,
+                        &mut helper_table,
+                        visited_boards,
+                        options,
+                        &|_, _| {},
+                        &helper_control,
+                    )
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut main_table: Box<dyn TranspositionTable> = Box::new(SharedTranspositionTable::new(shared_table.clone()));
+        let main_output = setup.searcher().search(
+            board,
+            &mut main_table,
+            visited_boards.clone(),
+            options,
+            &search_logging,
+            control,
+        );
+        // The helpers never stop on their own
+        control.stop();
+
+        let helper_outputs = helpers.into_iter()
+            .map(|x| x.join().expect("helper thread panicked"))
+            .collect::<Vec<_>>();
+        pick_search_output(main_output, helper_outputs)
+    });
+
+    return_transposition_table(transposition_table, shared_table);
+
+    search_output
+}
+
+/// The main thread's output, unless a helper completed a deeper iteration
+fn pick_search_output<T: SearchResult>(main_output: SearchOutput<T>, helper_outputs: Vec<SearchOutput<T>>) -> SearchOutput<T> {
+    helper_outputs.into_iter()
+        .filter(|x| !is_default_move(&x.search_result.best_move()))
+        .fold(main_output, |best, x| if x.depth > best.depth { x } else { best })
+}
@@ -1,23 +1,21 @@
-use std::sync::mpsc::Receiver;
 use chess::{Board, ChessMove};
-use crate::core::search::conspiracy_search::merging::merge_remove_overwritten;
-use crate::input::protocol_interpreter::{CalculateOptions, Command};
+use crate::analysis::database::rows::{MTSearchRow, PositionSearchRow};
+use crate::input::protocol_interpreter::CalculateOptions;
 
-use crate::core::search::iterative_deepening::iterative_deepening_search;
-use crate::core::search::mtd::mtd_iterative_deepening_search;
-use crate::core::search::mtdbi::{determine_mtdbi_step, mtdbi_iterative_deepening_search};
-use crate::core::search::mtdf::mtdf_iterative_deepening_search;
+use crate::core::search::lazy_smp::lazy_smp_search;
+use crate::core::search::search_control::SearchControl;
 use crate::core::search::search_result::debug_search_result::DebugSearchResult;
-use crate::core::search::search_result::SearchResult;
+use crate::core::search::searcher::{SearchOutput, SearchSetup};
 use crate::core::search::transpositions::{EvalBound, TranspositionTable};
-use crate::core::search::transpositions::high_depth_transposition::HighDepthTranspositionTable;
 
 pub mod search_result;
 pub mod transpositions;
 mod draw_detection;
+pub mod position_history;
 pub mod iterative_deepening;
-mod move_ordering;
+pub mod move_ordering;
 pub mod alpha_beta;
+pub mod pvs;
 pub mod mtdf;
 pub mod mt;
 pub mod common;
@@ -25,6 +23,16 @@ pub mod mtdbi;
 pub mod mtd;
 pub mod conspiracy_search;
 pub mod conspiracy_counter;
+pub mod search_control;
+pub mod time_management;
+pub mod multi_pv;
+pub mod info;
+pub mod bench;
+pub mod searcher;
+pub mod pruning;
+pub mod extensions;
+pub mod aspiration;
+pub mod lazy_smp;
 
 
 /// The information about what search has been done on a particular node.
@@ -45,76 +53,37 @@ pub enum SearchDepth {
     Depth(u32), // Depth still left to go
 }
 
-#[derive(Clone, Eq, PartialEq, Debug)]
-pub enum SearchCommand {
-    NewGame,
-    SetPosition(Board, Vec<u64>),
-    Calculate(CalculateOptions),
-    Stop,
-}
-
-impl SearchCommand {
-    pub fn from_command(command: Command) -> Option<Self> {
-        match command {
-            Command::NewGame => Some(SearchCommand::NewGame),
-            Command::SetPosition(board, moves) => Some(SearchCommand::SetPosition(board, moves)),
-            Command::Calculate(options) => Some(SearchCommand::Calculate(options)),
-            Command::Stop => Some(SearchCommand::Stop),
-            _ => None,
-        }
+/// Runs the search algorithm selected in the engine options of the `setup`, handing the DB rows of every iteration to `search_logging`.
+/// With a single thread, which is the default, the search is deterministic.
+pub fn search_position<L>(
+    board: &Board,
+    transposition_table: &mut Box<dyn TranspositionTable>,
+    visited_boards: Vec<u64>,
+    options: CalculateOptions,
+    setup: SearchSetup,
+    search_logging: L,
+    control: &SearchControl,
+) -> SearchOutput<DebugSearchResult> where
+    L: Fn(PositionSearchRow, Vec<MTSearchRow>) {
+    if setup.engine_options.threads > 1 {
+        return lazy_smp_search(
+            board,
+            transposition_table,
+            visited_boards,
+            options,
+            setup,
+            search_logging,
+            control,
+        );
     }
-}
-
-
-/// The function to have a thread start functioning as the search engine.
-pub fn start_search_engine(search_rx: Receiver<SearchCommand>) {
-    // init Transposition Table
-    // let mut transposition_table = HighDepthTranspositionTable::new(SearchDepth::Depth(2));
-    let mut transposition_table: Box<dyn TranspositionTable> = Box::new(HighDepthTranspositionTable::new(SearchDepth::Depth(2)));
-    let mut main_board: Board = Board::default();
-    let mut visited_boards: Vec<u64> = Vec::new(); // List of board hashes
 
-    loop {
-        let command = search_rx.recv().expect("search receiver error");
-        println!("{:?}", command);
-
-        match command {
-            SearchCommand::SetPosition(board, visited) => {
-                main_board = board;
-                visited_boards = visited;
-            },
-            // SearchCommand::NewGame => transposition_table = HighDepthTranspositionTable::new(SearchDepth::Depth(2)),
-            SearchCommand::NewGame => transposition_table = Box::new(HighDepthTranspositionTable::new(SearchDepth::Depth(2))),
-            SearchCommand::Calculate(options) => {
-
-                let options = CalculateOptions::Depth(6);
-                // let (search_result, depth, selective_depth): (DebugSearchResult, _, _) = iterative_deepening_search(
-                //     &main_board,
-                //     &mut transposition_table,
-                //     visited_boards.clone(),
-                //     options,
-                // );
-                // let (search_result, depth, selective_depth): (DebugSearchResult, _, _) = mtdbi_iterative_deepening_search(
-                //     &main_board,
-                //     &mut transposition_table,
-                //     visited_boards.clone(),
-                //     options,
-                // );
-                let (search_result, _conspiracy_counter, _depth, _selective_depth): (DebugSearchResult, _, _, _) = conspiracy_search::mtd_w_conspiracy::mtd_iterative_deepening_search(
-                    &main_board,
-                    &mut transposition_table,
-                    visited_boards.clone(),
-                    options,
-                    determine_mtdbi_step,
-                    20,
-                    101,
-                    merge_remove_overwritten,
-                    |_, _| {},
-                );
-
-                println!("bestmove {}", search_result.best_move());
-            },
-            SearchCommand::Stop => (),
-        }
-    }
+    setup.searcher()
+        .search(
+            board,
+            transposition_table,
+            visited_boards,
+            options,
+            &search_logging,
+            control,
+        )
 }
@@ -1,25 +1,158 @@
-use chess::{Board, ChessMove, Color, EMPTY, MoveGen};
+use std::cmp::Reverse;
+use chess::{Board, ChessMove, Color, EMPTY, MoveGen, Piece};
 use crate::core::score::{BoardEvaluation, Centipawns};
 use crate::core::{is_default_move, score};
+use crate::core::castling::castle_moves;
+use crate::core::evaluation::see::static_exchange_evaluation;
 use crate::core::score::score_tables::{piece_value};
 use crate::core::search::transpositions::TranspositionTable;
 
+/// History scores are halved once one gets this high, so recent cutoffs keep counting
+const MAX_HISTORY: u32 = 1 << 20;
 
+/// What the search learns about quiet moves, to order them better in the rest of the search.
+/// Kept for a whole search: through every iteration, every MTD probe, and every line of a multi-PV search.
+#[derive(Clone, Debug)]
+pub struct OrderingState {
+    killers: Vec<[Option<ChessMove>; 2]>, // By ply: the last two quiet moves that caused a cutoff there
+    history: Box<[[[u32; 64]; 64]; 2]>, // By color, source and destination: the cutoffs of a quiet move, weighted by depth
+    countermoves: Box<[[Option<ChessMove>; 64]; 64]>, // By source and destination of the previous move: the quiet reply that cut off
+    played_moves: Vec<Option<ChessMove>>, // By ply: the move being searched there, `None` for a null move
+}
+
+impl Default for OrderingState {
+    fn default() -> Self {
+        OrderingState {
+            killers: vec![],
+            history: Box::new([[[0; 64]; 64]; 2]),
+            countermoves: Box::new([[None; 64]; 64]),
+            played_moves: vec![],
+        }
+    }
+}
+
+impl OrderingState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers that `chess_move` is searched at `current_depth`, so the replies know their previous move
+    pub fn play_move(&mut self, current_depth: u32, chess_move: Option<ChessMove>) {
+        let ply = current_depth as usize;
+        if self.played_moves.len() <= ply {
+            self.played_moves.resize(ply + 1, None);
+        }
+
+        self.played_moves[ply] = chess_move;
+    }
+
+    /// Registers that `chess_move` caused a cutoff at `current_depth`; only quiet moves are remembered
+    pub fn record_cutoff(&mut self, board: &Board, chess_move: ChessMove, current_depth: u32, depth_left: u32) {
+        if is_capture_or_promotion(board, chess_move) {
+            return;
+        }
+
+        let ply = current_depth as usize;
+        if self.killers.len() <= ply {
+            self.killers.resize(ply + 1, [None; 2]);
+        }
+        let killers = &mut self.killers[ply];
+        if killers[0] != Some(chess_move) {
+            killers[1] = killers[0];
+            killers[0] = Some(chess_move);
+        }
+
+        let history = &mut self.history[board.side_to_move().to_index()][chess_move.get_source().to_index()][chess_move.get_dest().to_index()];
+        *history += depth_left * depth_left;
+        if *history > MAX_HISTORY {
+            self.age_history();
+        }
+
+        if let Some(previous_move) = self.previous_move(current_depth) {
+            self.countermoves[previous_move.get_source().to_index()][previous_move.get_dest().to_index()] = Some(chess_move);
+        }
+    }
+
+    pub fn killers(&self, current_depth: u32) -> [Option<ChessMove>; 2] {
+        self.killers.get(current_depth as usize).copied().unwrap_or([None; 2])
+    }
+
+    pub fn history(&self, color: Color, chess_move: ChessMove) -> u32 {
+        self.history[color.to_index()][chess_move.get_source().to_index()][chess_move.get_dest().to_index()]
+    }
+
+    /// The quiet move that last refuted the move played before `current_depth`
+    pub fn countermove(&self, current_depth: u32) -> Option<ChessMove> {
+        let previous_move = self.previous_move(current_depth)?;
+        self.countermoves[previous_move.get_source().to_index()][previous_move.get_dest().to_index()]
+    }
+
+    /// Puts the killers first, then the countermove, then the rest by history.
+    /// The sort is stable, so moves without history keep their order.
+    pub fn order_quiet_moves(&self, board: &Board, current_depth: u32, quiet_moves: &mut [ChessMove]) {
+        let killers = self.killers(current_depth);
+        let countermove = self.countermove(current_depth);
+        let color = board.side_to_move();
+
+        quiet_moves.sort_by_key(|x| {
+            let rank = match Some(*x) {
+                m if m == killers[0] => 0,
+                m if m == killers[1] => 1,
+                m if m == countermove => 2,
+                _ => 3,
+            };
+            (rank, Reverse(self.history(color, *x)))
+        });
+    }
+
+    fn previous_move(&self, current_depth: u32) -> Option<ChessMove> {
+        let previous_ply = (current_depth as usize).checked_sub(1)?;
+        self.played_moves.get(previous_ply).copied().flatten()
+    }
+
+    fn age_history(&mut self) {
+        self.history.iter_mut()
+            .flatten()
+            .flatten()
+            .for_each(|x| *x /= 2);
+    }
+}
 
+/// Captures, including en passant, and promotions: the moves ordered by the material they win.
+/// A Chess960 castle takes the own rook, which is no capture.
+pub fn is_capture_or_promotion(board: &Board, chess_move: ChessMove) -> bool {
+    let source = chess_move.get_source();
+    let dest = chess_move.get_dest();
+    let is_en_passant = board.piece_on(source) == Some(Piece::Pawn) && source.get_file() != dest.get_file();
+
+    board.color_on(dest) == Some(!board.side_to_move()) || is_en_passant || chess_move.get_promotion().is_some()
+}
+
+/// The TT move first, then the captures that don't lose material by their SEE, then the quiet moves, then the losing captures.
+/// With `captures_only`, the losing captures are pruned: this is what quiescence searches.
+/// With an `ordering`, the quiet moves are ordered by what the search learned at `current_depth` so far.
+/// The Chess960 `castles` the move generator doesn't know are quiet moves.
 pub fn order_moves(
     board: &Board,
     // transposition_table: &mut impl TranspositionTable,
     already_found_move: Option<ChessMove>,
     mut move_generator: &mut MoveGen,
+    castles: &[ChessMove],
     captures_only: bool,
+    ordering: Option<&OrderingState>,
+    current_depth: u32,
 ) -> Vec<ChessMove> {
     // First: Check if a best move exists
 
     // let search_info = transposition_table.get_transposition(board, None);
 
+    let our_color = board.side_to_move();
     let mut best_move = None;
     if let Some(m) = already_found_move {
-        if !is_default_move(&m) { // In the case an ended game (e.g. checkmate) ends up in the Transposition Table
+        // In the case an ended game (e.g. checkmate) ends up in the Transposition Table,
+        // or a castle from a position that only differs by its Chess960 castling rights
+        let is_lost_castle = board.color_on(m.get_dest()) == Some(our_color) && !castles.contains(&m);
+        if !is_default_move(&m) && !is_lost_castle {
             best_move = already_found_move;
         }
     }
@@ -33,11 +166,7 @@ pub fn order_moves(
     //     best_move = None;
     // }
 
-    let our_color = board.side_to_move();
-    let mut capture_moves: Vec<(ChessMove, Centipawns)> = match best_move {
-        Some(b) => vec![(b, Centipawns(1_000_000))], // must be the best move when reordering
-        _ => vec![],
-    };
+    let mut capture_moves: Vec<(ChessMove, Centipawns, Centipawns)> = vec![]; // With the SEE, and the capture score to break ties
 
     // Make extra sure we're only looking at the capture moves
     move_generator.set_iterator_mask(*board.color_combined(!board.side_to_move()));
@@ -57,18 +186,22 @@ pub fn order_moves(
             // are already in the transposition table
             capture_moves.push((
                 chess_move,
+                static_exchange_evaluation(board, chess_move),
                 chess_move_score,
             ));
         }
     }
 
-    capture_moves.sort_by_key(|(_, a)| *a);
-    let mut capture_moves_new;
-    if our_color == Color::White {
-        capture_moves_new = capture_moves.into_iter().map(|(x, _)| x).collect();
-    } else {
-        capture_moves_new = capture_moves.into_iter().map(|(x, _)| x).rev().collect();
-    }
+    // The best capture first, for either color
+    capture_moves.sort_by_key(|(_, see, score)| Reverse((*see, *score)));
+    // Captures that lose material come after the quiet moves, and are left out entirely when only captures are searched
+    let (winning_captures, losing_captures): (Vec<_>, Vec<_>) = capture_moves.into_iter()
+        .partition(|(_, see, _)| *see >= Centipawns::new(0));
+
+    let mut capture_moves_new: Vec<ChessMove> = best_move.into_iter()
+        .chain(winning_captures.into_iter().map(|(x, _, _)| x))
+        .collect();
+    let mut losing_capture_moves: Vec<ChessMove> = losing_captures.into_iter().map(|(x, _, _)| x).collect();
 
     if captures_only {
         return capture_moves_new;
@@ -102,19 +235,111 @@ pub fn order_moves(
         }
     }
 
+    for chess_move in castles {
+        if Some(*chess_move) != best_move {
+            let (king_move, rook_move) = castle_moves(board, *chess_move).expect("castle moves the king and the rook");
+            let move_score = |chess_move: ChessMove, piece: Piece| {
+                piece_value(our_color, piece, chess_move.get_dest().to_index()) as i64 - piece_value(our_color, piece, chess_move.get_source().to_index()) as i64
+            };
+
+            non_capture_moves.push((
+                *chess_move,
+                Centipawns::new(move_score(king_move, Piece::King) + move_score(rook_move, Piece::Rook)),
+            ));
+        }
+    }
+
     non_capture_moves.sort_by_key(|(_, a)| *a);
-    let mut non_capture_moves_new;
+    let mut non_capture_moves_new: Vec<ChessMove>;
     if our_color == Color::White {
         non_capture_moves_new = non_capture_moves.into_iter().map(|(x, _)| x).collect();
     } else {
         non_capture_moves_new = non_capture_moves.into_iter().map(|(x, _)| x).rev().collect();
     }
 
+    if let Some(ordering) = ordering {
+        ordering.order_quiet_moves(board, current_depth, &mut non_capture_moves_new);
+    }
+
     capture_moves_new.append(&mut non_capture_moves_new);
+    capture_moves_new.append(&mut losing_capture_moves);
 
     capture_moves_new
 }
 
+#[test]
+fn check_record_cutoff() {
+    use std::str::FromStr;
+
+    let board = Board::from_str("4k3/8/8/8/8/8/4P3/R3K3 w - - 0 1").unwrap();
+    let quiet_move = ChessMove::from_str("e2e3").unwrap();
+    let other_quiet_move = ChessMove::from_str("a1a7").unwrap();
+    let mut ordering = OrderingState::new();
+
+    ordering.play_move(0, Some(ChessMove::from_str("e8d8").unwrap()));
+    ordering.record_cutoff(&board, quiet_move, 1, 3);
+    ordering.record_cutoff(&board, quiet_move, 1, 3);
+    ordering.record_cutoff(&board, other_quiet_move, 1, 2);
+
+    assert_eq!(ordering.killers(1), [Some(other_quiet_move), Some(quiet_move)]);
+    assert_eq!(ordering.killers(2), [None, None]);
+    assert_eq!(ordering.history(Color::White, quiet_move), 18);
+    assert_eq!(ordering.history(Color::Black, quiet_move), 0);
+    assert_eq!(ordering.countermove(1), Some(other_quiet_move));
+    assert_eq!(ordering.countermove(0), None);
+
+    // Captures already have their own order
+    let capture_board = Board::from_str("r3k3/8/8/8/8/8/4P3/R3K3 w - - 0 1").unwrap();
+    ordering.record_cutoff(&capture_board, ChessMove::from_str("a1a8").unwrap(), 3, 3);
+    assert_eq!(ordering.killers(3), [None, None]);
+}
+
+#[test]
+fn check_order_quiet_moves() {
+    use std::str::FromStr;
+
+    let board = Board::default();
+    let mut ordering = OrderingState::new();
+    let killer = ChessMove::from_str("g1f3").unwrap();
+    let countermove = ChessMove::from_str("d2d4").unwrap();
+    let history_move = ChessMove::from_str("c2c4").unwrap();
+
+    ordering.play_move(1, Some(ChessMove::from_str("b8c6").unwrap()));
+    ordering.record_cutoff(&board, killer, 2, 1);
+    // The reply to the same move, deeper in the tree
+    ordering.play_move(5, Some(ChessMove::from_str("e7e5").unwrap()));
+    ordering.record_cutoff(&board, countermove, 6, 1);
+    ordering.record_cutoff(&board, history_move, 4, 5);
+    ordering.play_move(1, Some(ChessMove::from_str("e7e5").unwrap()));
+
+    let moves = order_moves(&board, None, &mut MoveGen::new_legal(&board), &[], false, Some(&ordering), 2);
+    assert_eq!(moves[..3], [killer, countermove, history_move]);
+    assert_eq!(moves.len(), 20);
+
+    // Without an ordering, the killers and countermoves are just quiet moves
+    let moves = order_moves(&board, None, &mut MoveGen::new_legal(&board), &[], false, None, 2);
+    assert_ne!(moves[..3], [killer, countermove, history_move]);
+}
+
+#[test]
+fn check_order_captures() {
+    use std::str::FromStr;
+
+    let board = Board::from_str("4k3/8/4p3/3p1n2/4P3/8/8/3RK3 w - - 0 1").unwrap();
+    let winning_capture = ChessMove::from_str("e4f5").unwrap();
+    let equal_capture = ChessMove::from_str("e4d5").unwrap();
+    let losing_capture = ChessMove::from_str("d1d5").unwrap();
+    let tt_move = ChessMove::from_str("d1d2").unwrap();
+
+    let moves = order_moves(&board, Some(tt_move), &mut MoveGen::new_legal(&board), &[], false, None, 0);
+    assert_eq!(moves[..3], [tt_move, winning_capture, equal_capture]);
+    assert_eq!(moves.last(), Some(&losing_capture));
+    assert_eq!(moves.len(), MoveGen::new_legal(&board).len());
+
+    let moves = order_moves(&board, None, &mut MoveGen::new_legal(&board), &[], true, None, 0);
+    assert_eq!(moves, vec![winning_capture, equal_capture]);
+}
+
 // pub fn order_captures(
 //     board: &Board,
 //     current_evaluation: BoardEvaluation,
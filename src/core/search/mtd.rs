@@ -2,14 +2,18 @@ use std::cmp::{max, min};
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use chess::{Board, ChessMove, Color};
 use crate::analysis::database::rows::{MTSearchRow, PositionSearchRow};
-use crate::core::evaluation::single_evaluation;
 use crate::core::score::{BoardEvaluation, Centipawns};
 use crate::core::search::alpha_beta::search_alpha_beta;
-use crate::core::search::iterative_deepening::{determine_critical_path_string, is_still_searching, log_info_search_results};
+use crate::core::search::extensions::ExtensionState;
+use crate::core::search::iterative_deepening::{determine_critical_path_string, first_depth, is_still_searching, log_info_search_results, selective_depth};
 use crate::core::search::mt::search_mt;
 use crate::core::search::mtdf::mtdf_search;
-use crate::core::search::search_result::SearchResult;
+use crate::core::search::multi_pv::search_multi_pv;
+use crate::core::search::position_history::PositionHistory;
+use crate::core::search::search_control::SearchControl;
+use crate::core::search::search_result::{with_selective_depth, SearchResult};
 use crate::core::search::transpositions::{EvalBound, TranspositionTable};
+use crate::debug_log;
 use crate::input::protocol_interpreter::CalculateOptions;
 
 /// The base implementation of the mtd framework
@@ -23,21 +27,24 @@ pub fn mtd_iterative_deepening_search<T: SearchResult + Default + Clone, L>(
     options: CalculateOptions,
     step_fn: fn(BoardEvaluation, BoardEvaluation, BoardEvaluation) -> BoardEvaluation,
     search_logging: L,
+    control: &SearchControl,
 ) -> (T, u32, u32) where
     L: Fn(PositionSearchRow, Vec<MTSearchRow>) { // (SearchResult, depth, selective_depth)
     let now = Instant::now();
-    let mut current_depth = 2;
+    let start_depth = first_depth(&options, control);
+    let mut current_depth = start_depth + 1;
     let (mut search_result, mt_rows, position_row): (T, _, _) = mtd_search(
         board,
         transposition_table,
         visited_boards.clone(),
-        1,
+        start_depth,
         BoardEvaluation::PieceScore(Centipawns::new(0)),
         step_fn.clone(),
+        control,
     );
     search_logging(position_row, mt_rows);
 
-    while is_still_searching(options, board, now, current_depth) {
+    while is_still_searching(&options, board, control, current_depth, &search_result) {
         let temp_search_result = mtd_search(
         // search_result = mtd_search(
             board,
@@ -46,25 +53,44 @@ pub fn mtd_iterative_deepening_search<T: SearchResult + Default + Clone, L>(
             current_depth,
             search_result.eval_bound().board_evaluation(),
             step_fn.clone(),
+            control,
         );
+        if control.is_stopped() {
+            // Keep the result of the last completed iteration
+            break;
+        }
         search_result = temp_search_result.0;
         search_logging(temp_search_result.2, temp_search_result.1);
 
+        let lines = search_multi_pv(board, control, search_result.clone(), |line_control| {
+            mtd_search(
+                board,
+                transposition_table,
+                visited_boards.clone(),
+                current_depth,
+                search_result.eval_bound().board_evaluation(),
+                step_fn,
+                line_control,
+            ).0
+        });
+
         let duration = now.elapsed();
         log_info_search_results(
-            &search_result,
-            board.side_to_move(),
+            &lines,
+            board,
             duration,
             current_depth,
-            current_depth, // TODO: Change to actual selective depth
+            selective_depth(&search_result, current_depth),
+            control,
         );
         current_depth += 1;
     }
 
+    let selective_depth = selective_depth(&search_result, current_depth);
     (
         search_result,
         current_depth,
-        current_depth,
+        selective_depth,
     )
 }
 
@@ -77,9 +103,10 @@ pub fn mtd_search<T: SearchResult + Default + Clone>(
     depth: u32,
     start_point: BoardEvaluation,
     step_fn: fn(BoardEvaluation, BoardEvaluation, BoardEvaluation) -> BoardEvaluation,
+    control: &SearchControl,
 ) -> (T, Vec<MTSearchRow>, PositionSearchRow) {
     let mut current_test_value = start_point;
-    let current_evaluation = single_evaluation(board, board.status());
+    let current_evaluation = control.evaluate(board, board.status());
 
     let simple_evaluation;
     match current_evaluation {
@@ -136,18 +163,27 @@ pub fn mtd_search<T: SearchResult + Default + Clone>(
         None,
     );
     let mut nodes_searched = 0;
+    let mut selective_depth = 0; // The deepest ply of all the MT searches
+    let mut position_history = PositionHistory::new(&visited_boards, board).with_castling(control.castling());
     while !result.eval_bound().is_exact() {
         let time = SystemTime::now();
         result = search_mt(
             board,
             transposition_table,
-            visited_boards.clone(),
+            &mut position_history,
             simple_evaluation,
             EvalBound::Exact(current_test_value),
             0,
             depth,
+            ExtensionState::default(),
+            true,
+            control,
         );
         nodes_searched += result.nodes_searched().unwrap_or(1);
+        selective_depth = selective_depth.max(result.selective_depth().unwrap_or(depth));
+        if control.is_stopped() {
+            break;
+        }
         // println!("----------");
         // println!("start lowerbound {lowerbound}, upperbound {upperbound}");
         // println!("mt_search result eval_bound: {:?}", result.eval_bound());
@@ -164,6 +200,7 @@ pub fn mtd_search<T: SearchResult + Default + Clone>(
             eval_bound: result.eval_bound(),
             conspiracy_counter: None,
             search_num: mt_search_num,
+            window: None,
             timestamp: time.duration_since(UNIX_EPOCH).expect("time went backwards").as_secs() as i64,
         });
         mt_search_num += 1;
@@ -184,11 +221,14 @@ pub fn mtd_search<T: SearchResult + Default + Clone>(
                 };
 
                 return (
-                    T::make_search_result(
-                        result.best_move(),
-                        result.eval_bound(),
-                        Some(nodes_searched),
-                        result.critical_path(),
+                    with_selective_depth(
+                        T::make_search_result(
+                            result.best_move(),
+                            result.eval_bound(),
+                            Some(nodes_searched),
+                            result.critical_path(),
+                        ),
+                        selective_depth,
                     ),
                     mt_searches,
                     position_search,
@@ -213,17 +253,14 @@ pub fn mtd_search<T: SearchResult + Default + Clone>(
 
             lowest_upperbound = min(upperbound, lowest_upperbound);
             highest_lowerbound = max(lowerbound, highest_lowerbound);
-            println!("UNSTABLE SEARCH: logging");
-            println!("lowerbound {:?}, upperbound {:?}", lowerbound, upperbound);
-            let path = result.critical_path().unwrap();
-            for chess_move in path.into_iter() {
-                print!("{} ", chess_move);
-            }
-            println!("");
-            // println!("result path: {}", result.critical_path());
-            println!("result best_move: {}", result.best_move());
-            println!("result nodes_searched: {:?}", result.nodes_searched());
-            println!("UNSTABLE SEARCH: end logging");
+            debug_log!(
+                "unstable search: lowerbound {:?}, upperbound {:?}, best_move {}, nodes {:?}, {}",
+                lowerbound,
+                upperbound,
+                result.best_move(),
+                result.nodes_searched(),
+                determine_critical_path_string(result.critical_path()),
+            );
 
             upperbound = newest_eval;
             lowerbound = newest_eval;
@@ -247,11 +284,14 @@ pub fn mtd_search<T: SearchResult + Default + Clone>(
                         };
 
                         return (
-                            T::make_search_result(
-                                result.best_move(),
-                                EvalBound::Exact(result.eval_bound().board_evaluation()),
-                                Some(nodes_searched),
-                                result.critical_path(),
+                            with_selective_depth(
+                                T::make_search_result(
+                                    result.best_move(),
+                                    EvalBound::Exact(result.eval_bound().board_evaluation()),
+                                    Some(nodes_searched),
+                                    result.critical_path(),
+                                ),
+                                selective_depth,
                             ),
                             mt_searches,
                             position_search,
@@ -271,11 +311,14 @@ pub fn mtd_search<T: SearchResult + Default + Clone>(
                         };
 
                         return (
-                            T::make_search_result(
-                                result.best_move(),
-                                EvalBound::Exact(result.eval_bound().board_evaluation()),
-                                Some(nodes_searched),
-                                result.critical_path(),
+                            with_selective_depth(
+                                T::make_search_result(
+                                    result.best_move(),
+                                    EvalBound::Exact(result.eval_bound().board_evaluation()),
+                                    Some(nodes_searched),
+                                    result.critical_path(),
+                                ),
+                                selective_depth,
                             ),
                             mt_searches,
                             position_search,
@@ -297,11 +340,14 @@ pub fn mtd_search<T: SearchResult + Default + Clone>(
                             };
 
                             return (
-                                T::make_search_result(
-                                    result.best_move(),
-                                    EvalBound::Exact(result.eval_bound().board_evaluation()),
-                                    Some(nodes_searched),
-                                    result.critical_path(),
+                                with_selective_depth(
+                                    T::make_search_result(
+                                        result.best_move(),
+                                        EvalBound::Exact(result.eval_bound().board_evaluation()),
+                                        Some(nodes_searched),
+                                        result.critical_path(),
+                                    ),
+                                    selective_depth,
                                 ),
                                 mt_searches,
                                 position_search,
@@ -372,11 +418,14 @@ pub fn mtd_search<T: SearchResult + Default + Clone>(
     // Usually `lowerbound == upperbound` here
     // But not always: just return anyways
     (
-        T::make_search_result(
-            result.best_move(),
-            EvalBound::Exact(result.eval_bound().board_evaluation()),
-            Some(nodes_searched),
-            result.critical_path(),
+        with_selective_depth(
+            T::make_search_result(
+                result.best_move(),
+                EvalBound::Exact(result.eval_bound().board_evaluation()),
+                Some(nodes_searched),
+                result.critical_path(),
+            ),
+            selective_depth,
         ),
         mt_searches,
         position_search,
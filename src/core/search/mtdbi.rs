@@ -4,6 +4,7 @@ use crate::core::evaluation::single_evaluation;
 use crate::core::score::BoardEvaluation;
 use crate::core::search::mt::search_mt;
 use crate::core::search::mtd::{avg_bounds, mtd_iterative_deepening_search, mtd_search};
+use crate::core::search::search_control::SearchControl;
 use crate::core::search::search_result::SearchResult;
 use crate::core::search::transpositions::{EvalBound, TranspositionTable};
 use crate::input::protocol_interpreter::CalculateOptions;
@@ -18,6 +19,7 @@ pub fn mtdbi_iterative_deepening_search<T: SearchResult + Default + Clone, L>(
     visited_boards: Vec<u64>,
     options: CalculateOptions,
     search_logging: L,
+    control: &SearchControl,
 ) -> (T, u32, u32) where
     L: Fn(PositionSearchRow, Vec<MTSearchRow>) { // (SearchResult, depth, selective_depth)
     mtd_iterative_deepening_search(
@@ -27,6 +29,7 @@ pub fn mtdbi_iterative_deepening_search<T: SearchResult + Default + Clone, L>(
         options,
         determine_mtdbi_step,
         search_logging,
+        control,
     )
 }
 
@@ -39,6 +42,7 @@ pub fn mtdbi_search<T: SearchResult + Default + Clone>(
     depth: u32,
     start_point: BoardEvaluation,
     // selective_depth: u32,
+    control: &SearchControl,
 ) -> (T, Vec<MTSearchRow>, PositionSearchRow)  {
     mtd_search(
         board,
@@ -47,6 +51,7 @@ pub fn mtdbi_search<T: SearchResult + Default + Clone>(
         depth,
         start_point,
         determine_mtdbi_step,
+        control,
     )
 }
 
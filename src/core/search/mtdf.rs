@@ -8,6 +8,7 @@ use crate::core::search::iterative_deepening::{is_still_searching, log_info_sear
 use crate::core::search::mt::search_mt;
 use crate::core::search::mtd::{avg_bounds, mtd_iterative_deepening_search, mtd_search};
 use crate::core::search::search_result::debug_search_result::DebugSearchResult;
+use crate::core::search::search_control::SearchControl;
 use crate::core::search::search_result::SearchResult;
 use crate::core::search::transpositions::{EvalBound, TranspositionTable};
 use crate::input::protocol_interpreter::CalculateOptions;
@@ -24,6 +25,7 @@ pub fn mtdf_iterative_deepening_search<T: SearchResult + Default + Clone, L>(
     visited_boards: Vec<u64>,
     options: CalculateOptions,
     search_logging: L,
+    control: &SearchControl,
 ) -> (T, u32, u32) where
     L: Fn(PositionSearchRow, Vec<MTSearchRow>) { // (SearchResult, depth, selective_depth)
     mtd_iterative_deepening_search(
@@ -33,6 +35,7 @@ pub fn mtdf_iterative_deepening_search<T: SearchResult + Default + Clone, L>(
         options,
         determine_mtdf_step,
         search_logging,
+        control,
     )
 }
 
@@ -44,6 +47,7 @@ pub fn mtdf_search<T: SearchResult + Default + Clone>(
     visited_boards: Vec<u64>,
     depth: u32,
     start_point: BoardEvaluation,
+    control: &SearchControl,
 ) -> (T, Vec<MTSearchRow>, PositionSearchRow)  {
     mtd_search(
         board,
@@ -52,6 +56,7 @@ pub fn mtdf_search<T: SearchResult + Default + Clone>(
         depth,
         start_point,
         determine_mtdf_step,
+        control,
     )
 }
 
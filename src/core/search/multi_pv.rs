@@ -0,0 +1,35 @@
+use chess::{Board, ChessMove};
+use crate::core::search::search_control::SearchControl;
+use crate::core::search::search_result::SearchResult;
+
+/// Adds the next best root moves to `best_line`, until `control.multi_pv()` lines are found.
+/// Every line is a search of its own, leaving out the root moves of the lines before it,
+/// so each gets an exact value even for the MTD drivers, whose probes only bound the moves that aren't best.
+/// `search` runs the search of the current iteration under the given control.
+pub fn search_multi_pv<T: SearchResult, F>(
+    board: &Board,
+    control: &SearchControl,
+    best_line: T,
+    mut search: F,
+) -> Vec<T> where
+    F: FnMut(&SearchControl) -> T {
+    let mut lines = vec![best_line];
+
+    while lines.len() < control.multi_pv() {
+        let excluded: Vec<ChessMove> = lines.iter()
+            .map(|x| x.best_move())
+            .collect();
+        let Some(line_control) = control.excluding_root_moves(board, &excluded) else {
+            break;
+        };
+
+        let line = search(&line_control);
+        if control.is_stopped() {
+            // The lines found so far are still complete
+            break;
+        }
+        lines.push(line);
+    }
+
+    lines
+}
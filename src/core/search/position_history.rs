@@ -0,0 +1,174 @@
+// The positions leading up to the one being searched, kept as one stack for the whole search to detect repetitions.
+
+use chess::{Board, Piece};
+use crate::core::castling::Chess960Castling;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct HistoryEntry {
+    hash: u64,
+    material: Option<(u64, u32)>, // The pawns and the piece count, which only irreversible moves change; unknown for the game before the search
+    reversible_start: usize, // The index of the first position since the last irreversible move
+    castling: Chess960Castling, // What the hash leaves out in Chess960; losing a right is irreversible too
+}
+
+/// The positions played before the search, followed by the line being searched.
+/// Every node pushes its children before searching them and pops them after, instead of copying the history.
+/// A position can't repeat one from before a capture or pawn move, so repetitions are only looked for since the last one.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PositionHistory {
+    entries: Vec<HistoryEntry>,
+}
+
+impl PositionHistory {
+    /// The history of a search from `board`, which was reached through the positions with hashes `visited_boards`
+    pub fn new(visited_boards: &[u64], board: &Board) -> Self {
+        let mut position_history = PositionHistory {
+            entries: visited_boards.iter()
+                .map(|x| HistoryEntry {
+                    hash: *x,
+                    material: None,
+                    reversible_start: 0,
+                    castling: Chess960Castling::default(),
+                })
+                .collect(),
+        };
+        position_history.push(board);
+
+        position_history
+    }
+
+    /// The Chess960 castling rights of the position the search starts from
+    pub fn with_castling(mut self, castling: Chess960Castling) -> Self {
+        if let Some(current) = self.entries.last_mut() {
+            current.castling = castling;
+        }
+        self
+    }
+
+    /// Makes `board` the current position; it follows the current one by a move
+    pub fn push(&mut self, board: &Board) {
+        let material = Some((board.pieces(Piece::Pawn).0, board.combined().popcnt()));
+        let castling = self.castling().after_move(board);
+        let reversible_start = match self.entries.last() {
+            Some(previous) if previous.castling != castling => self.entries.len(),
+            Some(previous) if previous.material.is_none() || previous.material == material => previous.reversible_start,
+            _ => self.entries.len(),
+        };
+
+        self.entries.push(HistoryEntry {
+            hash: board.get_hash(),
+            material,
+            reversible_start,
+            castling,
+        });
+    }
+
+    /// Makes `board` the current position; it follows the current one by a null move.
+    /// No line through a null move can be played, so it doesn't repeat the positions before it.
+    pub fn push_null(&mut self, board: &Board) {
+        self.entries.push(HistoryEntry {
+            hash: board.get_hash(),
+            material: Some((board.pieces(Piece::Pawn).0, board.combined().popcnt())),
+            reversible_start: self.entries.len(),
+            castling: self.castling(),
+        });
+    }
+
+    /// The Chess960 castling rights left in the current position
+    pub fn castling(&self) -> Chess960Castling {
+        self.entries.last().map(|x| x.castling).unwrap_or_default()
+    }
+
+    /// Goes back to the position before the current one
+    pub fn pop(&mut self) {
+        self.entries.pop();
+    }
+
+    /// How often the current position occurs, counting itself
+    pub fn repetitions(&self) -> usize {
+        match self.entries.last() {
+            None => 0,
+            Some(current) => self.entries[current.reversible_start..].iter()
+                .filter(|x| x.hash == current.hash)
+                .count(),
+        }
+    }
+}
+
+#[test]
+fn check_repetitions() {
+    use std::str::FromStr;
+    use chess::ChessMove;
+
+    let play = |position_history: &mut PositionHistory, board: &mut Board, moves: &[&str]| {
+        for chess_move in moves {
+            *board = board.make_move_new(ChessMove::from_str(chess_move).unwrap());
+            position_history.push(board);
+        }
+    };
+
+    let mut board = Board::default();
+    let mut position_history = PositionHistory::new(&[], &board);
+    assert_eq!(position_history.repetitions(), 1);
+
+    play(&mut position_history, &mut board, &["g1f3", "g8f6", "f3g1", "f6g8"]);
+    assert_eq!(position_history.repetitions(), 2);
+    play(&mut position_history, &mut board, &["g1f3", "g8f6", "f3g1", "f6g8"]);
+    assert_eq!(position_history.repetitions(), 3);
+
+    // Popping goes back to the earlier count
+    position_history.pop();
+    assert_eq!(position_history.repetitions(), 2);
+    position_history.push(&board);
+    assert_eq!(position_history.repetitions(), 3);
+
+    // After a pawn move, only the positions from then on are looked through
+    play(&mut position_history, &mut board, &["e2e4"]);
+    assert_eq!(position_history.repetitions(), 1);
+    assert_eq!(position_history.entries.last().unwrap().reversible_start, position_history.entries.len() - 1);
+}
+
+#[test]
+fn check_repetitions_of_played_positions() {
+    use std::str::FromStr;
+    use chess::ChessMove;
+
+    // Shuffled knights before the search: the irreversible moves of the game aren't known
+    let mut board = Board::default();
+    let mut visited_boards = vec![];
+    for chess_move in ["g1f3", "g8f6", "f3g1", "f6g8"] {
+        visited_boards.push(board.get_hash());
+        board = board.make_move_new(ChessMove::from_str(chess_move).unwrap());
+    }
+
+    let mut position_history = PositionHistory::new(&visited_boards, &board);
+    assert_eq!(position_history.entries.len(), 5);
+    assert_eq!(position_history.repetitions(), 2);
+
+    let knight_out = board.make_move_new(ChessMove::from_str("g1f3").unwrap());
+    position_history.push(&knight_out);
+    assert_eq!(position_history.repetitions(), 2);
+}
+
+#[test]
+fn check_repetitions_after_null_move() {
+    use std::str::FromStr;
+    use chess::ChessMove;
+
+    // The game went through the start position with black to move, which passing in the start position also reaches
+    let mut board = Board::default();
+    let null_board = board.null_move().unwrap();
+    let mut position_history = PositionHistory::new(&[null_board.get_hash()], &board);
+
+    position_history.push_null(&null_board);
+    assert_eq!(position_history.repetitions(), 1);
+
+    // Repetitions after the null move still count, but not those from before it
+    board = null_board;
+    for chess_move in ["g8f6", "g1f3", "f6g8", "f3g1"] {
+        board = board.make_move_new(ChessMove::from_str(chess_move).unwrap());
+        position_history.push(&board);
+    }
+    assert_eq!(board, null_board);
+    assert_eq!(position_history.repetitions(), 2);
+}
@@ -0,0 +1,273 @@
+// Forward pruning: giving up on part of the tree before searching it to the full depth.
+
+use chess::{Board, ChessMove, Color, Piece, EMPTY};
+use crate::core::evaluation::bubble_evaluation;
+use crate::core::score::BoardEvaluation;
+use crate::core::search::move_ordering::is_capture_or_promotion;
+use crate::core::search::transpositions::EvalBound;
+
+/// The depths and move numbers the reduction table covers; beyond them the last entry applies
+const REDUCTION_TABLE_SIZE: usize = 32;
+
+/// Which forward pruning the search may do; none by default, which keeps every search exact
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct PruningOptions {
+    pub null_move: Option<NullMovePruning>,
+    pub late_move_reductions: Option<LateMoveReductions>,
+}
+
+/// Lets the side to move pass: if its opponent still can't get below alpha or above beta, a real move won't either.
+/// A cutoff is only taken after a verification search without the null move, at the same reduced depth.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct NullMovePruning {
+    pub reduction: u32, // How much shallower the null move and the verification are searched
+}
+
+/// Searches the late quiet moves shallower first, with a null window.
+/// Only the moves that still beat the window there are searched again to the full depth.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct LateMoveReductions {
+    pub full_depth_moves: usize, // The first moves are never reduced
+    pub min_depth: u32, // The depth left needed to reduce at all
+    base: u32, // In hundredths of a ply
+    divisor: u32, // In hundredths
+    reductions: [[u8; REDUCTION_TABLE_SIZE]; REDUCTION_TABLE_SIZE], // By depth left, then by move index
+}
+
+impl Default for LateMoveReductions {
+    fn default() -> Self {
+        LateMoveReductions::new(Self::DEFAULT_BASE, Self::DEFAULT_DIVISOR)
+    }
+}
+
+impl LateMoveReductions {
+    pub const DEFAULT_BASE: u32 = 75;
+    pub const DEFAULT_DIVISOR: u32 = 225;
+
+    /// Reduces by `base + ln(depth left) * ln(move index) / divisor` plies, rounded down
+    pub fn new(base: u32, divisor: u32) -> Self {
+        let mut reductions = [[0; REDUCTION_TABLE_SIZE]; REDUCTION_TABLE_SIZE];
+        for (depth_left, row) in reductions.iter_mut().enumerate().skip(1) {
+            for (move_index, reduction) in row.iter_mut().enumerate().skip(1) {
+                let plies = (base as f64 + (depth_left as f64).ln() * (move_index as f64).ln() * 10_000.0 / divisor as f64) / 100.0;
+                *reduction = plies.floor() as u8;
+            }
+        }
+
+        LateMoveReductions {
+            full_depth_moves: 3,
+            min_depth: 3,
+            base,
+            divisor,
+            reductions,
+        }
+    }
+
+    pub fn base(&self) -> u32 {
+        self.base
+    }
+
+    pub fn divisor(&self) -> u32 {
+        self.divisor
+    }
+
+    /// The plies to reduce the move at `move_index` by, leaving at least one ply to search
+    pub fn reduction(&self, depth_left: u32, move_index: usize) -> u32 {
+        let depth_index = (depth_left as usize).min(REDUCTION_TABLE_SIZE - 1);
+        let move_index = move_index.min(REDUCTION_TABLE_SIZE - 1);
+
+        (self.reductions[depth_index][move_index] as u32).min(depth_left.saturating_sub(2))
+    }
+}
+
+impl PruningOptions {
+    /// The `max_depth` to search the null move to, if it may be tried at this node.
+    /// Never at the root, and never when there are too few plies left to reduce.
+    pub fn null_move_depth(&self, board: &Board, current_depth: u32, max_depth: u32) -> Option<u32> {
+        let null_move = self.null_move?;
+        let depth_left = max_depth.saturating_sub(current_depth);
+
+        if current_depth == 0 || depth_left <= null_move.reduction || !has_null_move_material(board) {
+            return None;
+        }
+
+        Some(max_depth - null_move.reduction)
+    }
+
+    /// The `max_depth` to first search `chess_move` to, if it is a late quiet move that may be reduced.
+    /// `new_board` is the position after the move; moves that give check are never reduced.
+    pub fn late_move_depth(
+        &self,
+        board: &Board,
+        new_board: &Board,
+        chess_move: ChessMove,
+        move_index: usize,
+        current_depth: u32,
+        max_depth: u32,
+    ) -> Option<u32> {
+        let late_move_reductions = self.late_move_reductions?;
+        let depth_left = max_depth.saturating_sub(current_depth);
+
+        if current_depth == 0
+            || move_index < late_move_reductions.full_depth_moves
+            || depth_left < late_move_reductions.min_depth
+            || !is_quiet_move(board, new_board, chess_move) {
+            return None;
+        }
+
+        match late_move_reductions.reduction(depth_left, move_index) {
+            0 => None,
+            reduction => Some(max_depth - reduction),
+        }
+    }
+}
+
+/// Neither a capture nor a promotion, and no check before or after it
+pub fn is_quiet_move(board: &Board, new_board: &Board, chess_move: ChessMove) -> bool {
+    !is_capture_or_promotion(board, chess_move)
+        && *board.checkers() == EMPTY
+        && *new_board.checkers() == EMPTY
+}
+
+/// Zugzwang guard: passing is only representative when the side to move has pawns and more than a single minor piece.
+/// Pawn endings and bare pieces are where every move can make things worse.
+pub fn has_null_move_material(board: &Board) -> bool {
+    let own_pieces = board.color_combined(board.side_to_move());
+    let count = |piece: Piece| (own_pieces & board.pieces(piece)).popcnt();
+
+    let minor_pieces = count(Piece::Knight) + count(Piece::Bishop);
+    let major_pieces = count(Piece::Rook) + count(Piece::Queen);
+
+    count(Piece::Pawn) > 0 && (major_pieces > 0 || minor_pieces > 1)
+}
+
+/// Whether the search after a null move by `side_to_move` is still outside the window.
+/// Mate scores after passing say nothing about the real moves, so they never cut off.
+pub fn is_null_move_cutoff(side_to_move: Color, evaluation: EvalBound, alpha: EvalBound, beta: EvalBound) -> bool {
+    let board_evaluation = evaluation.board_evaluation();
+    if !matches!(board_evaluation, BoardEvaluation::PieceScore(_)) {
+        return false;
+    }
+
+    match side_to_move {
+        Color::White => beta < EvalBound::Exact(board_evaluation),
+        Color::Black => EvalBound::Exact(board_evaluation) < alpha,
+    }
+}
+
+/// `is_null_move_cutoff` for the null window of MT, which tests against `test_value`
+pub fn is_null_move_test_cutoff(side_to_move: Color, evaluation: EvalBound, test_value: EvalBound) -> bool {
+    matches!(evaluation.board_evaluation(), BoardEvaluation::PieceScore(_))
+        && beats_test_value(side_to_move, evaluation, test_value)
+}
+
+/// Whether the null-window probe of a late move, searched with the window at alpha for white and beta for black,
+/// came out better for `side_to_move` than that window: the move then needs the full depth
+pub fn is_late_move_fail_high(side_to_move: Color, evaluation: EvalBound, alpha: EvalBound, beta: EvalBound) -> bool {
+    match side_to_move {
+        Color::White => evaluation.board_evaluation() > alpha.board_evaluation(),
+        Color::Black => evaluation.board_evaluation() < beta.board_evaluation(),
+    }
+}
+
+/// `is_late_move_fail_high` for MT, where `child_evaluation` still has to be bubbled up to compare it to the `test_value`
+pub fn is_late_move_test_fail_high(side_to_move: Color, child_evaluation: EvalBound, test_value: EvalBound) -> bool {
+    let mut evaluation = child_evaluation;
+    evaluation.set_board_evaluation(bubble_evaluation(evaluation.board_evaluation()));
+
+    beats_test_value(side_to_move, evaluation, test_value)
+}
+
+/// Whether `evaluation` makes a node of `side_to_move` fail high against the `test_value` of MT
+fn beats_test_value(side_to_move: Color, evaluation: EvalBound, test_value: EvalBound) -> bool {
+    match side_to_move {
+        Color::White => evaluation > test_value && evaluation.board_evaluation() > test_value.board_evaluation(),
+        Color::Black => evaluation < test_value && evaluation.board_evaluation() < test_value.board_evaluation(),
+    }
+}
+
+#[test]
+fn check_null_move_material() {
+    use std::str::FromStr;
+
+    assert!(has_null_move_material(&Board::default()));
+    // Pawn ending
+    assert!(!has_null_move_material(&Board::from_str("4k3/4p3/8/8/8/8/4P3/4K3 w - - 0 1").unwrap()));
+    // A single minor piece with pawns
+    assert!(!has_null_move_material(&Board::from_str("4k3/4p3/8/8/8/8/4P3/2B1K3 w - - 0 1").unwrap()));
+    // Pawnless
+    assert!(!has_null_move_material(&Board::from_str("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap()));
+    assert!(has_null_move_material(&Board::from_str("4k3/8/8/8/8/8/4P3/R3K3 w - - 0 1").unwrap()));
+    // Only the side to move counts
+    assert!(!has_null_move_material(&Board::from_str("4k3/4p3/8/8/8/8/4P3/R3K3 b - - 0 1").unwrap()));
+}
+
+#[test]
+fn check_null_move_depth() {
+    let pruning = PruningOptions {
+        null_move: Some(NullMovePruning { reduction: 2 }),
+        late_move_reductions: None,
+    };
+    let board = Board::default();
+
+    assert_eq!(pruning.null_move_depth(&board, 1, 6), Some(4));
+    assert_eq!(pruning.null_move_depth(&board, 0, 6), None);
+    assert_eq!(pruning.null_move_depth(&board, 4, 6), None);
+    assert_eq!(PruningOptions::default().null_move_depth(&board, 1, 6), None);
+}
+
+#[test]
+fn check_late_move_depth() {
+    use std::str::FromStr;
+
+    let pruning = PruningOptions {
+        null_move: None,
+        late_move_reductions: Some(LateMoveReductions::default()),
+    };
+    let board = Board::from_str("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3").unwrap();
+    let depth = |chess_move: &str, move_index: usize, current_depth: u32| {
+        let chess_move = ChessMove::from_str(chess_move).unwrap();
+        pruning.late_move_depth(&board, &board.make_move_new(chess_move), chess_move, move_index, current_depth, 8)
+    };
+
+    assert_eq!(depth("a2a3", 10, 1), Some(6));
+    // Too early, at the root, or too little depth left
+    assert_eq!(depth("a2a3", 1, 1), None);
+    assert_eq!(depth("a2a3", 10, 0), None);
+    assert_eq!(depth("a2a3", 10, 6), None);
+    // Captures are never reduced, other piece moves are
+    assert_eq!(depth("f3e5", 10, 1), None);
+    assert_eq!(depth("f1b5", 10, 1), Some(6));
+    assert_eq!(PruningOptions::default().late_move_depth(&board, &board, ChessMove::from_str("a2a3").unwrap(), 10, 1, 8), None);
+}
+
+#[test]
+fn check_quiet_move() {
+    use std::str::FromStr;
+
+    let is_quiet = |fen: &str, chess_move: &str| {
+        let board = Board::from_str(fen).unwrap();
+        let chess_move = ChessMove::from_str(chess_move).unwrap();
+        is_quiet_move(&board, &board.make_move_new(chess_move), chess_move)
+    };
+
+    assert!(is_quiet("4k3/8/8/8/8/8/4P3/R3K3 w - - 0 1", "e2e3"));
+    // Giving check, escaping check, en passant and promotion
+    assert!(!is_quiet("4k3/8/8/8/8/8/4P3/R3K3 w - - 0 1", "a1a8"));
+    assert!(!is_quiet("4k3/8/8/8/8/8/8/r3K3 w - - 0 1", "e1e2"));
+    assert!(!is_quiet("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6"));
+    assert!(!is_quiet("4k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7a8q"));
+}
+
+#[test]
+fn check_reduction_table() {
+    let late_move_reductions = LateMoveReductions::default();
+
+    // Deeper and later means at least as much reduction
+    assert_eq!(late_move_reductions.reduction(3, 3), 1);
+    assert!(late_move_reductions.reduction(20, 30) >= late_move_reductions.reduction(6, 30));
+    assert!(late_move_reductions.reduction(20, 30) >= late_move_reductions.reduction(20, 4));
+    // Always one ply left
+    assert_eq!(late_move_reductions.reduction(2, 30), 0);
+    assert_eq!(LateMoveReductions::new(0, 100_000).reduction(20, 30), 0);
+}
@@ -0,0 +1,56 @@
+use chess::Board;
+use crate::analysis::database::rows::{MTSearchRow, PositionSearchRow};
+use crate::core::search::alpha_beta::{search_window, SearchWindow};
+use crate::core::search::iterative_deepening::depth_first_iterative_deepening_search;
+use crate::core::search::search_control::SearchControl;
+use crate::core::search::search_result::SearchResult;
+use crate::core::search::transpositions::TranspositionTable;
+use crate::input::protocol_interpreter::CalculateOptions;
+
+// Principal Variation Search (NegaScout): alpha-beta, where every move after the first
+// only has to prove it is no better than the best so far, with a null window.
+// The nodes are those of `search_alpha_beta`, in its `pvs` mode.
+
+pub fn pvs_iterative_deepening_search<T: SearchResult + Default + Clone, L>(
+    board: &Board,
+    transposition_table: &mut Box<dyn TranspositionTable>,
+    visited_boards: Vec<u64>,
+    options: CalculateOptions,
+    search_logging: L,
+    control: &SearchControl,
+) -> (T, u32, u32) where
+    L: Fn(PositionSearchRow, Vec<MTSearchRow>) { // (SearchResult, depth, selective_depth)
+    depth_first_iterative_deepening_search(
+        board,
+        transposition_table,
+        visited_boards,
+        options,
+        search_pvs_window_pruned,
+        search_logging,
+        control,
+    )
+}
+
+pub fn search_pvs_depth_pruned<T: SearchResult + Default>(
+    board: &Board,
+    // transposition_table: &mut impl TranspositionTable,
+    transposition_table: &mut Box<dyn TranspositionTable>,
+    visited_boards: Vec<u64>,
+    depth: u32,
+    selective_depth: Option<u32>,
+    control: &SearchControl,
+) -> (T, PositionSearchRow) {
+    search_pvs_window_pruned(board, transposition_table, visited_boards, SearchWindow::full(depth, selective_depth), control)
+}
+
+/// A search that only tells the evaluation apart within the `window`
+pub fn search_pvs_window_pruned<T: SearchResult + Default>(
+    board: &Board,
+    // transposition_table: &mut impl TranspositionTable,
+    transposition_table: &mut Box<dyn TranspositionTable>,
+    visited_boards: Vec<u64>,
+    window: SearchWindow,
+    control: &SearchControl,
+) -> (T, PositionSearchRow) {
+    search_window(board, transposition_table, visited_boards, window, true, control)
+}
@@ -0,0 +1,354 @@
+use std::cell::RefCell;
+use std::mem;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+use chess::{Board, BoardStatus, ChessMove};
+use crate::core::castling::Chess960Castling;
+use crate::core::evaluation::{single_evaluation, Evaluator};
+use crate::core::score::{BoardEvaluation, Centipawns};
+use crate::core::search::aspiration::AspirationWindows;
+use crate::core::search::extensions::ExtensionOptions;
+use crate::core::search::info::{InfoCallback, IterationInfo};
+use crate::core::search::move_ordering::OrderingState;
+use crate::core::search::pruning::PruningOptions;
+use crate::core::search::time_management::{Clock, SystemClock, TimeManager};
+use crate::input::protocol_interpreter::CalculateOptions;
+
+/// Gives every search its own `ordering_id`
+static NEXT_ORDERING_ID: AtomicU64 = AtomicU64::new(1);
+
+thread_local! {
+    /// The move ordering of the last search on this thread, by the `ordering_id` of its control
+    static ORDERING: RefCell<(u64, OrderingState)> = RefCell::new((0, OrderingState::new()));
+}
+
+fn next_ordering_id() -> u64 {
+    NEXT_ORDERING_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// The shared signal used to interrupt a running search.
+/// Clones share the same signal, so one clone can be handed to the search thread,
+/// while another stays with the thread that listens for `stop` and `quit`.
+#[derive(Clone, Debug)]
+pub struct SearchControl {
+    stop: Arc<AtomicBool>,
+    pondering: Arc<AtomicBool>, // Time limits don't apply until `ponder_hit`
+    clock: Arc<dyn Clock>,
+    limits_start: Arc<AtomicU64>, // ms on the `clock` at which the time limits started counting
+    time_manager: Option<Arc<Mutex<TimeManager>>>,
+    hard_limit: Option<Duration>, // Copied from the `time_manager`, to check it at every node without locking
+    nodes: Arc<AtomicU64>,
+    node_limit: Option<u64>,
+    root_moves: Vec<ChessMove>, // The only moves searched at the root, unless empty
+    multi_pv: usize, // The number of best root moves to find, each with an exact value
+    info_callback: Option<InfoCallback>, // Gets the search info instead of the GUI
+    pruning: PruningOptions,
+    extensions: ExtensionOptions,
+    aspiration: Option<AspirationWindows>, // Only the alpha-beta searches narrow their window
+    depth_offset: u32, // How much deeper the iterative deepening starts, to stagger the threads of a parallel search
+    ordering_id: u64, // The move ordering is learned during the search, so it's new for every search, see `with_ordering`
+    castling: Chess960Castling, // The Chess960 castling rights of the searched position
+    evaluator: Option<Evaluator>, // Scores the leaves instead of the piece-square score the search keeps up to date
+}
+
+impl Default for SearchControl {
+    fn default() -> Self {
+        Self::with_clock(Arc::new(SystemClock::new()))
+    }
+}
+
+impl SearchControl {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A control that takes its time from `clock`, rather than the system clock
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            stop: Arc::new(AtomicBool::new(false)),
+            pondering: Arc::new(AtomicBool::new(false)),
+            clock,
+            limits_start: Arc::new(AtomicU64::new(0)),
+            time_manager: None,
+            hard_limit: None,
+            nodes: Arc::new(AtomicU64::new(0)),
+            node_limit: None,
+            root_moves: vec![],
+            multi_pv: 1,
+            info_callback: None,
+            pruning: PruningOptions::default(),
+            extensions: ExtensionOptions::default(),
+            aspiration: None,
+            depth_offset: 0,
+            ordering_id: next_ordering_id(),
+            castling: Chess960Castling::default(),
+            evaluator: None,
+        }
+    }
+
+    /// Returns a control sharing the same stop signal, that also enforces the limits of a search on `board`.
+    /// The time limits start counting now, or at the `ponder_hit` when pondering.
+    /// Limits that are only checked between iterations are left to `is_still_searching`.
+    pub fn with_limits(&self, calculate_options: &CalculateOptions, board: &Board, move_overhead: Duration) -> Self {
+        self.limits_start.store(self.clock.elapsed().as_millis() as u64, Ordering::Relaxed);
+        let time_manager = TimeManager::new(calculate_options, board.side_to_move(), move_overhead);
+
+        Self {
+            stop: self.stop.clone(),
+            pondering: self.pondering.clone(),
+            clock: self.clock.clone(),
+            limits_start: self.limits_start.clone(),
+            hard_limit: time_manager.as_ref().map(TimeManager::hard_limit),
+            time_manager: time_manager.map(|x| Arc::new(Mutex::new(x))),
+            nodes: Arc::new(AtomicU64::new(0)),
+            node_limit: calculate_options.nodes,
+            root_moves: calculate_options.search_moves.iter()
+                .filter(|x| self.castling.is_legal(board, **x))
+                .copied()
+                .collect(),
+            multi_pv: self.multi_pv,
+            info_callback: self.info_callback.clone(),
+            pruning: self.pruning,
+            extensions: self.extensions,
+            aspiration: self.aspiration,
+            depth_offset: self.depth_offset,
+            ordering_id: next_ordering_id(),
+            castling: self.castling,
+            evaluator: self.evaluator,
+        }
+    }
+
+    /// Set before `with_limits`, which checks the root moves against it
+    pub fn with_castling(mut self, castling: Chess960Castling) -> Self {
+        self.castling = castling;
+        self
+    }
+
+    pub fn castling(&self) -> Chess960Castling {
+        self.castling
+    }
+
+    pub fn with_evaluator(mut self, evaluator: Option<Evaluator>) -> Self {
+        self.evaluator = evaluator;
+        self
+    }
+
+    /// The evaluation of the position a search starts from
+    pub fn evaluate(&self, board: &Board, board_status: BoardStatus) -> BoardEvaluation {
+        self.evaluator.unwrap_or(single_evaluation)(board, board_status)
+    }
+
+    /// The evaluation of a leaf, whose piece-square score is `simple_evaluation`
+    #[inline]
+    pub fn evaluate_leaf(&self, board: &Board, board_status: BoardStatus, simple_evaluation: Centipawns) -> BoardEvaluation {
+        match self.evaluator {
+            Some(evaluator) => evaluator(board, board_status),
+            None => BoardEvaluation::PieceScore(simple_evaluation),
+        }
+    }
+
+    pub fn with_multi_pv(mut self, multi_pv: usize) -> Self {
+        self.multi_pv = multi_pv.max(1);
+        self
+    }
+
+    pub fn multi_pv(&self) -> usize {
+        self.multi_pv
+    }
+
+    pub fn with_pruning(mut self, pruning: PruningOptions) -> Self {
+        self.pruning = pruning;
+        self
+    }
+
+    pub fn pruning(&self) -> &PruningOptions {
+        &self.pruning
+    }
+
+    pub fn with_extensions(mut self, extensions: ExtensionOptions) -> Self {
+        self.extensions = extensions;
+        self
+    }
+
+    pub fn extensions(&self) -> &ExtensionOptions {
+        &self.extensions
+    }
+
+    pub fn with_aspiration(mut self, aspiration: Option<AspirationWindows>) -> Self {
+        self.aspiration = aspiration;
+        self
+    }
+
+    pub fn aspiration(&self) -> Option<AspirationWindows> {
+        self.aspiration
+    }
+
+    pub fn depth_offset(&self) -> u32 {
+        self.depth_offset
+    }
+
+    /// A control for a helper thread of a parallel search, starting `depth_offset` plies deeper.
+    /// It shares the stop signal and the limits, but leaves the timing of the iterations and the info to the main thread.
+    pub fn helper(&self, depth_offset: u32) -> Self {
+        Self {
+            time_manager: None, // Searches until the main thread stops it
+            multi_pv: 1,
+            info_callback: Some(InfoCallback::new(|_| {})),
+            depth_offset,
+            ordering_id: next_ordering_id(),
+            ..self.clone()
+        }
+    }
+
+    /// Runs `f` on the killers, history and countermoves of this search.
+    /// They are kept by the thread, so the search doesn't lock them at every node; a search on another thread starts over.
+    pub fn with_ordering<R>(&self, f: impl FnOnce(&mut OrderingState) -> R) -> R {
+        ORDERING.with(|x| {
+            let mut ordering = x.borrow_mut();
+            if ordering.0 != self.ordering_id {
+                *ordering = (self.ordering_id, OrderingState::new());
+            }
+
+            f(&mut ordering.1)
+        })
+    }
+
+    /// Takes the move ordering of this search off the thread, to carry on with it on another one through `resume_ordering`
+    pub fn take_ordering(&self) -> OrderingState {
+        self.with_ordering(mem::take)
+    }
+
+    pub fn resume_ordering(&self, ordering: OrderingState) {
+        ORDERING.with(|x| *x.borrow_mut() = (self.ordering_id, ordering));
+    }
+
+    pub fn with_info_callback(mut self, info_callback: Option<InfoCallback>) -> Self {
+        self.info_callback = info_callback;
+        self
+    }
+
+    /// Hands `info` to the info callback; returns false if there is none, and the info is for the GUI.
+    pub fn report_info(&self, info: &IterationInfo) -> bool {
+        match &self.info_callback {
+            Some(info_callback) => {
+                info_callback.call(info);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Returns a control that searches the same root moves, except for `excluded`; `None` if no moves remain
+    pub fn excluding_root_moves(&self, board: &Board, excluded: &[ChessMove]) -> Option<Self> {
+        let root_moves: Vec<ChessMove> = self.restrict_root_moves(self.castling.legal_moves(board), 0)
+            .into_iter()
+            .filter(|x| !excluded.contains(x))
+            .collect();
+
+        // No root moves would mean all of them
+        if root_moves.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            root_moves,
+            ..self.clone()
+        })
+    }
+
+    /// The time passed since the time limits started counting
+    pub fn elapsed(&self) -> Duration {
+        self.clock.elapsed().saturating_sub(Duration::from_millis(self.limits_start.load(Ordering::Relaxed)))
+    }
+
+    /// Registers the best move of a completed iteration, so the time manager can judge its stability
+    pub fn update_best_move(&self, best_move: ChessMove) {
+        if let Some(time_manager) = &self.time_manager {
+            time_manager.lock().expect("time manager poisoned").update_best_move(best_move);
+        }
+    }
+
+    /// Whether the time manager allows starting another iteration; always true without time limits, or while pondering
+    pub fn has_time_for_iteration(&self) -> bool {
+        if self.is_pondering() {
+            return true;
+        }
+
+        match &self.time_manager {
+            Some(time_manager) => time_manager.lock().expect("time manager poisoned").should_start_iteration(self.elapsed()),
+            None => true,
+        }
+    }
+
+    /// Set from the thread reading the input, before the search starts: a `ponderhit` may arrive before the search thread gets going.
+    pub fn set_pondering(&self, pondering: bool) {
+        self.pondering.store(pondering, Ordering::Relaxed);
+    }
+
+    pub fn is_pondering(&self) -> bool {
+        self.pondering.load(Ordering::Relaxed)
+    }
+
+    /// The opponent played the expected move: keep searching, but now on our own clock.
+    pub fn ponder_hit(&self) {
+        self.limits_start.store(self.clock.elapsed().as_millis() as u64, Ordering::Relaxed);
+        self.pondering.store(false, Ordering::Relaxed);
+    }
+
+    /// Asks every search using this control to return as soon as possible.
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    /// Clears the stop signal, so a new search can be started.
+    pub fn reset(&self) {
+        self.stop.store(false, Ordering::Relaxed);
+    }
+
+    /// Checked at every node: once true, the result of the running iteration is worthless.
+    #[inline]
+    pub fn is_stopped(&self) -> bool {
+        if self.stop.load(Ordering::Relaxed) {
+            return true;
+        }
+
+        let deadline_passed = !self.is_pondering() && self.hard_limit.is_some_and(|x| self.elapsed() >= x);
+        let node_limit_reached = self.node_limit.is_some_and(|x| self.nodes_searched() >= x);
+        if deadline_passed || node_limit_reached {
+            // Latch, so the other clones don't have to check again
+            self.stop();
+            return true;
+        }
+
+        false
+    }
+
+    /// Called once for every node visited, to enforce the node limit
+    #[inline]
+    pub fn count_node(&self) {
+        self.nodes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn nodes_searched(&self) -> u64 {
+        self.nodes.load(Ordering::Relaxed)
+    }
+
+    /// Whether the root node at `current_depth` only searches some of its moves.
+    /// Its evaluation then doesn't hold for the position, so it must stay out of the transposition table.
+    #[inline]
+    pub fn is_restricted_root(&self, current_depth: u32) -> bool {
+        current_depth == 0 && !self.root_moves.is_empty()
+    }
+
+    /// Removes the moves that shouldn't be searched, if this is the root node
+    pub fn restrict_root_moves(&self, moves: Vec<ChessMove>, current_depth: u32) -> Vec<ChessMove> {
+        if !self.is_restricted_root(current_depth) {
+            return moves;
+        }
+
+        moves.into_iter()
+            .filter(|x| self.root_moves.contains(x))
+            .collect()
+    }
+}
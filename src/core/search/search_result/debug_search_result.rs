@@ -8,6 +8,7 @@ pub struct DebugSearchResult {
     pub best_move: ChessMove,
     pub board_evaluation: EvalBound,
     pub nodes_searched: u32,
+    pub selective_depth: u32, // The deepest ply reached, counted from the root
     pub critical_path: Vec<ChessMove>, // The line of play of best moves (in reverse order, first move is at the end)
 }
 
@@ -22,6 +23,7 @@ impl DebugSearchResult {
             best_move,
             board_evaluation,
             nodes_searched: nodes_searched.unwrap_or(1),
+            selective_depth: 0,
             critical_path: critical_path.unwrap_or(Vec::new()),
         }
     }
@@ -61,6 +63,14 @@ impl SearchResult for DebugSearchResult {
         Some(self.nodes_searched)
     }
 
+    fn set_selective_depth(&mut self, selective_depth: Option<u32>) {
+        self.selective_depth = selective_depth.unwrap_or(0);
+    }
+
+    fn selective_depth(&self) -> Option<u32> {
+        Some(self.selective_depth)
+    }
+
     fn set_critical_path(&mut self, critical_path: Option<Vec<ChessMove>>) {
         self.critical_path = critical_path.unwrap_or(Vec::new());
     }
@@ -81,6 +91,7 @@ impl Default for DebugSearchResult {
             best_move: ChessMove::default(),
             board_evaluation: EvalBound::Exact(BoardEvaluation::PieceScore(Centipawns::new(0))),
             nodes_searched: 0,
+            selective_depth: 0,
             critical_path: Vec::new(),
         }
     }
@@ -53,6 +53,12 @@ impl SearchResult for MinimalSearchResult {
         None
     }
 
+    fn set_selective_depth(&mut self, _selective_depth: Option<u32>) {}
+
+    fn selective_depth(&self) -> Option<u32> {
+        None
+    }
+
     fn set_critical_path(&mut self, critical_path: Option<Vec<ChessMove>>) {
         ()
     }
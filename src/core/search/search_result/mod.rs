@@ -21,8 +21,22 @@ pub trait SearchResult {
     fn set_nodes_searched(&mut self, nodes_searched: Option<u32>);
     fn nodes_searched(&self) -> Option<u32>;
 
+    fn set_selective_depth(&mut self, selective_depth: Option<u32>);
+    fn selective_depth(&self) -> Option<u32>; // The deepest ply reached below the node
+
     fn set_critical_path(&mut self, critical_path: Option<Vec<ChessMove>>);
     fn prepend_move(&mut self, chess_move: ChessMove);
     fn critical_path(&self) -> Option<Vec<ChessMove>>;
 }
 
+
+/// The deepest ply reached by `search_result`, the search of a child of a node at `current_depth`
+pub fn reached_ply<T: SearchResult>(current_depth: u32, search_result: &T) -> u32 {
+    search_result.selective_depth().unwrap_or(0).max(current_depth + 1)
+}
+
+/// `search_result`, as having reached `selective_depth`
+pub fn with_selective_depth<T: SearchResult>(mut search_result: T, selective_depth: u32) -> T {
+    search_result.set_selective_depth(Some(selective_depth));
+    search_result
+}
@@ -0,0 +1,385 @@
+// Every search algorithm behind one interface, and the registry the engine, the tests and `store_analysis` pick them from.
+
+use chess::Board;
+use crate::analysis::database;
+use crate::analysis::database::rows::{MTSearchRow, PositionSearchRow};
+use crate::analysis::match_orchestration::SearchAlgorithm;
+use crate::analysis::mtd_h::mtd_h_iterative_deepening_search;
+use crate::analysis::mtd_h_utils::MtdHParams;
+use crate::analysis::parallel_mtd::{parallel_mtd_iterative_deepening_search, ParallelMtdParams};
+use crate::core::score::BoardEvaluation;
+use crate::core::search::conspiracy_counter::ConspiracyCounter;
+use crate::core::search::conspiracy_search::merging::MergeFn;
+use crate::core::search::conspiracy_search::mtd_w_conspiracy;
+use crate::core::search::iterative_deepening::iterative_deepening_search;
+use crate::core::search::mtdbi::{determine_mtdbi_step, mtdbi_iterative_deepening_search};
+use crate::core::search::mtdf::{determine_mtdf_step, mtdf_iterative_deepening_search};
+use crate::core::search::pvs::pvs_iterative_deepening_search;
+use crate::core::search::search_control::SearchControl;
+use crate::core::search::search_result::debug_search_result::DebugSearchResult;
+use crate::core::search::transpositions::TranspositionTable;
+use crate::input::engine_options::EngineOptions;
+use crate::input::protocol_interpreter::CalculateOptions;
+
+/// What every search algorithm returns
+#[derive(Clone, Debug)]
+pub struct SearchOutput<T> {
+    pub search_result: T,
+    pub conspiracy_counter: Option<ConspiracyCounter>, // Only kept by the conspiracy searches
+    pub depth: u32,
+    pub selective_depth: u32,
+}
+
+impl<T> From<(T, u32, u32)> for SearchOutput<T> {
+    fn from((search_result, depth, selective_depth): (T, u32, u32)) -> Self {
+        SearchOutput {
+            search_result,
+            conspiracy_counter: None,
+            depth,
+            selective_depth,
+        }
+    }
+}
+
+impl<T> From<(T, ConspiracyCounter, u32, u32)> for SearchOutput<T> {
+    fn from((search_result, conspiracy_counter, depth, selective_depth): (T, ConspiracyCounter, u32, u32)) -> Self {
+        SearchOutput {
+            search_result,
+            conspiracy_counter: Some(conspiracy_counter),
+            depth,
+            selective_depth,
+        }
+    }
+}
+
+/// An iterative deepening search, handing the DB rows of every iteration to `search_logging`
+pub trait Searcher {
+    fn search(
+        &self,
+        board: &Board,
+        transposition_table: &mut Box<dyn TranspositionTable>,
+        visited_boards: Vec<u64>,
+        options: CalculateOptions,
+        search_logging: &dyn Fn(PositionSearchRow, Vec<MTSearchRow>),
+        control: &SearchControl,
+    ) -> SearchOutput<DebugSearchResult>;
+}
+
+pub struct AlphaBetaSearcher;
+
+impl Searcher for AlphaBetaSearcher {
+    fn search(
+        &self,
+        board: &Board,
+        transposition_table: &mut Box<dyn TranspositionTable>,
+        visited_boards: Vec<u64>,
+        options: CalculateOptions,
+        search_logging: &dyn Fn(PositionSearchRow, Vec<MTSearchRow>),
+        control: &SearchControl,
+    ) -> SearchOutput<DebugSearchResult> {
+        iterative_deepening_search(
+            board,
+            transposition_table,
+            visited_boards,
+            options,
+            search_logging,
+            control,
+        ).into()
+    }
+}
+
+pub struct PvsSearcher;
+
+impl Searcher for PvsSearcher {
+    fn search(
+        &self,
+        board: &Board,
+        transposition_table: &mut Box<dyn TranspositionTable>,
+        visited_boards: Vec<u64>,
+        options: CalculateOptions,
+        search_logging: &dyn Fn(PositionSearchRow, Vec<MTSearchRow>),
+        control: &SearchControl,
+    ) -> SearchOutput<DebugSearchResult> {
+        pvs_iterative_deepening_search(
+            board,
+            transposition_table,
+            visited_boards,
+            options,
+            search_logging,
+            control,
+        ).into()
+    }
+}
+
+pub struct MtdfSearcher;
+
+impl Searcher for MtdfSearcher {
+    fn search(
+        &self,
+        board: &Board,
+        transposition_table: &mut Box<dyn TranspositionTable>,
+        visited_boards: Vec<u64>,
+        options: CalculateOptions,
+        search_logging: &dyn Fn(PositionSearchRow, Vec<MTSearchRow>),
+        control: &SearchControl,
+    ) -> SearchOutput<DebugSearchResult> {
+        mtdf_iterative_deepening_search(
+            board,
+            transposition_table,
+            visited_boards,
+            options,
+            search_logging,
+            control,
+        ).into()
+    }
+}
+
+pub struct MtdbiSearcher;
+
+impl Searcher for MtdbiSearcher {
+    fn search(
+        &self,
+        board: &Board,
+        transposition_table: &mut Box<dyn TranspositionTable>,
+        visited_boards: Vec<u64>,
+        options: CalculateOptions,
+        search_logging: &dyn Fn(PositionSearchRow, Vec<MTSearchRow>),
+        control: &SearchControl,
+    ) -> SearchOutput<DebugSearchResult> {
+        mtdbi_iterative_deepening_search(
+            board,
+            transposition_table,
+            visited_boards,
+            options,
+            search_logging,
+            control,
+        ).into()
+    }
+}
+
+/// MTD with the step function `step_fn`, counting conspiracy numbers on the way
+pub struct MtdConspiracySearcher {
+    pub step_fn: fn(BoardEvaluation, BoardEvaluation, BoardEvaluation) -> BoardEvaluation,
+    pub bucket_size: u32,
+    pub num_buckets: usize,
+    pub merge_fn: MergeFn,
+}
+
+impl Searcher for MtdConspiracySearcher {
+    fn search(
+        &self,
+        board: &Board,
+        transposition_table: &mut Box<dyn TranspositionTable>,
+        visited_boards: Vec<u64>,
+        options: CalculateOptions,
+        search_logging: &dyn Fn(PositionSearchRow, Vec<MTSearchRow>),
+        control: &SearchControl,
+    ) -> SearchOutput<DebugSearchResult> {
+        mtd_w_conspiracy::mtd_iterative_deepening_search(
+            board,
+            transposition_table,
+            visited_boards,
+            options,
+            self.step_fn,
+            self.bucket_size,
+            self.num_buckets,
+            self.merge_fn,
+            search_logging,
+            control,
+        ).into()
+    }
+}
+
+/// MTD-H: the test points come from the probability distributions of `mtd_h_params`
+pub struct MtdHSearcher {
+    pub bucket_size: u32,
+    pub num_buckets: usize,
+    pub merge_fn: MergeFn,
+    pub mtd_h_params: Vec<MtdHParams>,
+}
+
+impl Searcher for MtdHSearcher {
+    fn search(
+        &self,
+        board: &Board,
+        transposition_table: &mut Box<dyn TranspositionTable>,
+        visited_boards: Vec<u64>,
+        options: CalculateOptions,
+        search_logging: &dyn Fn(PositionSearchRow, Vec<MTSearchRow>),
+        control: &SearchControl,
+    ) -> SearchOutput<DebugSearchResult> {
+        mtd_h_iterative_deepening_search(
+            board,
+            transposition_table,
+            visited_boards,
+            options,
+            self.bucket_size,
+            self.num_buckets,
+            self.merge_fn,
+            search_logging,
+            &self.mtd_h_params,
+            control,
+        ).into()
+    }
+}
+
+/// MTD probing `probes` test values at once, from the distributions of `mtd_h_params` when there are any
+pub struct ParallelMtdSearcher {
+    pub probes: usize,
+    pub bucket_size: u32,
+    pub num_buckets: usize,
+    pub merge_fn: MergeFn,
+    pub mtd_h_params: Vec<MtdHParams>,
+}
+
+impl Searcher for ParallelMtdSearcher {
+    fn search(
+        &self,
+        board: &Board,
+        transposition_table: &mut Box<dyn TranspositionTable>,
+        visited_boards: Vec<u64>,
+        options: CalculateOptions,
+        search_logging: &dyn Fn(PositionSearchRow, Vec<MTSearchRow>),
+        control: &SearchControl,
+    ) -> SearchOutput<DebugSearchResult> {
+        parallel_mtd_iterative_deepening_search(
+            board,
+            transposition_table,
+            visited_boards,
+            options,
+            ParallelMtdParams {
+                probes: self.probes,
+                bucket_size: self.bucket_size,
+                num_buckets: self.num_buckets,
+                merge_fn: self.merge_fn,
+                mtd_h_params: &self.mtd_h_params,
+            },
+            search_logging,
+            control,
+        ).into()
+    }
+}
+
+/// What the search algorithm selected in the `engine_options` is built from
+#[derive(Copy, Clone)]
+pub struct SearchSetup<'a> {
+    pub engine_options: &'a EngineOptions,
+    pub mtd_h_params: &'a [MtdHParams],
+}
+
+impl SearchSetup<'_> {
+    pub fn searcher(&self) -> Box<dyn Searcher> {
+        algorithm_entry(self.engine_options.algorithm).build(self.engine_options, self.mtd_h_params)
+    }
+}
+
+/// A search algorithm and how to set it up
+pub struct AlgorithmEntry {
+    pub algorithm: SearchAlgorithm,
+    pub uci_name: &'static str,
+    pub db_algorithm: database::rows::SearchAlgorithm, // Conspiracy searches are stored as the search they count for
+    pub is_conspiracy_search: bool,
+    build: fn(&EngineOptions, &[MtdHParams]) -> Box<dyn Searcher>,
+}
+
+impl AlgorithmEntry {
+    pub fn build(&self, engine_options: &EngineOptions, mtd_h_params: &[MtdHParams]) -> Box<dyn Searcher> {
+        (self.build)(engine_options, mtd_h_params)
+    }
+}
+
+/// Every algorithm the engine can search with; a new `SearchAlgorithm` only needs an entry here
+pub static ALGORITHMS: [AlgorithmEntry; 8] = [
+    AlgorithmEntry {
+        algorithm: SearchAlgorithm::MTDBiIterativeDeepeningConspiracy,
+        uci_name: "MTD-bi Conspiracy",
+        db_algorithm: database::rows::SearchAlgorithm::MtdBi,
+        is_conspiracy_search: true,
+        build: |engine_options, _| Box::new(MtdConspiracySearcher {
+            step_fn: determine_mtdbi_step,
+            bucket_size: engine_options.bucket_size,
+            num_buckets: engine_options.num_buckets,
+            merge_fn: engine_options.conspiracy_merge_fn(),
+        }),
+    },
+    AlgorithmEntry {
+        algorithm: SearchAlgorithm::MTDFIterativeDeepeningConspiracy,
+        uci_name: "MTD-f Conspiracy",
+        db_algorithm: database::rows::SearchAlgorithm::MtdF,
+        is_conspiracy_search: true,
+        build: |engine_options, _| Box::new(MtdConspiracySearcher {
+            step_fn: determine_mtdf_step,
+            bucket_size: engine_options.bucket_size,
+            num_buckets: engine_options.num_buckets,
+            merge_fn: engine_options.conspiracy_merge_fn(),
+        }),
+    },
+    AlgorithmEntry {
+        algorithm: SearchAlgorithm::MTDBiIterativeDeepening,
+        uci_name: "MTD-bi",
+        db_algorithm: database::rows::SearchAlgorithm::MtdBi,
+        is_conspiracy_search: false,
+        build: |_, _| Box::new(MtdbiSearcher),
+    },
+    AlgorithmEntry {
+        algorithm: SearchAlgorithm::MTDFIterativeDeepening,
+        uci_name: "MTD-f",
+        db_algorithm: database::rows::SearchAlgorithm::MtdF,
+        is_conspiracy_search: false,
+        build: |_, _| Box::new(MtdfSearcher),
+    },
+    AlgorithmEntry {
+        algorithm: SearchAlgorithm::AlphaBetaIterativeDeepening,
+        uci_name: "Alpha-Beta",
+        db_algorithm: database::rows::SearchAlgorithm::AlphaBeta,
+        is_conspiracy_search: false,
+        build: |_, _| Box::new(AlphaBetaSearcher),
+    },
+    AlgorithmEntry {
+        algorithm: SearchAlgorithm::MTDHIterativeDeepening,
+        uci_name: "MTD-H",
+        db_algorithm: database::rows::SearchAlgorithm::MtdH,
+        is_conspiracy_search: true,
+        build: |engine_options, mtd_h_params| Box::new(MtdHSearcher {
+            bucket_size: engine_options.bucket_size,
+            num_buckets: engine_options.num_buckets,
+            merge_fn: engine_options.conspiracy_merge_fn(),
+            mtd_h_params: mtd_h_params.to_vec(),
+        }),
+    },
+    AlgorithmEntry {
+        algorithm: SearchAlgorithm::PVSIterativeDeepening,
+        uci_name: "PVS",
+        db_algorithm: database::rows::SearchAlgorithm::Pvs,
+        is_conspiracy_search: false,
+        build: |_, _| Box::new(PvsSearcher),
+    },
+    AlgorithmEntry {
+        algorithm: SearchAlgorithm::ParallelMTDIterativeDeepening,
+        uci_name: "Parallel MTD",
+        db_algorithm: database::rows::SearchAlgorithm::ParallelMtd,
+        is_conspiracy_search: true,
+        build: |engine_options, mtd_h_params| Box::new(ParallelMtdSearcher {
+            probes: engine_options.mtd_probes as usize,
+            bucket_size: engine_options.bucket_size,
+            num_buckets: engine_options.num_buckets,
+            merge_fn: engine_options.conspiracy_merge_fn(),
+            mtd_h_params: mtd_h_params.to_vec(),
+        }),
+    },
+];
+
+pub fn algorithm_entry(algorithm: SearchAlgorithm) -> &'static AlgorithmEntry {
+    ALGORITHMS.iter()
+        .find(|x| x.algorithm == algorithm)
+        .expect("every search algorithm is registered")
+}
+
+#[test]
+fn check_every_algorithm_registered() {
+    use clap::ValueEnum;
+
+    for algorithm in SearchAlgorithm::value_variants() {
+        assert_eq!(ALGORITHMS.iter().filter(|x| x.algorithm == *algorithm).count(), 1, "{algorithm:?}");
+    }
+}
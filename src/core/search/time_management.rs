@@ -0,0 +1,278 @@
+use std::cmp::min;
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use chess::{ChessMove, Color};
+use crate::input::protocol_interpreter::CalculateOptions;
+
+/// The number of moves the remaining clock time is divided over, if the GUI doesn't send `movestogo`
+const DEFAULT_MOVES_TO_GO: u64 = 30;
+/// The part of the increment that gets spent on top of the share of the remaining time
+const INCREMENT_USAGE: (u64, u64) = (3, 4);
+/// Never spend more than this part of the remaining time on a single move
+const MAX_TIME_USAGE: (u64, u64) = (4, 5);
+/// How many times the soft limit a single move may take, when the best move keeps changing
+const HARD_LIMIT_FACTOR: u64 = 4;
+/// The next iteration takes longer than all previous iterations together,
+/// so it is only started while less than this part of the soft limit has passed.
+const NEXT_ITERATION_FRACTION: f64 = 0.5;
+
+/// The source of time for the search limits, so they can be tested with a simulated clock.
+pub trait Clock: Debug + Send + Sync {
+    /// The time passed since some fixed moment
+    fn elapsed(&self) -> Duration;
+}
+
+#[derive(Debug)]
+pub struct SystemClock {
+    epoch: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self {
+            epoch: Instant::now(),
+        }
+    }
+}
+
+impl Clock for SystemClock {
+    fn elapsed(&self) -> Duration {
+        self.epoch.elapsed()
+    }
+}
+
+/// A clock that only moves when told to
+#[derive(Debug, Default)]
+pub struct SimulatedClock {
+    elapsed: AtomicU64, // in ms
+}
+
+impl SimulatedClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.elapsed.fetch_add(duration.as_millis() as u64, Ordering::Relaxed);
+    }
+}
+
+impl Clock for SimulatedClock {
+    fn elapsed(&self) -> Duration {
+        Duration::from_millis(self.elapsed.load(Ordering::Relaxed))
+    }
+}
+
+/// Decides how long to think on a move.
+/// The soft limit decides whether to start another iteration, and shrinks or grows with the stability of the best move.
+/// The hard limit aborts the search, even mid-iteration.
+#[derive(Clone, Debug)]
+pub struct TimeManager {
+    soft_limit: Duration,
+    hard_limit: Duration,
+    adjustable: bool, // Whether the soft limit depends on the best move stability; not so for a fixed `movetime`
+    best_move: Option<ChessMove>,
+    stable_iterations: u32, // The number of iterations in a row that found the same best move
+}
+
+impl TimeManager {
+    /// The time limits for `side_to_move`, or `None` if the search isn't bound by time.
+    /// The `move_overhead` is kept in reserve for the delay between the engine and the GUI's clock.
+    pub fn new(calculate_options: &CalculateOptions, side_to_move: Color, move_overhead: Duration) -> Option<Self> {
+        if calculate_options.infinite {
+            return None;
+        }
+
+        let overhead = move_overhead.as_millis() as u64;
+        let move_time = calculate_options.move_time
+            .map(|x| x.saturating_sub(overhead));
+        let clock_limits = calculate_options.clock(side_to_move)
+            .map(|(time, increment)| determine_clock_limits(time, increment, calculate_options.moves_to_go, overhead));
+
+        let (soft_limit, hard_limit, adjustable) = match (move_time, clock_limits) {
+            (None, None) => return None,
+            (Some(x), None) => (x, x, false),
+            (None, Some((soft, hard))) => (soft, hard, true),
+            // The move time caps whatever the clock allows
+            (Some(x), Some((soft, hard))) => (min(x, soft), min(x, hard), x > soft),
+        };
+
+        Some(Self {
+            soft_limit: Duration::from_millis(soft_limit),
+            hard_limit: Duration::from_millis(hard_limit),
+            adjustable,
+            best_move: None,
+            stable_iterations: 0,
+        })
+    }
+
+    /// The time after which the search has to be aborted
+    pub fn hard_limit(&self) -> Duration {
+        self.hard_limit
+    }
+
+    /// The time the search aims for, given how stable the best move has been
+    pub fn soft_limit(&self) -> Duration {
+        if !self.adjustable {
+            return self.soft_limit;
+        }
+
+        let factor = match self.stable_iterations {
+            0 => 1.5,
+            1 => 1.0,
+            2 => 0.8,
+            _ => 0.6,
+        };
+
+        min(self.soft_limit.mul_f64(factor), self.hard_limit)
+    }
+
+    /// Registers the best move of a completed iteration
+    pub fn update_best_move(&mut self, best_move: ChessMove) {
+        if self.best_move == Some(best_move) {
+            self.stable_iterations += 1;
+        } else {
+            self.stable_iterations = 0;
+        }
+
+        self.best_move = Some(best_move);
+    }
+
+    /// Whether there is time to start the next iteration, `elapsed` after the limits started counting
+    pub fn should_start_iteration(&self, elapsed: Duration) -> bool {
+        if !self.adjustable {
+            return elapsed < self.soft_limit;
+        }
+
+        elapsed < self.soft_limit().mul_f64(NEXT_ITERATION_FRACTION)
+    }
+}
+
+/// The soft and hard limit in ms, for `time` ms left on the clock
+fn determine_clock_limits(time: u64, increment: u64, moves_to_go: Option<u64>, overhead: u64) -> (u64, u64) {
+    let time_left = time.saturating_sub(overhead);
+    let moves_to_go = moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+    let max_time = time_left * MAX_TIME_USAGE.0 / MAX_TIME_USAGE.1;
+
+    let soft_limit = min(time_left / moves_to_go + increment * INCREMENT_USAGE.0 / INCREMENT_USAGE.1, max_time);
+    let hard_limit = min(soft_limit * HARD_LIMIT_FACTOR, max_time);
+
+    (soft_limit, hard_limit)
+}
+
+#[test]
+fn check_clock_limits() {
+    let options = CalculateOptions {
+        white_time: Some(60_000),
+        black_time: Some(30_000),
+        white_increment: Some(1_000),
+        ..Default::default()
+    };
+
+    let white = TimeManager::new(&options, Color::White, Duration::ZERO).unwrap();
+    assert_eq!(white.soft_limit, Duration::from_millis(60_000 / DEFAULT_MOVES_TO_GO + 750));
+    assert_eq!(white.hard_limit(), white.soft_limit * HARD_LIMIT_FACTOR as u32);
+
+    // Both sides get the same treatment
+    let black = TimeManager::new(&options, Color::Black, Duration::ZERO).unwrap();
+    assert_eq!(black.soft_limit, Duration::from_millis(30_000 / DEFAULT_MOVES_TO_GO));
+
+    let options = CalculateOptions {
+        moves_to_go: Some(1),
+        ..options
+    };
+    let last_move = TimeManager::new(&options, Color::Black, Duration::from_millis(1_000)).unwrap();
+    assert_eq!(last_move.hard_limit(), Duration::from_millis(29_000 * 4 / 5));
+    assert!(last_move.soft_limit() <= last_move.hard_limit());
+}
+
+#[test]
+fn check_move_overhead() {
+    let options = CalculateOptions::move_time(1_000);
+    let manager = TimeManager::new(&options, Color::White, Duration::from_millis(50)).unwrap();
+    assert_eq!(manager.hard_limit(), Duration::from_millis(950));
+
+    // Out of time: move right away
+    let options = CalculateOptions {
+        white_time: Some(40),
+        ..Default::default()
+    };
+    let manager = TimeManager::new(&options, Color::White, Duration::from_millis(50)).unwrap();
+    assert_eq!(manager.hard_limit(), Duration::ZERO);
+    assert!(!manager.should_start_iteration(Duration::ZERO));
+}
+
+#[test]
+fn check_no_time_limits() {
+    assert!(TimeManager::new(&CalculateOptions::infinite(), Color::White, Duration::ZERO).is_none());
+    assert!(TimeManager::new(&CalculateOptions::depth(5), Color::White, Duration::ZERO).is_none());
+
+    // Only the opponent's clock is known
+    let options = CalculateOptions {
+        black_time: Some(10_000),
+        ..Default::default()
+    };
+    assert!(TimeManager::new(&options, Color::White, Duration::ZERO).is_none());
+}
+
+#[test]
+fn check_best_move_stability() {
+    let options = CalculateOptions {
+        white_time: Some(300_000),
+        ..Default::default()
+    };
+    let mut manager = TimeManager::new(&options, Color::White, Duration::ZERO).unwrap();
+    let first_move = ChessMove::new(chess::Square::E2, chess::Square::E4, None);
+    let second_move = ChessMove::new(chess::Square::D2, chess::Square::D4, None);
+
+    manager.update_best_move(first_move);
+    let unstable_limit = manager.soft_limit();
+    manager.update_best_move(first_move);
+    manager.update_best_move(first_move);
+    manager.update_best_move(first_move);
+    let stable_limit = manager.soft_limit();
+    assert!(stable_limit < unstable_limit);
+
+    // A change of mind buys more time
+    manager.update_best_move(second_move);
+    assert_eq!(manager.soft_limit(), unstable_limit);
+    assert!(manager.soft_limit() <= manager.hard_limit());
+
+    let elapsed = stable_limit.mul_f64(NEXT_ITERATION_FRACTION);
+    assert!(manager.should_start_iteration(elapsed));
+    manager.update_best_move(second_move);
+    manager.update_best_move(second_move);
+    manager.update_best_move(second_move);
+    assert!(!manager.should_start_iteration(elapsed));
+}
+
+#[test]
+fn check_fixed_move_time() {
+    let mut manager = TimeManager::new(&CalculateOptions::move_time(500), Color::White, Duration::ZERO).unwrap();
+    let best_move = ChessMove::new(chess::Square::E2, chess::Square::E4, None);
+
+    for _ in 0..5 {
+        manager.update_best_move(best_move);
+    }
+
+    assert_eq!(manager.soft_limit(), Duration::from_millis(500));
+    assert!(manager.should_start_iteration(Duration::from_millis(499)));
+    assert!(!manager.should_start_iteration(Duration::from_millis(500)));
+}
+
+#[test]
+fn check_simulated_clock() {
+    let clock = SimulatedClock::new();
+    assert_eq!(clock.elapsed(), Duration::ZERO);
+
+    clock.advance(Duration::from_millis(250));
+    clock.advance(Duration::from_millis(250));
+    assert_eq!(clock.elapsed(), Duration::from_millis(500));
+}
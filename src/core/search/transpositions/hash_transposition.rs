@@ -44,4 +44,8 @@ impl TranspositionTable for HashTranspositionTable {
         }
         None
     }
+
+    fn into_shards(self: Box<Self>, _count: usize) -> Vec<Box<dyn TranspositionTable>> {
+        vec![self]
+    }
 }
@@ -1,21 +1,72 @@
-use std::cmp::max;
+use std::any::Any;
 use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::mem::size_of;
 use chess::{Board, ChessMove};
+use nohash::BuildNoHashHasher;
 use crate::core::search::{SearchDepth, SearchInfo};
-use crate::core::search::transpositions::hash_transposition::HashTranspositionTable;
-use crate::core::search::transpositions::{EvalBound, TranspositionTable};
+use crate::core::search::transpositions::{shard, EvalBound, TranspositionTable};
+
+/// Longer prime variations are cut off, so every entry has a bounded size; the moves furthest from the position go
+pub const MAX_STORED_VARIATION: usize = 16;
 
 #[derive(Clone, Debug)]
 pub struct HighDepthTranspositionTable {
     pub minimal_depth: SearchDepth,
-    transposition_table: HashTranspositionTable,
+    pub max_entries: usize, // Room is made once full, see `make_room`
+    generation: u32, // Counts the searches, to tell the entries of earlier ones apart
+    transposition_table: HashMap<Board, (SearchInfo, u32), BuildNoHashHasher<u64>>, // Also the generation that stored it
 }
 
 impl HighDepthTranspositionTable {
     pub fn new(minimal_depth: SearchDepth) -> Self {
         HighDepthTranspositionTable {
             minimal_depth,
-            transposition_table: HashTranspositionTable::default(),
+            max_entries: usize::MAX,
+            generation: 0,
+            transposition_table: HashMap::default(),
+        }
+    }
+
+    /// Creates a table that takes up roughly `size_mb` megabytes, counting the prime variations at their longest.
+    pub fn with_size_mb(minimal_depth: SearchDepth, size_mb: usize) -> Self {
+        HighDepthTranspositionTable {
+            minimal_depth,
+            max_entries: size_mb * 1024 * 1024 / Self::entry_size(),
+            generation: 0,
+            transposition_table: HashMap::default(),
+        }
+    }
+
+    /// The memory an entry takes up: the hash map keeps an extra control byte per bucket, and a bucket free per 8
+    fn entry_size() -> usize {
+        let bucket = size_of::<(Board, (SearchInfo, u32))>() + 1;
+        bucket * 8 / 7 + MAX_STORED_VARIATION * size_of::<ChessMove>()
+    }
+
+    pub fn len(&self) -> usize {
+        self.transposition_table.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transposition_table.is_empty()
+    }
+
+    /// Frees a quarter of the table, at least one entry: first the entries of earlier searches go, then the shallowest ones
+    fn make_room(&mut self) {
+        let target = self.max_entries - (self.max_entries / 4).max(1);
+        let excess = self.transposition_table.len().saturating_sub(target);
+        if excess == 0 {
+            return;
+        }
+
+        let generation = self.generation;
+        let mut entries = self.transposition_table.iter()
+            .map(|(board, x)| ((x.1 == generation, x.0.depth_searched), *board))
+            .collect::<Vec<_>>();
+        entries.select_nth_unstable_by_key(excess - 1, |x| x.0);
+        for (_, board) in &entries[..excess] {
+            self.transposition_table.remove(board);
         }
     }
 }
@@ -23,42 +74,175 @@ impl HighDepthTranspositionTable {
 impl TranspositionTable for HighDepthTranspositionTable {
     fn update(&mut self, board: &Board, search_depth: SearchDepth, evaluation: EvalBound, best_move: ChessMove, prime_variation: Option<Vec<ChessMove>>) {
         // Only keep entries of sufficient depth
-        if search_depth < self.minimal_depth {
+        if search_depth < self.minimal_depth || self.max_entries == 0 {
             return;
         }
 
-        let current_entry = self.transposition_table.entry(board.clone());
+        if self.transposition_table.len() >= self.max_entries && !self.transposition_table.contains_key(board) {
+            self.make_room();
+        }
+
+        let search_info = SearchInfo {
+            depth_searched: search_depth,
+            evaluation,
+            best_move,
+            prime_variation: prime_variation.map(|mut x| {
+                // Stored in reverse, like the critical path
+                x.drain(..x.len().saturating_sub(MAX_STORED_VARIATION));
+                x
+            }),
+        };
 
-        match current_entry {
-            Entry::Vacant(mut o) => {
-                o.insert(SearchInfo {
-                    depth_searched: search_depth,
-                    evaluation,
-                    best_move,
-                    prime_variation,
-                });
+        match self.transposition_table.entry(*board) {
+            Entry::Vacant(o) => {
+                o.insert((search_info, self.generation));
             },
             Entry::Occupied(mut o) => {
-                let search_info = o.get();
-
-                if search_info.depth_searched <= search_depth {
-                    o.insert(SearchInfo {
-                        depth_searched: search_depth,
-                        evaluation,
-                        best_move,
-                        prime_variation,
-                    });
+                // Entries of earlier searches are replaced, even by shallower ones
+                let (stored_info, stored_generation) = o.get();
+
+                if *stored_generation != self.generation || stored_info.depth_searched <= search_depth {
+                    o.insert((search_info, self.generation));
                 }
             },
         }
     }
 
     fn get_transposition(&mut self, board: &Board, minimal_search_depth: Option<SearchDepth>) -> Option<&SearchInfo> {
-        let search_info = self.transposition_table.get(board)?;
+        let (search_info, _) = self.transposition_table.get(board)?;
 
         if search_info.depth_searched >= minimal_search_depth.unwrap_or(SearchDepth::Single) {
             return Some(search_info);
         }
         None
     }
-}
\ No newline at end of file
+
+    fn new_search(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    /// Every shard gets its part of `max_entries`, so together they take up as much memory as the table
+    fn into_shards(self: Box<Self>, count: usize) -> Vec<Box<dyn TranspositionTable>> {
+        let mut shards = (0..count)
+            .map(|x| HighDepthTranspositionTable {
+                max_entries: self.max_entries / count + usize::from(x < self.max_entries % count),
+                transposition_table: HashMap::default(),
+                ..*self
+            })
+            .collect::<Vec<_>>();
+
+        for (board, entry) in self.transposition_table {
+            shards[shard(&board, count)].transposition_table.insert(board, entry);
+        }
+
+        shards.into_iter()
+            .map(|x| Box::new(x) as Box<dyn TranspositionTable>)
+            .collect()
+    }
+
+    fn merge_shards(&mut self, shards: Vec<Box<dyn TranspositionTable>>) {
+        for mut other in shards {
+            let other = other.as_any_mut()
+                .and_then(|x| x.downcast_mut::<HighDepthTranspositionTable>())
+                .expect("shards of the same table");
+
+            self.max_entries = self.max_entries.saturating_add(other.max_entries);
+            self.transposition_table.extend(other.transposition_table.drain());
+        }
+    }
+
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        Some(self)
+    }
+}
+
+#[test]
+fn check_replaces_once_full() {
+    use std::str::FromStr;
+    use chess::MoveGen;
+    use crate::core::score::{BoardEvaluation, Centipawns};
+
+    let evaluation = EvalBound::Exact(BoardEvaluation::PieceScore(Centipawns::new(0)));
+    let board = Board::default();
+    let children = MoveGen::new_legal(&board)
+        .map(|x| board.make_move_new(x))
+        .collect::<Vec<_>>();
+
+    let mut table = HighDepthTranspositionTable::new(SearchDepth::Depth(1));
+    table.max_entries = 8;
+    for (index, child) in children.iter().enumerate() {
+        let depth = if index < 4 { SearchDepth::Depth(5) } else { SearchDepth::Depth(2) };
+        table.update(child, depth, evaluation, ChessMove::default(), None);
+        assert!(table.len() <= table.max_entries);
+    }
+    // The deep entries survive making room within one search
+    for child in &children[..4] {
+        assert!(table.get_transposition(child, None).is_some());
+    }
+    let last = children.last().unwrap();
+    assert!(table.get_transposition(last, None).is_some());
+
+    // The entries of an earlier search make room first, however deep
+    table.new_search();
+    let kiwipete = Board::from_str("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+    let new_children = MoveGen::new_legal(&kiwipete)
+        .map(|x| kiwipete.make_move_new(x))
+        .take(table.max_entries)
+        .collect::<Vec<_>>();
+    for child in &new_children {
+        table.update(child, SearchDepth::Depth(1), evaluation, ChessMove::default(), None);
+    }
+    for child in &new_children {
+        assert!(table.get_transposition(child, None).is_some());
+    }
+}
+
+#[test]
+fn check_makes_room_for_one_quarter() {
+    use chess::MoveGen;
+    use crate::core::score::{BoardEvaluation, Centipawns};
+
+    let evaluation = EvalBound::Exact(BoardEvaluation::PieceScore(Centipawns::new(0)));
+    let board = Board::default();
+    let children = MoveGen::new_legal(&board)
+        .map(|x| board.make_move_new(x))
+        .collect::<Vec<_>>();
+
+    // All the same depth: a quarter goes, not every entry tied at the cutoff
+    let mut table = HighDepthTranspositionTable::new(SearchDepth::Depth(1));
+    table.max_entries = 8;
+    for child in &children[..8] {
+        table.update(child, SearchDepth::Depth(3), evaluation, ChessMove::default(), None);
+    }
+    table.update(&children[8], SearchDepth::Depth(3), evaluation, ChessMove::default(), None);
+    assert_eq!(table.len(), 7);
+
+    // A new search only clears as many of the old entries as needed
+    table.new_search();
+    for child in &children[9..11] {
+        table.update(child, SearchDepth::Depth(3), evaluation, ChessMove::default(), None);
+    }
+    assert_eq!(table.len(), 7);
+    for child in &children[9..11] {
+        assert!(table.get_transposition(child, None).is_some());
+    }
+}
+
+#[test]
+fn check_variation_is_capped() {
+    use std::str::FromStr;
+    use crate::core::score::{BoardEvaluation, Centipawns};
+
+    let evaluation = EvalBound::Exact(BoardEvaluation::PieceScore(Centipawns::new(0)));
+    let first_move = ChessMove::from_str("e2e4").unwrap();
+    let mut variation = vec![ChessMove::default(); MAX_STORED_VARIATION * 2];
+    variation.push(first_move);
+    let mut table = HighDepthTranspositionTable::with_size_mb(SearchDepth::Depth(1), 1);
+    assert!(table.max_entries > 0);
+
+    table.update(&Board::default(), SearchDepth::Depth(3), evaluation, ChessMove::default(), Some(variation));
+    let search_info = table.get_transposition(&Board::default(), None).unwrap();
+    let stored_variation = search_info.prime_variation.as_ref().unwrap();
+    assert_eq!(stored_variation.len(), MAX_STORED_VARIATION);
+    assert_eq!(stored_variation.last(), Some(&first_move));
+}
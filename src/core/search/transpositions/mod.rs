@@ -1,3 +1,4 @@
+use std::any::Any;
 use std::cmp::Ordering;
 use chess::{Board, ChessMove};
 use serde::{Serialize, Deserialize};
@@ -7,8 +8,10 @@ use crate::core::search::{SearchDepth, SearchInfo};
 pub mod hash_transposition;
 pub mod no_transposition;
 pub mod high_depth_transposition;
+pub mod shared_transposition;
 
-pub trait TranspositionTable {
+/// `Send`, so the threads of a parallel search can share one
+pub trait TranspositionTable: Send {
     fn update(
         &mut self,
         board: &Board,
@@ -23,6 +26,26 @@ pub trait TranspositionTable {
         board: &Board,
         minimal_search_depth: Option<SearchDepth>,
     ) -> Option<&SearchInfo>;
+
+    /// Called before every search, so tables can prefer its entries over those of earlier searches
+    fn new_search(&mut self) {}
+
+    /// Splits the table into the given number of tables, the one for each board picked by `shard`, so threads can lock them apart.
+    /// Tables that can't be split stay whole, as the only shard.
+    fn into_shards(self: Box<Self>, count: usize) -> Vec<Box<dyn TranspositionTable>>;
+
+    /// Takes back the entries of the other shards made by `into_shards`, after the first
+    fn merge_shards(&mut self, _shards: Vec<Box<dyn TranspositionTable>>) {}
+
+    /// Lets `merge_shards` get at the entries of its own type
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        None
+    }
+}
+
+/// The shard of `into_shards` holding `board`. It takes the high bits of the hash, as the hash maps use the low ones.
+pub fn shard(board: &Board, count: usize) -> usize {
+    (board.get_hash() >> 32) as usize % count
 }
 
 
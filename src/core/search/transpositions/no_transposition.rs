@@ -14,4 +14,8 @@ impl TranspositionTable for NoTranspositionTable {
     fn get_transposition(&mut self, board: &Board, minimal_search_depth: Option<SearchDepth>) -> Option<&SearchInfo> {
         None
     }
+
+    fn into_shards(self: Box<Self>, _count: usize) -> Vec<Box<dyn TranspositionTable>> {
+        vec![self]
+    }
 }
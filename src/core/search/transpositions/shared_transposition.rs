@@ -0,0 +1,137 @@
+use std::mem;
+use std::sync::{Arc, Mutex};
+use chess::{Board, ChessMove};
+use crate::core::search::{SearchDepth, SearchInfo};
+use crate::core::search::transpositions::no_transposition::NoTranspositionTable;
+use crate::core::search::transpositions::{shard, EvalBound, TranspositionTable};
+
+/// The shards per thread: the more there are, the less often two threads wait for the same lock
+const SHARDS_PER_THREAD: usize = 16;
+
+/// The shards of a table lent out to the threads of a parallel search, each behind its own lock
+pub type ShardedTable = Arc<Vec<Mutex<Box<dyn TranspositionTable>>>>;
+
+/// Takes `transposition_table` out, split up to be shared between `threads` threads; it has no table until `return_transposition_table`.
+pub fn lend_transposition_table(transposition_table: &mut Box<dyn TranspositionTable>, threads: usize) -> ShardedTable {
+    let whole_table = mem::replace(transposition_table, Box::new(NoTranspositionTable));
+
+    Arc::new(
+        whole_table.into_shards(threads.max(1) * SHARDS_PER_THREAD)
+            .into_iter()
+            .map(Mutex::new)
+            .collect(),
+    )
+}
+
+/// Puts the table lent out by `lend_transposition_table` back together, once every handle on it is dropped.
+pub fn return_transposition_table(transposition_table: &mut Box<dyn TranspositionTable>, shared_table: ShardedTable) {
+    let mut shards = Arc::try_unwrap(shared_table)
+        .ok()
+        .expect("every thread is done with the table")
+        .into_iter()
+        .map(|x| x.into_inner().expect("transposition table poisoned"));
+
+    let mut whole_table = shards.next().expect("at least one shard");
+    whole_table.merge_shards(shards.collect());
+    *transposition_table = whole_table;
+}
+
+/// A handle on a table shared by the threads of a parallel search, one handle per thread.
+/// Only the shard of the board is locked. A probe copies the entry out from under the lock, as the other threads may overwrite it right after.
+pub struct SharedTranspositionTable {
+    shards: ShardedTable,
+    last_probe: Option<SearchInfo>, // What `get_transposition` lends out
+}
+
+impl SharedTranspositionTable {
+    pub fn new(shards: ShardedTable) -> Self {
+        SharedTranspositionTable {
+            shards,
+            last_probe: None,
+        }
+    }
+
+    fn shard(&self, board: &Board) -> &Mutex<Box<dyn TranspositionTable>> {
+        &self.shards[shard(board, self.shards.len())]
+    }
+}
+
+impl TranspositionTable for SharedTranspositionTable {
+    fn update(&mut self, board: &Board, search_depth: SearchDepth, evaluation: EvalBound, best_move: ChessMove, prime_variation: Option<Vec<ChessMove>>) {
+        self.shard(board).lock()
+            .expect("transposition table poisoned")
+            .update(board, search_depth, evaluation, best_move, prime_variation);
+    }
+
+    fn get_transposition(&mut self, board: &Board, minimal_search_depth: Option<SearchDepth>) -> Option<&SearchInfo> {
+        let probe = self.shard(board).lock()
+            .expect("transposition table poisoned")
+            .get_transposition(board, minimal_search_depth)
+            .cloned();
+        self.last_probe = probe;
+
+        self.last_probe.as_ref()
+    }
+
+    /// Already shared
+    fn into_shards(self: Box<Self>, _count: usize) -> Vec<Box<dyn TranspositionTable>> {
+        vec![self]
+    }
+}
+
+#[test]
+fn check_shared_between_handles() {
+    use crate::core::score::BoardEvaluation;
+    use crate::core::search::transpositions::hash_transposition::HashTranspositionTable;
+
+    let mut transposition_table: Box<dyn TranspositionTable> = Box::new(HashTranspositionTable::default());
+    let shared_table = lend_transposition_table(&mut transposition_table, 2);
+    let mut writer = SharedTranspositionTable::new(shared_table.clone());
+    let mut reader = SharedTranspositionTable::new(shared_table);
+
+    let board = Board::default();
+    assert!(reader.get_transposition(&board, None).is_none());
+    writer.update(&board, SearchDepth::Depth(3), EvalBound::Exact(BoardEvaluation::WhiteMate(5)), ChessMove::default(), None);
+
+    let search_info = reader.get_transposition(&board, None).expect("stored by the other handle");
+    assert_eq!(search_info.depth_searched, SearchDepth::Depth(3));
+    assert!(reader.get_transposition(&board, Some(SearchDepth::Depth(4))).is_none());
+}
+
+#[test]
+fn check_sharded_and_returned() {
+    use chess::MoveGen;
+    use crate::core::score::BoardEvaluation;
+    use crate::core::search::transpositions::high_depth_transposition::HighDepthTranspositionTable;
+
+    let evaluation = EvalBound::Exact(BoardEvaluation::WhiteMate(5));
+    let board = Board::default();
+    let children = MoveGen::new_legal(&board)
+        .map(|x| board.make_move_new(x))
+        .collect::<Vec<_>>();
+
+    let mut high_depth_table = HighDepthTranspositionTable::new(SearchDepth::Depth(1));
+    high_depth_table.max_entries = 1000;
+    high_depth_table.update(&board, SearchDepth::Depth(4), evaluation, ChessMove::default(), None);
+    let mut transposition_table: Box<dyn TranspositionTable> = Box::new(high_depth_table);
+
+    let shared_table = lend_transposition_table(&mut transposition_table, 4);
+    assert_eq!(shared_table.len(), 4 * SHARDS_PER_THREAD);
+    let mut handle = SharedTranspositionTable::new(shared_table.clone());
+    assert!(handle.get_transposition(&board, None).is_some());
+    for child in &children {
+        handle.update(child, SearchDepth::Depth(3), evaluation, ChessMove::default(), None);
+    }
+    drop(handle);
+    return_transposition_table(&mut transposition_table, shared_table);
+
+    // Every entry is back in the one table, which is as large as before
+    for position in children.iter().chain([&board]) {
+        assert!(transposition_table.get_transposition(position, None).is_some());
+    }
+    let high_depth_table = transposition_table.as_any_mut()
+        .and_then(|x| x.downcast_mut::<HighDepthTranspositionTable>())
+        .unwrap();
+    assert_eq!(high_depth_table.len(), children.len() + 1);
+    assert_eq!(high_depth_table.max_entries, 1000);
+}
@@ -0,0 +1,147 @@
+// The replies to the commands for inspecting the engine from a terminal, which no GUI sends.
+
+use chess::{Board, Color, MoveGen, Piece, Square};
+use itertools::Itertools;
+use crate::core::evaluation::breakdown::{evaluation_breakdown, PieceContribution};
+use crate::core::evaluation::{game_status, single_evaluation};
+use crate::core::score::Centipawns;
+use crate::core::search::bench::BenchResult;
+
+/// `d`: the board with white at the bottom, its FEN, its hash and the positions played before it
+pub fn position_lines(board: &Board, visited_boards: &[u64]) -> Vec<String> {
+    let mut lines = board_diagram(3, |square| {
+        vec![piece_string(board, square).unwrap_or_default()]
+    });
+
+    let repetitions = visited_boards.iter().filter(|x| **x == board.get_hash()).count();
+    lines.extend([
+        String::new(),
+        format!("Fen: {board}"),
+        format!("Key: {:016X}", board.get_hash()),
+        format!("Checkers: {}", board.checkers().map(|x| x.to_string()).join(" ")),
+        format!("History: {} earlier positions, of which {repetitions} repeat this one", visited_boards.len()),
+        format!("History keys: {}", visited_boards.iter().map(|x| format!("{x:016X}")).join(" ")),
+    ]);
+
+    lines
+}
+
+/// `eval`: what every piece adds to the `single_evaluation`, by square and by piece type.
+/// Scores are from white's perspective.
+pub fn evaluation_lines(board: &Board) -> Vec<String> {
+    let breakdown = evaluation_breakdown(board);
+    let mut lines = board_diagram(7, |square| {
+        match breakdown.iter().find(|x| x.square == square) {
+            Some(contribution) => vec![
+                piece_string(board, square).unwrap_or_default(),
+                contribution.score.to_string(),
+            ],
+            None => vec![String::new(), String::new()],
+        }
+    });
+
+    let total = |contributions: &mut dyn Iterator<Item = &PieceContribution>| {
+        contributions.fold(Centipawns::new(0), |total, x| total + x.score)
+    };
+
+    lines.push(String::new());
+    lines.push(format!("{:<8}{:>8}{:>8}{:>8}", "", "White", "Black", "Total"));
+    for piece in chess::ALL_PIECES {
+        let of_piece = |color: Color| total(&mut breakdown.iter().filter(|x| x.piece == piece && x.color == color));
+        lines.push(format!(
+            "{:<8}{:>8}{:>8}{:>8}",
+            format!("{piece:?}"),
+            of_piece(Color::White).to_string(),
+            of_piece(Color::Black).to_string(),
+            (of_piece(Color::White) + of_piece(Color::Black)).to_string(),
+        ));
+    }
+    lines.push(format!(
+        "{:<8}{:>8}{:>8}{:>8}",
+        "Total",
+        total(&mut breakdown.iter().filter(|x| x.color == Color::White)).to_string(),
+        total(&mut breakdown.iter().filter(|x| x.color == Color::Black)).to_string(),
+        total(&mut breakdown.iter()).to_string(),
+    ));
+
+    // Differs from the total once the game is over
+    let status = game_status(board, MoveGen::new_legal(board).len() > 0);
+    lines.push(String::new());
+    lines.push(format!("Evaluation: {} ({status:?})", single_evaluation(board, status)));
+
+    lines
+}
+
+/// `bench`: the node count identifies the search, the speed the machine
+pub fn bench_lines(result: &BenchResult) -> Vec<String> {
+    vec![
+        format!("Total time (ms) : {}", result.time.as_millis()),
+        format!("Nodes searched  : {}", result.nodes),
+        format!("Nodes/second    : {}", result.nodes_per_second()),
+    ]
+}
+
+/// Draws the board with rank 8 at the top, filling every square with the lines of `square_lines`
+fn board_diagram<F>(square_width: usize, square_lines: F) -> Vec<String> where
+    F: Fn(Square) -> Vec<String> {
+    let separator = format!(" +{}", format!("{}+", "-".repeat(square_width)).repeat(8));
+    let mut lines = vec![separator.clone()];
+
+    for rank in chess::ALL_RANKS.iter().rev() {
+        let squares = chess::ALL_FILES.iter()
+            .map(|file| square_lines(Square::make_square(*rank, *file)))
+            .collect::<Vec<_>>();
+        let height = squares.iter().map(|x| x.len()).max().unwrap_or(1);
+
+        for line_index in 0..height {
+            let mut line = " |".to_string();
+            for square in squares.iter() {
+                let text = square.get(line_index).map(String::as_str).unwrap_or("");
+                line.push_str(&format!("{text:^square_width$}|"));
+            }
+            if line_index == 0 {
+                line.push_str(&format!(" {}", rank.to_index() + 1));
+            }
+            lines.push(line);
+        }
+        lines.push(separator.clone());
+    }
+
+    let files = chess::ALL_FILES.iter()
+        .map(|file| format!("{:^square_width$}", (b'a' + file.to_index() as u8) as char))
+        .join(" ");
+    lines.push(format!("  {files}").trim_end().to_string());
+
+    lines
+}
+
+/// The letter of the piece on `square`: upper case for white
+fn piece_string(board: &Board, square: Square) -> Option<String> {
+    let piece: Piece = board.piece_on(square)?;
+    let color = board.color_on(square)?;
+    Some(piece.to_string(color))
+}
+
+#[test]
+fn check_position_lines() {
+    let board = Board::default();
+    let lines = position_lines(&board, &[board.get_hash(), 1]);
+
+    assert_eq!(lines[0], " +---+---+---+---+---+---+---+---+");
+    assert_eq!(lines[1], " | r | n | b | q | k | b | n | r | 8");
+    assert_eq!(lines[7], " |   |   |   |   |   |   |   |   | 5");
+    assert_eq!(lines[17], "   a   b   c   d   e   f   g   h");
+    assert!(lines.contains(&"Fen: rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".to_string()));
+    assert!(lines.contains(&"History: 2 earlier positions, of which 1 repeat this one".to_string()));
+}
+
+#[test]
+fn check_evaluation_lines() {
+    let lines = evaluation_lines(&Board::default());
+
+    assert_eq!(lines[1], " |   r   |   n   |   b   |   q   |   k   |   b   |   n   |   r   | 8");
+    assert_eq!(lines[2], " | -460  | -280  | -250  | -900  | -110  | -250  | -280  | -460  |");
+    assert!(lines.contains(&"Total       3680   -3680       0".to_string()));
+    assert_eq!(lines.last().unwrap(), "Evaluation: 0 (Ongoing)");
+}
+
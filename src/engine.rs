@@ -0,0 +1,435 @@
+// The search engine as a library: owns the search thread, the transposition table and the options.
+
+use std::cell::RefCell;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+use chess::{Board, ChessMove};
+use crate::analysis::database::rows::{ConspiracyMergeFn, MTSearchRow, PositionSearchRow};
+use crate::analysis::match_orchestration::SearchAlgorithm;
+use crate::analysis::mtd_h_utils::MtdHParams;
+use crate::core::castling::Chess960Castling;
+use crate::core::evaluation::Evaluator;
+use crate::core::is_default_move;
+use crate::core::perft::perft_divide;
+use crate::core::search::aspiration::AspirationWindows;
+use crate::core::search::bench::{bench, BenchResult};
+use crate::core::search::conspiracy_counter::ConspiracyCounter;
+use crate::core::search::info::{InfoCallback, IterationInfo};
+use crate::core::search::extensions::ExtensionOptions;
+use crate::core::search::pruning::PruningOptions;
+use crate::core::search::search_control::SearchControl;
+use crate::core::search::search_position;
+use crate::core::search::searcher::SearchSetup;
+use crate::core::search::search_result::debug_search_result::DebugSearchResult;
+use crate::core::search::search_result::SearchResult;
+use crate::core::search::transpositions::TranspositionTable;
+use crate::input::engine_options::{EngineOptionError, EngineOptionName, EngineOptions};
+use crate::input::protocol_interpreter::CalculateOptions;
+
+/// Creates the transposition table, again on every new game or change of the options.
+/// Called on the search thread, which lends the table to its helper threads when searching with several.
+pub type TranspositionFactory = Box<dyn Fn(&EngineOptions) -> Box<dyn TranspositionTable> + Send>;
+
+/// The DB rows of a single iteration
+pub type SearchRows = (PositionSearchRow, Vec<MTSearchRow>);
+
+enum EngineCommand {
+    NewGame,
+    SetPosition(Board, Chess960Castling, Vec<u64>),
+    SetOptions(EngineOptions, EngineOptionName),
+    Go(CalculateOptions, Sender<SearchOutcome>),
+    Perft(u32, Sender<Vec<(ChessMove, u64)>>),
+    Position(Sender<(Board, Vec<u64>)>),
+    Bench(u32, Sender<BenchResult>),
+}
+
+pub struct SearchOutcome {
+    pub board: Board, // The position that was searched
+    pub best_move: ChessMove, // Always legal, even when stopped before the first iteration completed
+    pub ponder_move: Option<ChessMove>, // The expected reply, if known
+    pub search_result: DebugSearchResult,
+    pub conspiracy_counter: Option<ConspiracyCounter>, // Only kept by the conspiracy searches
+    pub search_rows: Vec<SearchRows>, // Only kept if the engine records them
+}
+
+#[derive(Default)]
+pub struct EngineBuilder {
+    options: EngineOptions,
+    transposition_factory: Option<TranspositionFactory>,
+    mtd_h_params: Option<Vec<MtdHParams>>,
+    info_callback: Option<InfoCallback>,
+    evaluator: Option<Evaluator>,
+    record_search_rows: bool,
+}
+
+impl EngineBuilder {
+    /// Starts from `options`, as if every option was set through `setoption`
+    pub fn options(mut self, options: EngineOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn algorithm(mut self, algorithm: SearchAlgorithm) -> Self {
+        self.options.algorithm = algorithm;
+        self
+    }
+
+    pub fn conspiracy(mut self, bucket_size: u32, num_buckets: usize, merge_fn: ConspiracyMergeFn) -> Self {
+        self.options.bucket_size = bucket_size;
+        self.options.num_buckets = num_buckets;
+        self.options.merge_fn = merge_fn;
+        self
+    }
+
+    /// Replaces the default table, sized by the `Hash` option
+    pub fn transposition_table<F>(mut self, factory: F) -> Self where
+        F: Fn(&EngineOptions) -> Box<dyn TranspositionTable> + Send + 'static {
+        self.transposition_factory = Some(Box::new(factory));
+        self
+    }
+
+    /// Used instead of reading the params from the `MTD-H Params Path`, until that option is set
+    pub fn mtd_h_params(mut self, mtd_h_params: Vec<MtdHParams>) -> Self {
+        self.mtd_h_params = Some(mtd_h_params);
+        self
+    }
+
+    pub fn move_overhead(mut self, move_overhead: Duration) -> Self {
+        self.options.move_overhead = move_overhead.as_millis() as u64;
+        self
+    }
+
+    pub fn multi_pv(mut self, multi_pv: usize) -> Self {
+        self.options.multi_pv = multi_pv.max(1) as u64;
+        self
+    }
+
+    /// Helper threads search along with the main one when above 1
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.options.threads = threads.max(1) as u64;
+        self
+    }
+
+    /// The test values Parallel MTD searches at once
+    pub fn mtd_probes(mut self, mtd_probes: usize) -> Self {
+        self.options.mtd_probes = mtd_probes.max(1) as u64;
+        self
+    }
+
+    pub fn pruning(mut self, pruning: PruningOptions) -> Self {
+        self.options.null_move_pruning = pruning.null_move.is_some();
+        if let Some(null_move) = pruning.null_move {
+            self.options.null_move_reduction = null_move.reduction;
+        }
+        self.options.late_move_reductions = pruning.late_move_reductions.is_some();
+        if let Some(late_move_reductions) = pruning.late_move_reductions {
+            self.options.lmr_base = late_move_reductions.base();
+            self.options.lmr_divisor = late_move_reductions.divisor();
+        }
+        self
+    }
+
+    pub fn extensions(mut self, extensions: ExtensionOptions) -> Self {
+        self.options.check_extension = extensions.check;
+        self.options.recapture_extension = extensions.recapture;
+        self
+    }
+
+    pub fn aspiration(mut self, aspiration: Option<AspirationWindows>) -> Self {
+        self.options.aspiration_windows = aspiration.is_some();
+        if let Some(aspiration) = aspiration {
+            self.options.aspiration_width = aspiration.width.0 as u32;
+            self.options.aspiration_widening = aspiration.widening;
+        }
+        self
+    }
+
+    /// Scores the positions the search ends on, instead of the piece-square tables.
+    /// Without it, the search updates the piece-square score move by move rather than scoring every position anew.
+    pub fn evaluator(mut self, evaluator: Evaluator) -> Self {
+        self.evaluator = Some(evaluator);
+        self
+    }
+
+    /// Receives the info of every iteration, which is then no longer printed for the GUI
+    pub fn on_info<F>(mut self, callback: F) -> Self where
+        F: Fn(&IterationInfo) + Send + Sync + 'static {
+        self.info_callback = Some(InfoCallback::new(callback));
+        self
+    }
+
+    /// Keeps the DB rows of every iteration in the `SearchOutcome`
+    pub fn record_search_rows(mut self) -> Self {
+        self.record_search_rows = true;
+        self
+    }
+
+    /// Starts the search thread
+    pub fn build(self) -> Engine {
+        let (engine_tx, engine_rx) = channel::<EngineCommand>();
+        let control = SearchControl::new()
+            .with_info_callback(self.info_callback)
+            .with_evaluator(self.evaluator);
+        let options = self.options.clone();
+
+        let search_thread_control = control.clone();
+        thread::spawn(move || {
+            run_search_thread(
+                engine_rx,
+                search_thread_control,
+                self.options,
+                self.transposition_factory.unwrap_or_else(|| Box::new(EngineOptions::transposition_table)),
+                self.mtd_h_params,
+                self.record_search_rows,
+            );
+        });
+
+        Engine {
+            engine_tx,
+            control,
+            options,
+        }
+    }
+}
+
+/// The commands are queued for the search thread, so none of the methods block on a running search.
+pub struct Engine {
+    engine_tx: Sender<EngineCommand>,
+    control: SearchControl, // Shared with the search thread
+    options: EngineOptions, // Validated here, so errors are returned right away
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Engine::builder().build()
+    }
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn builder() -> EngineBuilder {
+        EngineBuilder::default()
+    }
+
+    pub fn options(&self) -> &EngineOptions {
+        &self.options
+    }
+
+    /// Applies `setoption name <name> value <value>` once the running search is done
+    pub fn set_option(&mut self, name: &str, value: Option<&str>) -> Result<EngineOptionName, EngineOptionError> {
+        let option_name = self.options.set_option(name, value)?;
+        self.send(EngineCommand::SetOptions(self.options.clone(), option_name));
+        Ok(option_name)
+    }
+
+    /// Clears the transposition table
+    pub fn new_game(&self) {
+        self.send(EngineCommand::NewGame);
+    }
+
+    /// The position to search, with the hashes of the positions before it for draw detection
+    pub fn set_position(&self, board: Board, visited_boards: Vec<u64>) {
+        self.set_chess960_position(board, Chess960Castling::default(), visited_boards);
+    }
+
+    /// A position with the castling rights of Chess960, which the board can't hold
+    pub fn set_chess960_position(&self, board: Board, castling: Chess960Castling, visited_boards: Vec<u64>) {
+        self.send(EngineCommand::SetPosition(board, castling, visited_boards));
+    }
+
+    /// Starts searching the current position within `limits`, after the running search is done.
+    pub fn go(&self, limits: CalculateOptions) -> SearchHandle {
+        // Here rather than on the search thread: a `stop` or `ponderhit` may arrive before it gets going
+        self.control.reset();
+        self.control.set_pondering(limits.ponder);
+
+        let (outcome_tx, outcome_rx) = channel::<SearchOutcome>();
+        self.send(EngineCommand::Go(limits, outcome_tx));
+
+        SearchHandle {
+            control: self.control.clone(),
+            outcome_rx,
+        }
+    }
+
+    /// Counts the move sequences of `depth` plies below every legal move of the current position, after the running search is done.
+    pub fn perft(&self, depth: u32) -> Receiver<Vec<(ChessMove, u64)>> {
+        let (divide_tx, divide_rx) = channel();
+        self.send(EngineCommand::Perft(depth, divide_tx));
+        divide_rx
+    }
+
+    /// The current position with the hashes of the positions before it, once the running search is done
+    pub fn position(&self) -> Receiver<(Board, Vec<u64>)> {
+        let (position_tx, position_rx) = channel();
+        self.send(EngineCommand::Position(position_tx));
+        position_rx
+    }
+
+    /// Searches the bench positions to `depth` with the current options, after the running search is done.
+    pub fn bench(&self, depth: u32) -> Receiver<BenchResult> {
+        let (bench_tx, bench_rx) = channel();
+        self.send(EngineCommand::Bench(depth, bench_tx));
+        bench_rx
+    }
+
+    /// Interrupts the running search, which still reports its best move
+    pub fn stop(&self) {
+        self.control.stop();
+    }
+
+    /// The opponent played the expected move: the pondering search now runs on our own clock
+    pub fn ponder_hit(&self) {
+        self.control.ponder_hit();
+    }
+
+    fn send(&self, command: EngineCommand) {
+        self.engine_tx.send(command).expect("search thread stopped");
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        // The search thread ends once it sees the channel closed
+        self.control.stop();
+    }
+}
+
+/// A search started by `Engine::go`
+pub struct SearchHandle {
+    control: SearchControl,
+    outcome_rx: Receiver<SearchOutcome>,
+}
+
+impl SearchHandle {
+    pub fn stop(&self) {
+        self.control.stop();
+    }
+
+    pub fn ponder_hit(&self) {
+        self.control.ponder_hit();
+    }
+
+    /// Blocks until the search is done
+    pub fn wait(self) -> SearchOutcome {
+        self.outcome_rx.recv().expect("search thread stopped")
+    }
+
+    /// The outcome, if the search is done
+    pub fn try_outcome(&self) -> Option<SearchOutcome> {
+        self.outcome_rx.try_recv().ok()
+    }
+}
+
+fn run_search_thread(
+    engine_rx: Receiver<EngineCommand>,
+    control: SearchControl,
+    mut engine_options: EngineOptions,
+    transposition_factory: TranspositionFactory,
+    mtd_h_params: Option<Vec<MtdHParams>>,
+    record_search_rows: bool,
+) {
+    let mut transposition_table = transposition_factory(&engine_options);
+    let mut mtd_h_params = mtd_h_params.unwrap_or_else(|| engine_options.mtd_h_params());
+    let mut main_board = Board::default();
+    let mut main_castling = Chess960Castling::default();
+    let mut visited_boards: Vec<u64> = Vec::new(); // List of board hashes
+
+    while let Ok(command) = engine_rx.recv() {
+        match command {
+            EngineCommand::SetPosition(board, castling, visited) => {
+                main_board = board;
+                main_castling = castling;
+                visited_boards = visited;
+            },
+            EngineCommand::NewGame => transposition_table = transposition_factory(&engine_options),
+            EngineCommand::SetOptions(options, changed) => {
+                engine_options = options;
+                match changed {
+                    EngineOptionName::Hash | EngineOptionName::MinimumTranspositionDepth => {
+                        transposition_table = transposition_factory(&engine_options);
+                    },
+                    EngineOptionName::MtdHParamsPath | EngineOptionName::MtdHTrainingDistance => {
+                        mtd_h_params = engine_options.mtd_h_params();
+                    },
+                    _ => (),
+                }
+            },
+            EngineCommand::Go(options, outcome_tx) => {
+                let search_control = control.clone()
+                    .with_castling(main_castling)
+                    .with_limits(&options, &main_board, engine_options.move_overhead())
+                    .with_multi_pv(engine_options.multi_pv as usize)
+                    .with_pruning(engine_options.pruning_options())
+                    .with_extensions(engine_options.extension_options())
+                    .with_aspiration(engine_options.aspiration_options());
+                let search_rows = RefCell::new(Vec::new());
+
+                transposition_table.new_search();
+                let search_output = search_position(
+                    &main_board,
+                    &mut transposition_table,
+                    visited_boards.clone(),
+                    options,
+                    SearchSetup {
+                        engine_options: &engine_options,
+                        mtd_h_params: &mtd_h_params,
+                    },
+                    |position_row, mt_rows| {
+                        if record_search_rows {
+                            search_rows.borrow_mut().push((position_row, mt_rows));
+                        }
+                    },
+                    &search_control,
+                );
+                let search_result = search_output.search_result;
+
+                let mut best_move = search_result.best_move();
+                if is_default_move(&best_move) {
+                    // Interrupted before the first iteration completed: any legal move beats none
+                    let legal_moves = search_control.restrict_root_moves(main_castling.legal_moves(&main_board), 0);
+                    if let Some(chess_move) = legal_moves.first() {
+                        best_move = *chess_move;
+                    }
+                }
+
+                // Nobody waiting for it is fine
+                let _ = outcome_tx.send(SearchOutcome {
+                    board: main_board,
+                    best_move,
+                    ponder_move: determine_ponder_move(&main_board, main_castling, &search_result, best_move),
+                    search_result,
+                    conspiracy_counter: search_output.conspiracy_counter,
+                    search_rows: search_rows.into_inner(),
+                });
+            },
+            EngineCommand::Perft(depth, divide_tx) => {
+                let _ = divide_tx.send(perft_divide(&main_board, main_castling, depth));
+            },
+            EngineCommand::Position(position_tx) => {
+                let _ = position_tx.send((main_board, visited_boards.clone()));
+            },
+            EngineCommand::Bench(depth, bench_tx) => {
+                let result = bench(depth, &engine_options, &mtd_h_params, || transposition_factory(&engine_options));
+                let _ = bench_tx.send(result);
+            },
+        }
+    }
+}
+
+/// The expected reply to `best_move`, taken from the critical path
+fn determine_ponder_move<T: SearchResult>(board: &Board, castling: Chess960Castling, search_result: &T, best_move: ChessMove) -> Option<ChessMove> {
+    // The critical path is stored in reverse
+    let mut critical_path = search_result.critical_path()?.into_iter().rev();
+    if critical_path.next() != Some(best_move) {
+        return None;
+    }
+
+    let new_board = castling.make_move(board, best_move);
+    critical_path.next()
+        .filter(|x| castling.after_move(&new_board).is_legal(&new_board, *x))
+}
@@ -0,0 +1,567 @@
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+use clap::ValueEnum;
+use thiserror::Error;
+use crate::analysis::database::rows::ConspiracyMergeFn;
+use crate::analysis::match_orchestration::SearchAlgorithm;
+use crate::analysis::mtd_h_utils::{filter_mtd_h_params, MtdHParams, read_mtd_h_params};
+use crate::analysis::parallel_mtd::DEFAULT_MTD_PROBES;
+use crate::core::score::Centipawns;
+use crate::core::search::aspiration::{AspirationWindows, MAX_ASPIRATION_WIDTH};
+use crate::core::search::conspiracy_search::merging::{merge_remove_overwritten, MergeFn};
+use crate::core::search::extensions::{ExtensionOptions, ONE_PLY};
+use crate::core::search::pruning::{LateMoveReductions, NullMovePruning, PruningOptions};
+use crate::core::search::searcher::algorithm_entry;
+use crate::core::search::SearchDepth;
+use crate::core::search::transpositions::high_depth_transposition::HighDepthTranspositionTable;
+use crate::core::search::transpositions::TranspositionTable;
+
+/// How an empty string option is written in UCI
+const EMPTY_STRING: &str = "<empty>";
+
+/// The options a GUI can change through `setoption`, and the values the search thread uses.
+
+#[derive(Error, Debug, Clone, Eq, PartialEq)]
+pub enum EngineOptionError {
+    #[error("unknown option {0}")]
+    UnknownOption(String),
+    #[error("no value given for option {0}")]
+    MissingValue(String),
+    #[error("invalid value {value} for option {name}")]
+    InvalidValue {
+        name: String,
+        value: String,
+    },
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum EngineOptionName {
+    Hash,
+    Threads,
+    Algorithm,
+    ConspiracyBucketSize,
+    ConspiracyBuckets,
+    ConspiracyMergeFn,
+    MinimumTranspositionDepth,
+    MtdHParamsPath,
+    MtdHTrainingDistance,
+    MtdProbes,
+    Ponder,
+    MoveOverhead,
+    DebugLogFile,
+    MultiPV,
+    Chess960,
+    NullMovePruning,
+    NullMoveReduction,
+    LateMoveReductions,
+    LmrBase,
+    LmrDivisor,
+    CheckExtension,
+    RecaptureExtension,
+    AspirationWindows,
+    AspirationWidth,
+    AspirationWidening,
+}
+
+impl EngineOptionName {
+    pub const ALL: [EngineOptionName; 25] = [
+        EngineOptionName::Hash,
+        EngineOptionName::Threads,
+        EngineOptionName::Algorithm,
+        EngineOptionName::ConspiracyBucketSize,
+        EngineOptionName::ConspiracyBuckets,
+        EngineOptionName::ConspiracyMergeFn,
+        EngineOptionName::MinimumTranspositionDepth,
+        EngineOptionName::MtdHParamsPath,
+        EngineOptionName::MtdHTrainingDistance,
+        EngineOptionName::MtdProbes,
+        EngineOptionName::Ponder,
+        EngineOptionName::MoveOverhead,
+        EngineOptionName::DebugLogFile,
+        EngineOptionName::MultiPV,
+        EngineOptionName::Chess960,
+        EngineOptionName::NullMovePruning,
+        EngineOptionName::NullMoveReduction,
+        EngineOptionName::LateMoveReductions,
+        EngineOptionName::LmrBase,
+        EngineOptionName::LmrDivisor,
+        EngineOptionName::CheckExtension,
+        EngineOptionName::RecaptureExtension,
+        EngineOptionName::AspirationWindows,
+        EngineOptionName::AspirationWidth,
+        EngineOptionName::AspirationWidening,
+    ];
+
+    pub fn uci_name(&self) -> &'static str {
+        match self {
+            EngineOptionName::Hash => "Hash",
+            EngineOptionName::Threads => "Threads",
+            EngineOptionName::Algorithm => "Algorithm",
+            EngineOptionName::ConspiracyBucketSize => "Conspiracy Bucket Size",
+            EngineOptionName::ConspiracyBuckets => "Conspiracy Buckets",
+            EngineOptionName::ConspiracyMergeFn => "Conspiracy Merge Function",
+            EngineOptionName::MinimumTranspositionDepth => "Minimum Transposition Depth",
+            EngineOptionName::MtdHParamsPath => "MTD-H Params Path",
+            EngineOptionName::MtdHTrainingDistance => "MTD-H Training Distance",
+            EngineOptionName::MtdProbes => "MTD Probes",
+            EngineOptionName::Ponder => "Ponder",
+            EngineOptionName::MoveOverhead => "Move Overhead",
+            EngineOptionName::DebugLogFile => "Debug Log File",
+            EngineOptionName::MultiPV => "MultiPV",
+            EngineOptionName::Chess960 => "UCI_Chess960",
+            EngineOptionName::NullMovePruning => "Null Move Pruning",
+            EngineOptionName::NullMoveReduction => "Null Move Reduction",
+            EngineOptionName::LateMoveReductions => "Late Move Reductions",
+            EngineOptionName::LmrBase => "LMR Base",
+            EngineOptionName::LmrDivisor => "LMR Divisor",
+            EngineOptionName::CheckExtension => "Check Extension",
+            EngineOptionName::RecaptureExtension => "Recapture Extension",
+            EngineOptionName::AspirationWindows => "Aspiration Windows",
+            EngineOptionName::AspirationWidth => "Aspiration Width",
+            EngineOptionName::AspirationWidening => "Aspiration Widening",
+        }
+    }
+
+    /// Option names are case insensitive in UCI
+    pub fn from_uci_name(name: &str) -> Option<Self> {
+        EngineOptionName::ALL.into_iter()
+            .find(|x| x.uci_name().eq_ignore_ascii_case(name.trim()))
+    }
+}
+
+/// The UCI option types, with their default values.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum OptionType {
+    Check {
+        default: bool,
+    },
+    Spin {
+        default: u64,
+        min: u64,
+        max: u64,
+    },
+    Combo {
+        default: String,
+        vars: Vec<String>,
+    },
+    String {
+        default: String,
+    },
+}
+
+impl Display for OptionType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OptionType::Check { default } => write!(f, "type check default {default}"),
+            OptionType::Spin { default, min, max } => write!(f, "type spin default {default} min {min} max {max}"),
+            OptionType::Combo { default, vars } => {
+                write!(f, "type combo default {default}")?;
+                for var in vars {
+                    write!(f, " var {var}")?;
+                }
+                Ok(())
+            },
+            OptionType::String { default } if default.is_empty() => write!(f, "type string default {EMPTY_STRING}"),
+            OptionType::String { default } => write!(f, "type string default {default}"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EngineOptions {
+    pub hash_size: u64, // in MB
+    pub algorithm: SearchAlgorithm,
+    pub bucket_size: u32,
+    pub num_buckets: usize,
+    pub merge_fn: ConspiracyMergeFn,
+    pub minimum_transposition_depth: u32,
+    pub mtd_h_params_path: String,
+    pub mtd_h_training_distance: u32,
+    pub mtd_probes: u64, // The test values Parallel MTD searches at once, each on its own thread
+    pub ponder: bool, // Only tells whether the GUI may send `go ponder`
+    pub move_overhead: u64, // in ms, kept in reserve for the delay between the engine and the GUI's clock
+    pub debug_log_file: String, // Empty for no log file
+    pub multi_pv: u64, // The number of best root moves to report
+    pub chess960: bool, // Castles are written as the king taking its rook
+    pub null_move_pruning: bool,
+    pub null_move_reduction: u32,
+    pub late_move_reductions: bool,
+    pub lmr_base: u32, // In hundredths of a ply
+    pub lmr_divisor: u32, // In hundredths
+    pub check_extension: u32, // In quarter plies
+    pub recapture_extension: u32, // In quarter plies
+    pub aspiration_windows: bool,
+    pub aspiration_width: u32, // In centipawns
+    pub aspiration_widening: u32,
+    pub threads: u64, // Above 1, helper threads search along with the main one, which makes the search nondeterministic
+}
+
+impl Default for EngineOptions {
+    fn default() -> Self {
+        EngineOptions {
+            hash_size: 128,
+            algorithm: SearchAlgorithm::MTDBiIterativeDeepeningConspiracy,
+            bucket_size: 20,
+            num_buckets: 101,
+            merge_fn: ConspiracyMergeFn::MergeRemoveOverwritten,
+            minimum_transposition_depth: 2,
+            mtd_h_params_path: "./python/analysis_output/optimal_params.csv".to_string(),
+            mtd_h_training_distance: 2,
+            mtd_probes: DEFAULT_MTD_PROBES as u64,
+            ponder: false,
+            move_overhead: 30,
+            debug_log_file: String::new(),
+            multi_pv: 1,
+            chess960: false,
+            null_move_pruning: false,
+            null_move_reduction: 2,
+            late_move_reductions: false,
+            lmr_base: LateMoveReductions::DEFAULT_BASE,
+            lmr_divisor: LateMoveReductions::DEFAULT_DIVISOR,
+            check_extension: 0,
+            recapture_extension: 0,
+            aspiration_windows: false,
+            aspiration_width: AspirationWindows::DEFAULT_WIDTH,
+            aspiration_widening: AspirationWindows::DEFAULT_WIDENING,
+            threads: 1,
+        }
+    }
+}
+
+impl EngineOptions {
+    pub fn option_type(name: EngineOptionName) -> OptionType {
+        let defaults = EngineOptions::default();
+
+        match name {
+            EngineOptionName::Hash => OptionType::Spin {
+                default: defaults.hash_size,
+                min: 1,
+                max: 65536,
+            },
+            EngineOptionName::Threads => OptionType::Spin {
+                default: defaults.threads,
+                min: 1,
+                max: 256,
+            },
+            EngineOptionName::Algorithm => OptionType::Combo {
+                default: algorithm_uci_name(defaults.algorithm).to_string(),
+                vars: SearchAlgorithm::value_variants().iter()
+                    .map(|x| algorithm_uci_name(*x).to_string())
+                    .collect(),
+            },
+            EngineOptionName::ConspiracyBucketSize => OptionType::Spin {
+                default: defaults.bucket_size as u64,
+                min: 1,
+                max: 1000,
+            },
+            EngineOptionName::ConspiracyBuckets => OptionType::Spin {
+                default: defaults.num_buckets as u64,
+                min: 1,
+                max: 10001,
+            },
+            EngineOptionName::ConspiracyMergeFn => OptionType::Combo {
+                default: defaults.merge_fn.to_string(),
+                vars: ConspiracyMergeFn::value_variants().iter()
+                    .map(|x| x.to_string())
+                    .collect(),
+            },
+            EngineOptionName::MinimumTranspositionDepth => OptionType::Spin {
+                default: defaults.minimum_transposition_depth as u64,
+                min: 0,
+                max: 100,
+            },
+            EngineOptionName::MtdHParamsPath => OptionType::String {
+                default: defaults.mtd_h_params_path,
+            },
+            EngineOptionName::MtdHTrainingDistance => OptionType::Spin {
+                default: defaults.mtd_h_training_distance as u64,
+                min: 1,
+                max: 100,
+            },
+            EngineOptionName::MtdProbes => OptionType::Spin {
+                default: defaults.mtd_probes,
+                min: 1,
+                max: 64,
+            },
+            EngineOptionName::Ponder => OptionType::Check {
+                default: defaults.ponder,
+            },
+            EngineOptionName::MoveOverhead => OptionType::Spin {
+                default: defaults.move_overhead,
+                min: 0,
+                max: 5000,
+            },
+            EngineOptionName::DebugLogFile => OptionType::String {
+                default: defaults.debug_log_file,
+            },
+            EngineOptionName::MultiPV => OptionType::Spin {
+                default: defaults.multi_pv,
+                min: 1,
+                max: 256,
+            },
+            EngineOptionName::Chess960 => OptionType::Check {
+                default: defaults.chess960,
+            },
+            EngineOptionName::NullMovePruning => OptionType::Check {
+                default: defaults.null_move_pruning,
+            },
+            EngineOptionName::NullMoveReduction => OptionType::Spin {
+                default: defaults.null_move_reduction as u64,
+                min: 1,
+                max: 6,
+            },
+            EngineOptionName::LateMoveReductions => OptionType::Check {
+                default: defaults.late_move_reductions,
+            },
+            EngineOptionName::LmrBase => OptionType::Spin {
+                default: defaults.lmr_base as u64,
+                min: 0,
+                max: 500,
+            },
+            EngineOptionName::LmrDivisor => OptionType::Spin {
+                default: defaults.lmr_divisor as u64,
+                min: 50,
+                max: 1000,
+            },
+            EngineOptionName::CheckExtension => OptionType::Spin {
+                default: defaults.check_extension as u64,
+                min: 0,
+                max: ONE_PLY as u64,
+            },
+            EngineOptionName::RecaptureExtension => OptionType::Spin {
+                default: defaults.recapture_extension as u64,
+                min: 0,
+                max: ONE_PLY as u64,
+            },
+            EngineOptionName::AspirationWindows => OptionType::Check {
+                default: defaults.aspiration_windows,
+            },
+            EngineOptionName::AspirationWidth => OptionType::Spin {
+                default: defaults.aspiration_width as u64,
+                min: 1,
+                max: MAX_ASPIRATION_WIDTH.0 as u64,
+            },
+            EngineOptionName::AspirationWidening => OptionType::Spin {
+                default: defaults.aspiration_widening as u64,
+                min: 2,
+                max: 16,
+            },
+        }
+    }
+
+    /// The `option name ... type ...` lines announced during the `uci` handshake
+    pub fn uci_option_lines() -> Vec<String> {
+        EngineOptionName::ALL.into_iter()
+            .map(|x| format!("option name {} {}", x.uci_name(), EngineOptions::option_type(x)))
+            .collect()
+    }
+
+    /// Applies `setoption name <name> value <value>`, returning which option was changed.
+    pub fn set_option(&mut self, name: &str, value: Option<&str>) -> Result<EngineOptionName, EngineOptionError> {
+        let option_name = EngineOptionName::from_uci_name(name)
+            .ok_or_else(|| EngineOptionError::UnknownOption(name.to_string()))?;
+        let value = value
+            .map(|x| x.trim())
+            .ok_or_else(|| EngineOptionError::MissingValue(option_name.uci_name().to_string()))?;
+        let invalid_value = || EngineOptionError::InvalidValue {
+            name: option_name.uci_name().to_string(),
+            value: value.to_string(),
+        };
+
+        match (option_name, EngineOptions::option_type(option_name)) {
+            (_, OptionType::Check { .. }) => {
+                let checked = match value {
+                    "true" => true,
+                    "false" => false,
+                    _ => return Err(invalid_value()),
+                };
+
+                match option_name {
+                    EngineOptionName::Ponder => self.ponder = checked,
+                    EngineOptionName::Chess960 => self.chess960 = checked,
+                    EngineOptionName::NullMovePruning => self.null_move_pruning = checked,
+                    EngineOptionName::LateMoveReductions => self.late_move_reductions = checked,
+                    EngineOptionName::AspirationWindows => self.aspiration_windows = checked,
+                    _ => unreachable!("not a check option"),
+                }
+            },
+            (_, OptionType::Spin { min, max, .. }) => {
+                let number = value.parse::<u64>().map_err(|_| invalid_value())?;
+                if number < min || number > max {
+                    return Err(invalid_value());
+                }
+
+                match option_name {
+                    EngineOptionName::Hash => self.hash_size = number,
+                    EngineOptionName::Threads => self.threads = number,
+                    EngineOptionName::ConspiracyBucketSize => self.bucket_size = number as u32,
+                    EngineOptionName::ConspiracyBuckets => {
+                        // The middle bucket has to be centered around the node value
+                        if number % 2 == 0 {
+                            return Err(invalid_value());
+                        }
+                        self.num_buckets = number as usize;
+                    },
+                    EngineOptionName::MinimumTranspositionDepth => self.minimum_transposition_depth = number as u32,
+                    EngineOptionName::MtdHTrainingDistance => self.mtd_h_training_distance = number as u32,
+                    EngineOptionName::MtdProbes => self.mtd_probes = number,
+                    EngineOptionName::MoveOverhead => self.move_overhead = number,
+                    EngineOptionName::MultiPV => self.multi_pv = number,
+                    EngineOptionName::NullMoveReduction => self.null_move_reduction = number as u32,
+                    EngineOptionName::LmrBase => self.lmr_base = number as u32,
+                    EngineOptionName::LmrDivisor => self.lmr_divisor = number as u32,
+                    EngineOptionName::CheckExtension => self.check_extension = number as u32,
+                    EngineOptionName::RecaptureExtension => self.recapture_extension = number as u32,
+                    EngineOptionName::AspirationWidth => self.aspiration_width = number as u32,
+                    EngineOptionName::AspirationWidening => self.aspiration_widening = number as u32,
+                    _ => unreachable!("not a spin option"),
+                }
+            },
+            (EngineOptionName::Algorithm, _) => {
+                self.algorithm = *SearchAlgorithm::value_variants().iter()
+                    .find(|x| algorithm_uci_name(**x).eq_ignore_ascii_case(value))
+                    .ok_or_else(invalid_value)?;
+            },
+            (EngineOptionName::ConspiracyMergeFn, _) => {
+                self.merge_fn = *ConspiracyMergeFn::value_variants().iter()
+                    .find(|x| x.to_string().eq_ignore_ascii_case(value))
+                    .ok_or_else(invalid_value)?;
+            },
+            (EngineOptionName::MtdHParamsPath, _) => self.mtd_h_params_path = value.to_string(),
+            (EngineOptionName::DebugLogFile, _) if value == EMPTY_STRING => self.debug_log_file = String::new(),
+            (EngineOptionName::DebugLogFile, _) => self.debug_log_file = value.to_string(),
+            _ => unreachable!("all options are covered"),
+        }
+
+        Ok(option_name)
+    }
+
+    pub fn transposition_table(&self) -> Box<dyn TranspositionTable> {
+        Box::new(HighDepthTranspositionTable::with_size_mb(
+            SearchDepth::Depth(self.minimum_transposition_depth),
+            self.hash_size as usize,
+        ))
+    }
+
+    pub fn conspiracy_merge_fn(&self) -> MergeFn {
+        match self.merge_fn {
+            ConspiracyMergeFn::MergeRemoveOverwritten => merge_remove_overwritten,
+        }
+    }
+
+    pub fn move_overhead(&self) -> Duration {
+        Duration::from_millis(self.move_overhead)
+    }
+
+    pub fn pruning_options(&self) -> PruningOptions {
+        PruningOptions {
+            null_move: self.null_move_pruning.then_some(NullMovePruning {
+                reduction: self.null_move_reduction,
+            }),
+            late_move_reductions: self.late_move_reductions
+                .then(|| LateMoveReductions::new(self.lmr_base, self.lmr_divisor)),
+        }
+    }
+
+    pub fn extension_options(&self) -> ExtensionOptions {
+        ExtensionOptions {
+            check: self.check_extension,
+            recapture: self.recapture_extension,
+        }
+    }
+
+    pub fn aspiration_options(&self) -> Option<AspirationWindows> {
+        self.aspiration_windows.then_some(AspirationWindows {
+            width: Centipawns::new(self.aspiration_width as i64),
+            widening: self.aspiration_widening,
+        })
+    }
+
+    pub fn mtd_h_params(&self) -> Vec<MtdHParams> {
+        filter_mtd_h_params(read_mtd_h_params(&self.mtd_h_params_path), self.mtd_h_training_distance)
+    }
+}
+
+pub fn algorithm_uci_name(algorithm: SearchAlgorithm) -> &'static str {
+    algorithm_entry(algorithm).uci_name
+}
+
+#[test]
+fn check_option_lines() {
+    let lines = EngineOptions::uci_option_lines();
+
+    assert!(lines.contains(&"option name Hash type spin default 128 min 1 max 65536".to_string()));
+    assert!(lines.contains(&"option name Threads type spin default 1 min 1 max 256".to_string()));
+    assert!(lines.contains(&"option name Ponder type check default false".to_string()));
+    assert!(lines.contains(&"option name UCI_Chess960 type check default false".to_string()));
+    assert!(lines.contains(&"option name Debug Log File type string default <empty>".to_string()));
+    assert!(lines.contains(&"option name Algorithm type combo default MTD-bi Conspiracy var MTD-bi Conspiracy var MTD-f Conspiracy var MTD-bi var MTD-f var Alpha-Beta var MTD-H var PVS var Parallel MTD".to_string()));
+    assert!(lines.contains(&"option name MTD Probes type spin default 4 min 1 max 64".to_string()));
+}
+
+#[test]
+fn check_set_option() {
+    let mut options = EngineOptions::default();
+
+    assert_eq!(options.set_option("hash", Some("32")), Ok(EngineOptionName::Hash));
+    assert_eq!(options.hash_size, 32);
+    assert_eq!(options.set_option("Threads", Some("4")), Ok(EngineOptionName::Threads));
+    assert_eq!(options.threads, 4);
+    assert_eq!(options.set_option("Algorithm", Some("Alpha-Beta")), Ok(EngineOptionName::Algorithm));
+    assert_eq!(options.algorithm, SearchAlgorithm::AlphaBetaIterativeDeepening);
+    assert_eq!(options.set_option("MTD Probes", Some("8")), Ok(EngineOptionName::MtdProbes));
+    assert_eq!(options.mtd_probes, 8);
+    assert_eq!(options.set_option("MTD-H Params Path", Some("params.csv")), Ok(EngineOptionName::MtdHParamsPath));
+    assert_eq!(options.mtd_h_params_path, "params.csv");
+    assert_eq!(options.set_option("Ponder", Some("true")), Ok(EngineOptionName::Ponder));
+    assert!(options.ponder);
+    assert_eq!(options.set_option("Move Overhead", Some("100")), Ok(EngineOptionName::MoveOverhead));
+    assert_eq!(options.move_overhead(), Duration::from_millis(100));
+    assert_eq!(options.set_option("Debug Log File", Some("sn0l.log")), Ok(EngineOptionName::DebugLogFile));
+    assert_eq!(options.debug_log_file, "sn0l.log");
+    assert_eq!(options.set_option("Debug Log File", Some("<empty>")), Ok(EngineOptionName::DebugLogFile));
+    assert_eq!(options.debug_log_file, "");
+    assert_eq!(options.set_option("multipv", Some("3")), Ok(EngineOptionName::MultiPV));
+    assert_eq!(options.multi_pv, 3);
+    assert_eq!(options.set_option("UCI_Chess960", Some("true")), Ok(EngineOptionName::Chess960));
+    assert!(options.chess960);
+
+    assert_eq!(options.pruning_options(), PruningOptions::default());
+    assert_eq!(options.set_option("Null Move Pruning", Some("true")), Ok(EngineOptionName::NullMovePruning));
+    assert_eq!(options.set_option("Null Move Reduction", Some("3")), Ok(EngineOptionName::NullMoveReduction));
+    assert_eq!(options.pruning_options().null_move, Some(NullMovePruning { reduction: 3 }));
+    assert_eq!(options.set_option("Late Move Reductions", Some("true")), Ok(EngineOptionName::LateMoveReductions));
+    assert_eq!(options.set_option("LMR Base", Some("50")), Ok(EngineOptionName::LmrBase));
+    assert_eq!(options.set_option("LMR Divisor", Some("300")), Ok(EngineOptionName::LmrDivisor));
+    assert_eq!(options.pruning_options().late_move_reductions, Some(LateMoveReductions::new(50, 300)));
+
+    assert_eq!(options.extension_options(), ExtensionOptions::default());
+    assert_eq!(options.set_option("Check Extension", Some("4")), Ok(EngineOptionName::CheckExtension));
+    assert_eq!(options.set_option("Recapture Extension", Some("2")), Ok(EngineOptionName::RecaptureExtension));
+    assert_eq!(options.extension_options(), ExtensionOptions { check: 4, recapture: 2 });
+
+    assert_eq!(options.aspiration_options(), None);
+    assert_eq!(options.set_option("Aspiration Windows", Some("true")), Ok(EngineOptionName::AspirationWindows));
+    assert_eq!(options.aspiration_options(), Some(AspirationWindows::default()));
+    assert_eq!(options.set_option("Aspiration Width", Some("25")), Ok(EngineOptionName::AspirationWidth));
+    assert_eq!(options.set_option("Aspiration Widening", Some("4")), Ok(EngineOptionName::AspirationWidening));
+    assert_eq!(options.aspiration_options(), Some(AspirationWindows { width: Centipawns::new(25), widening: 4 }));
+}
+
+#[test]
+fn check_set_option_errors() {
+    let mut options = EngineOptions::default();
+
+    assert!(matches!(options.set_option("Contempt", Some("2")), Err(EngineOptionError::UnknownOption(_))));
+    assert!(matches!(options.set_option("Threads", Some("0")), Err(EngineOptionError::InvalidValue { .. })));
+    assert!(matches!(options.set_option("MTD Probes", Some("0")), Err(EngineOptionError::InvalidValue { .. })));
+    assert!(matches!(options.set_option("Hash", None), Err(EngineOptionError::MissingValue(_))));
+    assert!(matches!(options.set_option("Hash", Some("0")), Err(EngineOptionError::InvalidValue { .. })));
+    assert!(matches!(options.set_option("Conspiracy Buckets", Some("100")), Err(EngineOptionError::InvalidValue { .. })));
+    assert!(matches!(options.set_option("Algorithm", Some("Minimax")), Err(EngineOptionError::InvalidValue { .. })));
+    assert!(matches!(options.set_option("UCI_Chess960", Some("yes")), Err(EngineOptionError::InvalidValue { .. })));
+    assert!(matches!(options.set_option("LMR Divisor", Some("0")), Err(EngineOptionError::InvalidValue { .. })));
+    assert!(matches!(options.set_option("Check Extension", Some("5")), Err(EngineOptionError::InvalidValue { .. })));
+    assert!(matches!(options.set_option("Aspiration Widening", Some("1")), Err(EngineOptionError::InvalidValue { .. })));
+    assert_eq!(options, EngineOptions::default());
+}
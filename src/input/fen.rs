@@ -0,0 +1,121 @@
+use std::str::FromStr;
+use chess::{BitBoard, Board, Color, File, Piece, Rank, Square, ALL_FILES, EMPTY};
+use crate::core::castling::Chess960Castling;
+use crate::input::protocol_interpreter::CommandParseError;
+
+/// Reads a FEN, also accepting the castling rights of X-FEN and Shredder-FEN, where `HAha` are the files of the castling rooks.
+/// The board keeps the rights of a king on the e-file and rooks in the corners; any others make a Chess960 position,
+/// whose rights are returned next to a board without any.
+pub fn parse_fen(fen: &str) -> Result<(Board, Chess960Castling), CommandParseError> {
+    let invalid_fen = || CommandParseError::InvalidFen(fen.to_string());
+
+    let mut fields: Vec<&str> = fen.split_whitespace().collect();
+    let castling = fields.get(2).copied().ok_or_else(invalid_fen)?;
+
+    // The pieces have to be known to read the castling rights
+    fields[2] = "-";
+    let board = Board::from_str(&fields.join(" ")).map_err(|_| invalid_fen())?;
+    if castling == "-" {
+        return Ok((board, Chess960Castling::default()));
+    }
+
+    let rooks = castling.chars()
+        .map(|x| determine_castling_rook(&board, x))
+        .collect::<Option<Vec<Square>>>()
+        .ok_or_else(|| CommandParseError::InvalidCastling(castling.to_string()))?;
+
+    let is_standard = rooks.iter().all(|x| {
+        let king = board.king_square(board.color_on(*x).expect("castling rook is on the board"));
+        king.get_file() == File::E && (x.get_file() == File::A || x.get_file() == File::H)
+    });
+    if !is_standard {
+        let rooks = rooks.into_iter().fold(EMPTY, |x, y| x | BitBoard::from_square(y));
+        return Ok((board, Chess960Castling::new(&board, rooks)));
+    }
+
+    let castling_rights: String = rooks.into_iter()
+        .map(|x| {
+            let letter = match x.get_file() {
+                File::H => 'k',
+                _ => 'q',
+            };
+            match x.get_rank() {
+                Rank::First => letter.to_ascii_uppercase(),
+                _ => letter,
+            }
+        })
+        .collect();
+    fields[2] = &castling_rights;
+    let board = Board::from_str(&fields.join(" ")).map_err(|_| invalid_fen())?;
+
+    Ok((board, Chess960Castling::default()))
+}
+
+/// The square of the rook a castling right is for. `KQkq` stand for the outermost rook on either side of the king.
+fn determine_castling_rook(board: &Board, right: char) -> Option<Square> {
+    let color = match right.is_ascii_uppercase() {
+        true => Color::White,
+        false => Color::Black,
+    };
+    let king = board.king_square(color);
+    if king.get_rank() != color.to_my_backrank() {
+        return None;
+    }
+
+    let is_own_rook = |file: &File| {
+        let square = Square::make_square(king.get_rank(), *file);
+        board.piece_on(square) == Some(Piece::Rook) && board.color_on(square) == Some(color)
+    };
+    let rook_file = match right.to_ascii_lowercase() {
+        'k' => ALL_FILES[king.get_file().to_index() + 1..].iter().rev().copied().find(is_own_rook),
+        'q' => ALL_FILES[..king.get_file().to_index()].iter().copied().find(is_own_rook),
+        file @ 'a'..='h' => Some(File::from_index(file as usize - 'a' as usize)).filter(is_own_rook),
+        _ => None,
+    }?;
+
+    Some(Square::make_square(king.get_rank(), rook_file))
+}
+
+#[test]
+fn check_castling_notations() {
+    let expected = Board::from_str("r3k2r/8/8/8/8/8/8/R3K2R w Kq - 0 1").unwrap();
+    let standard = |x| Ok((x, Chess960Castling::default()));
+
+    assert_eq!(parse_fen("r3k2r/8/8/8/8/8/8/R3K2R w Kq - 0 1"), standard(expected));
+    assert_eq!(parse_fen("r3k2r/8/8/8/8/8/8/R3K2R w Ha - 0 1"), standard(expected));
+    assert_eq!(parse_fen("r3k2r/8/8/8/8/8/8/R3K2R w - - 0 1"), standard(Board::from_str("r3k2r/8/8/8/8/8/8/R3K2R w - - 0 1").unwrap()));
+}
+
+#[test]
+fn check_chess960_castling() {
+    use chess::ChessMove;
+
+    // A Chess960 start position, in Shredder-FEN and X-FEN
+    let board = Board::from_str("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w - - 2 9").unwrap();
+    let rooks = [Square::F1, Square::H1, Square::F8, Square::H8].into_iter().fold(EMPTY, |x, y| x | BitBoard::from_square(y));
+    let expected = Ok((board, Chess960Castling::new(&board, rooks)));
+
+    assert_eq!(parse_fen("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9"), expected);
+    assert_eq!(parse_fen("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w KFkf - 2 9"), expected);
+
+    // `K` is the outermost rook on the side of the king, even with another one in the corner on the other side
+    let (board, castling) = parse_fen("1r4kr/8/8/8/8/8/8/R5KR w Kk - 0 1").unwrap();
+    assert_eq!(castling.rooks(Color::White), BitBoard::from_square(Square::H1));
+    assert_eq!(castling.rooks(Color::Black), BitBoard::from_square(Square::H8));
+    assert_eq!(castling.castles(&board), vec![ChessMove::new(Square::G1, Square::H1, None)]);
+}
+
+#[test]
+fn check_invalid_castling() {
+    // No rook to castle with
+    assert_eq!(
+        parse_fen("4k3/8/8/8/8/8/8/4K2R w Q - 0 1"),
+        Err(CommandParseError::InvalidCastling("Q".to_string())),
+    );
+    assert_eq!(
+        parse_fen("4k3/8/8/8/8/8/8/4K2R w G - 0 1"),
+        Err(CommandParseError::InvalidCastling("G".to_string())),
+    );
+    assert_eq!(parse_fen("8/8/8 w - - 0 1"), Err(CommandParseError::InvalidFen("8/8/8 w - - 0 1".to_string())));
+    assert_eq!(parse_fen("8/8/8/8/8/8/8/8"), Err(CommandParseError::InvalidFen("8/8/8/8/8/8/8/8".to_string())));
+}
@@ -3,7 +3,10 @@ use std::fmt::{Debug, Display, Formatter};
 
 pub mod protocol_interpreter;
 pub mod uci_interpreter;
+pub mod xboard_interpreter;
 pub mod stdin;
+pub mod engine_options;
+pub mod fen;
 
 #[derive(Debug)]
 pub struct ProtocolSupportError;
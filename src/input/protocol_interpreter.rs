@@ -1,27 +1,70 @@
-use chess::{Board};
+use std::str::FromStr;
+use chess::{Board, ChessMove, Color};
+use thiserror::Error;
+use crate::core::castling::Chess960Castling;
 
-#[derive(Copy, Clone, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum SupportedProtocols {
     UCI,
+    XBoard,
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
-pub enum CalculateOptions {
-    Infinite,  // Keep on calculating in perpetuity
-    MoveTime(u64),  // Time to calculate, in ms
-    Game {
-        white_time: u64,
-        black_time: u64,
-        white_increment: u64,
-        black_increment: u64,
-        // moves_to_go: u64  // moves to next time control
-    },
-    Depth(u32),
+impl SupportedProtocols {
+    /// The protocol announced by the first command of the GUI
+    pub fn from_first_line(line: &str) -> Option<Self> {
+        match line.trim() {
+            "uci" => Some(SupportedProtocols::UCI),
+            "xboard" => Some(SupportedProtocols::XBoard),
+            _ => None,
+        }
+    }
+}
+
+/// The limits of a search, as set by `go`. Every limit is optional: the search stops once any limit is reached.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CalculateOptions {
+    pub white_time: Option<u64>,  // Time left on the clock, in ms
+    pub black_time: Option<u64>,
+    pub white_increment: Option<u64>,  // Increment per move, in ms
+    pub black_increment: Option<u64>,
+    pub moves_to_go: Option<u64>,  // moves to next time control
+    pub move_time: Option<u64>,  // Time to calculate, in ms
+    pub depth: Option<u32>,
+    pub nodes: Option<u64>,
+    pub mate: Option<u32>,  // Search for a mate in `x` moves
+    pub search_moves: Vec<ChessMove>,  // Only consider these moves at the root, unless empty
+    pub ponder: bool,
+    pub infinite: bool,  // Keep on calculating in perpetuity
 }
 
-impl Default for CalculateOptions {
-    fn default() -> Self {
-        CalculateOptions::Infinite
+impl CalculateOptions {
+    pub fn infinite() -> Self {
+        CalculateOptions {
+            infinite: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn depth(depth: u32) -> Self {
+        CalculateOptions {
+            depth: Some(depth),
+            ..Default::default()
+        }
+    }
+
+    pub fn move_time(move_time: u64) -> Self {
+        CalculateOptions {
+            move_time: Some(move_time),
+            ..Default::default()
+        }
+    }
+
+    /// The time left and the increment of `side`, if its clock is given
+    pub fn clock(&self, side: Color) -> Option<(u64, u64)> {
+        match side {
+            Color::White => self.white_time.map(|x| (x, self.white_increment.unwrap_or(0))),
+            Color::Black => self.black_time.map(|x| (x, self.black_increment.unwrap_or(0))),
+        }
     }
 }
 
@@ -37,17 +80,65 @@ pub enum Command {
     Identify,
     ToggleDebug(DebugState),
     IsReady,  // queries whether the engine is finished long task, e.g. initializing, loading opening table
-    // SetOption {OptionValue},
+    Ping(u32),  // `ping` in xboard: like `IsReady`, but the reply echoes the number
+    SetOption {
+        name: String,
+        value: Option<String>,
+    },
     NewGame,
-    SetPosition(Board, Vec<u64>),  // sets the board position for that game
+    SetPosition(Board, Chess960Castling, Vec<u64>),  // sets the board position for that game, with the castling rights the board can't hold
+    RejectPosition,  // The position sent couldn't be read: nothing is searched until the next one
     Calculate(CalculateOptions),  // `go` in UCI: Start calculating
     Stop,  // Stop Calculating, otherwise ignore
-    // Ponder,  see UCI doc
-    // PonderHit,
+    PonderHit,  // The opponent played the move pondered on: start using the clock
+    ShowThinking(bool),  // `post` and `nopost` in xboard: whether to send the search info
+    Perft(u32),  // Count the move sequences of this depth from the current position
+    ShowPosition,  // `d`: print the current board
+    Evaluate,  // `eval`: print the static evaluation of the current board
+    Bench(Option<u32>),  // Search the bench positions, to the given depth or the default
     Quit,  // exit the program
 }
 
 
+#[derive(Error, Debug, Clone, Eq, PartialEq)]
+pub enum CommandParseError {
+    #[error("empty line")]
+    Empty,
+    #[error("unknown command {0}")]
+    UnknownCommand(String),
+    #[error("invalid FEN {0}")]
+    InvalidFen(String),
+    #[error("castling rights {0} need the king on the e-file and the rook in the corner")]
+    UnsupportedCastling(String),
+    #[error("invalid castling rights {0}")]
+    InvalidCastling(String),
+    #[error("illegal move {0}")]
+    IllegalMove(String),
+    #[error("invalid number {0}")]
+    InvalidNumber(String),
+    #[error("unknown {command} parameter {subcommand}")]
+    UnknownSubcommand {
+        command: String,
+        subcommand: String,
+    },
+    #[error("no value given for {0}")]
+    MissingValue(String),
+}
+
+/// Turns the lines sent by the GUI into commands.
+/// A line may need several commands, or none, since some protocols leave it to the engine to keep track of the game.
 pub trait ProtocolInterpreter {
-    fn line_to_command(line: &str) -> Option<Command>;
+    fn line_to_commands(&mut self, line: &str) -> Result<Vec<Command>, CommandParseError>;
+
+    /// The commands for a line that couldn't be parsed, besides reporting it
+    fn rejected_line_commands(&mut self, _line: &str) -> Vec<Command> {
+        vec![]
+    }
+}
+
+/// Parses the value of the parameter `name`
+pub(crate) fn parse_number<T: FromStr>(value: Option<&str>, name: &str) -> Result<T, CommandParseError> {
+    let value = value.ok_or_else(|| CommandParseError::MissingValue(name.to_string()))?;
+
+    value.parse::<T>().map_err(|_| CommandParseError::InvalidNumber(value.to_string()))
 }
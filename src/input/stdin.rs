@@ -1,22 +1,33 @@
 use std::io;
 use std::io::BufRead;
 use std::sync::mpsc::Sender;
+use crate::debug_log;
+use crate::output;
 
-use super::protocol_interpreter::Command;
-use super::uci_interpreter::UciInterpreter;
+use super::protocol_interpreter::{Command, CommandParseError};
 use super::protocol_interpreter::ProtocolInterpreter;
 
-pub fn listen_to_stdin(input_tx: Sender<Command>) -> ! {
+pub fn listen_to_stdin(input_tx: Sender<Command>, mut interpreter: impl ProtocolInterpreter) -> ! {
     loop {
         let mut buffer = String::new();
         io::stdin().lock().read_line(&mut buffer).expect("Failed stdin read");
 
-        let command = UciInterpreter::line_to_command(&buffer);
+        let commands = interpreter.line_to_commands(&buffer);
+        debug_log!("received {:?}", commands);
 
-        if let Some(val) = command.clone() {
-            input_tx.send(val).unwrap();
+        match commands {
+            Ok(commands) => {
+                for command in commands {
+                    input_tx.send(command).unwrap();
+                }
+            },
+            Err(CommandParseError::Empty) => (),
+            Err(error) => {
+                output::parse_error(&buffer, &error);  // Report, but keep the engine running
+                for command in interpreter.rejected_line_commands(&buffer) {
+                    input_tx.send(command).unwrap();
+                }
+            },
         }
-
-        println!("{:?}", command);
     }
-}
\ No newline at end of file
+}
@@ -1,187 +1,195 @@
 use std::str::{FromStr};
 use chess::{Board, ChessMove, Square};
 use itertools::Itertools;
-use lazy_static::lazy_static;
-use regex::Regex;
 
-use crate::input::protocol_interpreter::{CalculateOptions, DebugState};
-use crate::input::protocol_interpreter::CalculateOptions::Game;
+use crate::core::castling::{from_king_takes_rook, Chess960Castling};
+use crate::input::fen::parse_fen;
+use crate::input::protocol_interpreter::{CalculateOptions, CommandParseError, DebugState, parse_number};
 use super::protocol_interpreter::{Command, ProtocolInterpreter};
 
 // Interpreter for the Universal Chess Interface protocol
 pub struct UciInterpreter;
 
+const GO_PARAMETERS: [&str; 12] = [
+    "wtime", "btime", "winc", "binc", "movestogo", "movetime", "depth", "nodes", "mate", "ponder", "infinite", "searchmoves",
+];
+
 impl UciInterpreter {
-    pub fn determine_board<'a>(mut args: impl Iterator<Item=&'a str>) -> Board {
-        match args.next() {
-            Some("startpos") => {
-                let mut board = Board::default();
+    pub fn determine_board<'a>(args: impl Iterator<Item=&'a str>) -> Result<Board, CommandParseError> {
+        UciInterpreter::determine_position(args).map(|(board, _, _)| board)
+    }
 
-                if args.next() == Some("moves") {
-                    for arg in args {
-                        board = board.make_move_new(ChessMove::from_str(&arg).expect("non-chess move specified"));
-                    }
-                }
+    /// The board after the moves, with its Chess960 castling rights and the hashes of the positions before it.
+    /// Each move has to be legal in the position it's played in.
+    pub fn determine_position<'a>(args: impl Iterator<Item=&'a str>) -> Result<(Board, Chess960Castling, Vec<u64>), CommandParseError> {
+        let args = args.collect::<Vec<_>>();
+        let (mut board, mut castling) = UciInterpreter::determine_pre_move_board(args.iter().copied())?;
+        let mut visited_boards = vec![];
 
-                board
-            },
-            Some("fen") => {
-                let mut args_owned: Vec<String> = Vec::new();
-                for arg in args {
-                    args_owned.push(arg.to_string());
-                }
-                let mut board = Board::from_str(&args_owned.join(" ")).expect("invalid FEN code");
-
-                let args = args_owned.join(" ");
-                if let Some(moves) = args.split("moves ").skip(1).next() {
-                    for chess_move in moves.split(" ").into_iter() {
-                        board = board.make_move_new(ChessMove::from_str(chess_move).expect("non-chess move specified"));
-                    }
-                }
+        for arg in args.into_iter().skip_while(|x| *x != "moves").skip(1) {
+            let chess_move = UciInterpreter::determine_move(&board, castling, arg)?;
 
-                board
-            },
-            x => panic!("unsupported position parameters {:?}", x),
+            visited_boards.push(board.get_hash());
+            board = castling.make_move(&board, chess_move);
+            castling = castling.after_move(&board);
         }
+
+        Ok((board, castling, visited_boards))
     }
 
-    pub fn determine_pre_move_board<'a>(mut args: impl Iterator<Item=&'a str>) -> Board {
+    pub fn determine_pre_move_board<'a>(mut args: impl Iterator<Item=&'a str>) -> Result<(Board, Chess960Castling), CommandParseError> {
         match args.next() {
-            Some("startpos") => {
-                Board::default()
-            },
+            Some("startpos") => Ok((Board::default(), Chess960Castling::default())),
             Some("fen") => {
-                let mut args_owned: Vec<String> = Vec::new();
-                for arg in args {
-                    args_owned.push(arg.to_string());
-                }
-                Board::from_str(&args_owned.join(" ")).expect("invalid FEN code")
+                let fen = args
+                    .take_while(|x| *x != "moves")
+                    .join(" ");
+
+                parse_fen(&fen)
             },
-            _ => panic!("unsupported position parameters")
+            Some(other) => Err(CommandParseError::UnknownSubcommand {
+                command: "position".to_string(),
+                subcommand: other.to_string(),
+            }),
+            None => Err(CommandParseError::MissingValue("position".to_string())),
         }
     }
 
-    fn determine_calculate_options<'a>(mut args: impl Iterator<Item=&'a str>) -> CalculateOptions {
-        match args.next() {
-            Some("infinite") => CalculateOptions::Infinite,
-            Some("movetime") => CalculateOptions::MoveTime(
-                args
-                    .next()
-                    .expect("no movetime value specified")
-                    .parse::<u64>()
-                    .expect("movetime must be a positive integer")
-            ),
-            Some("wtime") => {
-                lazy_static! {
-                    static ref TIME_REGEX: Regex = Regex::new(r"(?P<wtime>\d+) btime (?P<btime>\d+) winc (?P<winc>\d+) binc (?P<binc>\d+)").unwrap();
-                }
-
-                let args_joined = args.join(" ");
-                let capture = TIME_REGEX.captures(&args_joined).expect("invalid calculation times set");
-
-                let wtime = capture.name("wtime").unwrap();
-                let btime = capture.name("btime").unwrap();
-                let winc = capture.name("winc").unwrap();
-                let binc = capture.name("binc").unwrap();
-
-                let wtime = wtime.as_str().parse::<u64>().expect("wtime must be a positive integer");
-                let btime = btime.as_str().parse::<u64>().expect("btime must be a positive integer");
-                let winc = winc.as_str().parse::<u64>().expect("winc must be a positive integer");
-                let binc = binc.as_str().parse::<u64>().expect("binc must be a positive integer");
-
-                Game {
-                    white_time: wtime,
-                    black_time: btime,
-                    white_increment: winc,
-                    black_increment: binc,
-                }
+    /// Castles are written as the king taking its rook in Chess960, which the move generator only knows as king moves
+    /// for the castling rights the board holds itself
+    fn determine_move(board: &Board, castling: Chess960Castling, arg: &str) -> Result<ChessMove, CommandParseError> {
+        ChessMove::from_str(arg)
+            .ok()
+            .map(|x| match castling.is_empty() {
+                true => from_king_takes_rook(board, x),
+                false => x,
+            })
+            .filter(|x| castling.is_legal(board, *x))
+            .ok_or_else(|| CommandParseError::IllegalMove(arg.to_string()))
+    }
+
+    fn determine_calculate_options<'a>(args: impl Iterator<Item=&'a str>) -> Result<CalculateOptions, CommandParseError> {
+        let mut options = CalculateOptions::default();
+        let mut args = args.peekable();
+
+        while let Some(arg) = args.next() {
+            match arg {
+                "wtime" => options.white_time = Some(parse_number(args.next(), arg)?),
+                "btime" => options.black_time = Some(parse_number(args.next(), arg)?),
+                "winc" => options.white_increment = Some(parse_number(args.next(), arg)?),
+                "binc" => options.black_increment = Some(parse_number(args.next(), arg)?),
+                "movestogo" => options.moves_to_go = Some(parse_number(args.next(), arg)?),
+                "movetime" => options.move_time = Some(parse_number(args.next(), arg)?),
+                "depth" => options.depth = Some(parse_number(args.next(), arg)?),
+                "nodes" => options.nodes = Some(parse_number(args.next(), arg)?),
+                "mate" => options.mate = Some(parse_number(args.next(), arg)?),
+                "ponder" => options.ponder = true,
+                "infinite" => options.infinite = true,
+                "searchmoves" => {
+                    // The moves run until the next parameter
+                    while let Some(chess_move) = args.next_if(|x| !GO_PARAMETERS.contains(x)) {
+                        options.search_moves.push(
+                            ChessMove::from_str(chess_move)
+                                .map_err(|_| CommandParseError::IllegalMove(chess_move.to_string()))?
+                        );
+                    }
+
+                    if options.search_moves.is_empty() {
+                        return Err(CommandParseError::MissingValue(arg.to_string()));
+                    }
+                },
+                other => return Err(CommandParseError::UnknownSubcommand {
+                    command: "go".to_string(),
+                    subcommand: other.to_string(),
+                }),
             }
-            Some("depth") => {
-                CalculateOptions::Depth(
-                    args
-                        .next()
-                        .expect("no depth value specified")
-                        .parse::<u64>()
-                        .expect("depth must be a positive integer") as u32
-                )
-            },
-            Some(_other) => CalculateOptions::Infinite,  // TODO
-            // Some(_) => panic!("unsupported calculate option"),
-            None => CalculateOptions::Infinite,
         }
+
+        Ok(options)
     }
 
-    fn determine_debug_state<'a>(mut args: impl Iterator<Item=&'a str>) -> DebugState {
+    fn determine_debug_state<'a>(mut args: impl Iterator<Item=&'a str>) -> Result<DebugState, CommandParseError> {
         match args.next() {
-            Some("on") => DebugState::On,
-            Some("off") => DebugState::Off,
-            _ => panic!("unknown debug state"),
+            Some("on") => Ok(DebugState::On),
+            Some("off") => Ok(DebugState::Off),
+            Some(other) => Err(CommandParseError::UnknownSubcommand {
+                command: "debug".to_string(),
+                subcommand: other.to_string(),
+            }),
+            None => Err(CommandParseError::MissingValue("debug".to_string())),
         }
     }
 
-    /// Returns a vector of hashes of visited board positions
-    pub fn determine_visited_boards<'a>(board: &Board, mut args: impl Iterator<Item=&'a str>) -> Vec<u64> {
-        let moves = {
-            let mut result = Vec::new();
-            let mut listing_moves = false;
-            for arg in args {
-                if listing_moves {
-                    result.push(ChessMove::from_str(arg).expect("expected a chess move"));
-                } else {
-                    if arg == "moves" {
-                        listing_moves = true;
-                    }
-                }
-            }
-            result
-        };
-
-        let mut result = Vec::from([board.get_hash()]);
+    /// Parses `name <id> [value <x>]`: both the name and the value may contain spaces
+    fn determine_option<'a>(mut args: impl Iterator<Item=&'a str>) -> Result<(String, Option<String>), CommandParseError> {
+        let args_joined = args.join(" ");
+        let option = args_joined.strip_prefix("name ")
+            .ok_or_else(|| CommandParseError::MissingValue("setoption name".to_string()))?;
 
-        let mut current_board = *board;
-        for chess_move in moves {
-            current_board = current_board.make_move_new(chess_move);
-            result.push(current_board.get_hash());
-        }
-        // Remove last board, since that board is the actual one in play,
-        result.pop();
+        let (name, value) = match option.split_once(" value ") {
+            Some((name, value)) => (name, Some(value.to_string())),
+            None => (option.strip_suffix(" value").unwrap_or(option), None),
+        };
 
-        result
+        Ok((name.to_string(), value))
     }
-}
 
-impl ProtocolInterpreter for UciInterpreter {
-    fn line_to_command(line: &str) -> Option<Command> {
+    pub fn line_to_command(line: &str) -> Result<Command, CommandParseError> {
         let mut split = line.split_whitespace();
 
-        let command_word = split.next()?;
+        let command_word = split.next().ok_or(CommandParseError::Empty)?;
 
         match command_word {
-            "uci" => Some(Command::Identify),
-            // "debug" => Some(Command::ToggleDebug),
-            "debug" => Some(Command::ToggleDebug(UciInterpreter::determine_debug_state(split.into_iter()))),
-            "isready" => Some(Command::IsReady),
-            // "setoption" => ,
-            "ucinewgame" => Some(Command::NewGame),
+            "uci" => Ok(Command::Identify),
+            "debug" => Ok(Command::ToggleDebug(UciInterpreter::determine_debug_state(split.into_iter())?)),
+            "isready" => Ok(Command::IsReady),
+            "setoption" => {
+                let (name, value) = UciInterpreter::determine_option(split)?;
+                Ok(Command::SetOption {
+                    name,
+                    value,
+                })
+            },
+            "ucinewgame" => Ok(Command::NewGame),
             "position" => {
-                let board = UciInterpreter::determine_board(split.clone().into_iter());
-                let pre_move_board = UciInterpreter::determine_pre_move_board(split.clone().into_iter());
-                Some(Command::SetPosition(
-                    board.clone(),
-                    UciInterpreter::determine_visited_boards(&pre_move_board, split.into_iter())
-                ))
+                let (board, castling, visited_boards) = UciInterpreter::determine_position(split)?;
+                Ok(Command::SetPosition(board, castling, visited_boards))
+            },
+            // Not part of UCI, but the usual way to ask for a perft
+            "go" if split.clone().next() == Some("perft") => Ok(Command::Perft(parse_number(split.nth(1), "perft")?)),
+            "go" => Ok(Command::Calculate(UciInterpreter::determine_calculate_options(split.into_iter())?)),
+            "perft" => Ok(Command::Perft(parse_number(split.next(), command_word)?)),
+            "d" => Ok(Command::ShowPosition),
+            "eval" => Ok(Command::Evaluate),
+            "bench" => match split.next() {
+                Some(depth) => Ok(Command::Bench(Some(parse_number(Some(depth), command_word)?))),
+                None => Ok(Command::Bench(None)),
             },
-            "go" => Some(Command::Calculate(UciInterpreter::determine_calculate_options(split.into_iter()))),
-            "stop" => Some(Command::Stop),
-            "quit" => Some(Command::Quit),
-            _ => None,
+            "stop" => Ok(Command::Stop),
+            "ponderhit" => Ok(Command::PonderHit),
+            "quit" => Ok(Command::Quit),
+            other => Err(CommandParseError::UnknownCommand(other.to_string())),
+        }
+    }
+}
+
+impl ProtocolInterpreter for UciInterpreter {
+    fn line_to_commands(&mut self, line: &str) -> Result<Vec<Command>, CommandParseError> {
+        UciInterpreter::line_to_command(line).map(|x| vec![x])
+    }
+
+    /// The GUI expects the next `go` to search the position it sent, not the one before
+    fn rejected_line_commands(&mut self, line: &str) -> Vec<Command> {
+        match line.split_whitespace().next() {
+            Some("position") => vec![Command::RejectPosition],
+            _ => vec![],
         }
     }
 }
 
 #[test]
 fn check_debug_toggle() {
-    let expected = Some(Command::ToggleDebug(DebugState::On));
+    let expected = Ok(Command::ToggleDebug(DebugState::On));
 
     assert_eq!(UciInterpreter::line_to_command("debug on"), expected);
 }
@@ -190,7 +198,7 @@ fn check_debug_toggle() {
 fn check_position_fen_command() {
     let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
     let expected_board = Board::from_str(fen).unwrap();
-    let expected = Some(Command::SetPosition(expected_board, vec![]));
+    let expected = Ok(Command::SetPosition(expected_board, Chess960Castling::default(), vec![]));
 
     let command_str = format!("{} {}", "position fen", fen);
 
@@ -199,7 +207,7 @@ fn check_position_fen_command() {
 
 #[test]
 fn check_position_start() {
-    let expected = Some(Command::SetPosition(Board::default(), vec![]));
+    let expected = Ok(Command::SetPosition(Board::default(), Chess960Castling::default(), vec![]));
 
     let command_str = "position startpos";
     assert_eq!(UciInterpreter::line_to_command(&command_str), expected);
@@ -210,33 +218,164 @@ fn check_position_start_with_moves() {
     let board = Board::default();
     let visited = vec![board.get_hash()];
     let board = board.make_move_new(ChessMove::new(Square::D2, Square::D4, None));
-    let expected = Some(Command::SetPosition(board, visited));
+    let expected = Ok(Command::SetPosition(board, Chess960Castling::default(), visited));
     let command_str = "position startpos moves d2d4";
 
     assert_eq!(UciInterpreter::line_to_command(command_str), expected);
 }
 
+#[test]
+fn check_position_chess960() {
+    use chess::{Color, EMPTY};
+
+    let board = Board::from_str("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+    let visited = vec![board.get_hash()];
+    let expected = Ok(Command::SetPosition(
+        board.make_move_new(ChessMove::new(Square::E1, Square::G1, None)),
+        Chess960Castling::default(),
+        visited,
+    ));
+    assert_eq!(UciInterpreter::line_to_command("position fen r3k2r/8/8/8/8/8/8/R3K2R w HAha - 0 1 moves e1h1"), expected);
+
+    // The rooks on b1 and g1: castling short leaves the king on g1 and black keeps both castles
+    let (board, castling, visited) = UciInterpreter::determine_position(
+        "fen 1r2k1r1/pppppppp/8/8/8/8/PPPPPPPP/1R2K1R1 w GBgb - 0 1 moves e1g1".split_whitespace(),
+    ).unwrap();
+    assert_eq!(board, Board::from_str("1r2k1r1/pppppppp/8/8/8/8/PPPPPPPP/1R3RK1 b - - 1 1").unwrap());
+    assert!(castling.rooks(Color::White) == EMPTY && castling.rooks(Color::Black).popcnt() == 2);
+    assert_eq!(visited.len(), 1);
+
+    // Without the rights, the king can't take its rook
+    assert_eq!(
+        UciInterpreter::line_to_command("position fen 1r2k1r1/8/8/8/8/8/8/1R2K1R1 w gb - 0 1 moves e1g1"),
+        Err(CommandParseError::IllegalMove("e1g1".to_string())),
+    );
+}
+
+#[test]
+fn check_rejected_position() {
+    let mut interpreter = UciInterpreter;
+    let line = "position fen 1r2k1r1/8/8/8/8/8/8/1R2K1R1 w Cc - 0 1";
+
+    assert_eq!(interpreter.line_to_commands(line), Err(CommandParseError::InvalidCastling("Cc".to_string())));
+    assert_eq!(interpreter.rejected_line_commands(line), vec![Command::RejectPosition]);
+    assert_eq!(interpreter.rejected_line_commands("go sideways"), vec![]);
+}
+
+#[test]
+fn check_set_option() {
+    let expected = Ok(Command::SetOption {
+        name: "Conspiracy Bucket Size".to_string(),
+        value: Some("25".to_string()),
+    });
+    assert_eq!(UciInterpreter::line_to_command("setoption name Conspiracy Bucket Size value 25"), expected);
+
+    let expected = Ok(Command::SetOption {
+        name: "Clear Hash".to_string(),
+        value: None,
+    });
+    assert_eq!(UciInterpreter::line_to_command("setoption name Clear Hash"), expected);
+
+    assert_eq!(UciInterpreter::line_to_command("setoption value 25"), Err(CommandParseError::MissingValue("setoption name".to_string())));
+}
+
 #[test]
 fn check_calculate_infinite() {
-    let expected = Some(Command::Calculate(CalculateOptions::Infinite));
+    let expected = Ok(Command::Calculate(CalculateOptions::infinite()));
 
     let command_str = "go infinite";
     assert_eq!(UciInterpreter::line_to_command(&command_str), expected);
 
+    let expected = Err(CommandParseError::UnknownSubcommand { command: "go".to_string(), subcommand: "asd".to_string() });
+
     let command_str = "go infinite asd";
     assert_eq!(UciInterpreter::line_to_command(&command_str), expected);
 }
 
 #[test]
 fn check_calculate_movetime() {
-    let expected = Some(Command::Calculate(CalculateOptions::MoveTime(2000)));
+    let expected = Ok(Command::Calculate(CalculateOptions::move_time(2000)));
 
     let command_str = "go movetime 2000";
     assert_eq!(UciInterpreter::line_to_command(&command_str), expected);
 }
 
 #[test]
-#[should_panic]
-fn assert_movetime_panic() {
-    UciInterpreter::line_to_command("go movetime -1");
-}
\ No newline at end of file
+fn check_calculate_any_order() {
+    let expected = Ok(Command::Calculate(CalculateOptions {
+        white_time: Some(60000),
+        black_time: Some(50000),
+        white_increment: Some(1000),
+        black_increment: None,
+        moves_to_go: Some(20),
+        nodes: Some(100000),
+        search_moves: vec![
+            ChessMove::new(Square::E2, Square::E4, None),
+            ChessMove::new(Square::D2, Square::D4, None),
+        ],
+        ponder: true,
+        ..Default::default()
+    }));
+
+    let command_str = "go ponder movestogo 20 btime 50000 searchmoves e2e4 d2d4 winc 1000 nodes 100000 wtime 60000";
+    assert_eq!(UciInterpreter::line_to_command(&command_str), expected);
+}
+
+#[test]
+fn check_calculate_limits() {
+    let expected = Ok(Command::Calculate(CalculateOptions {
+        depth: Some(8),
+        mate: Some(3),
+        ..Default::default()
+    }));
+    assert_eq!(UciInterpreter::line_to_command("go mate 3 depth 8"), expected);
+
+    let expected = Ok(Command::Calculate(CalculateOptions::default()));
+    assert_eq!(UciInterpreter::line_to_command("go"), expected);
+
+    let expected = Err(CommandParseError::MissingValue("searchmoves".to_string()));
+    assert_eq!(UciInterpreter::line_to_command("go searchmoves infinite"), expected);
+}
+
+#[test]
+fn check_ponder() {
+    let expected = Ok(Command::Calculate(CalculateOptions {
+        white_time: Some(1000),
+        black_time: Some(1000),
+        ponder: true,
+        ..Default::default()
+    }));
+    assert_eq!(UciInterpreter::line_to_command("go ponder wtime 1000 btime 1000"), expected);
+
+    assert_eq!(UciInterpreter::line_to_command("ponderhit"), Ok(Command::PonderHit));
+}
+
+#[test]
+fn check_parse_errors() {
+    assert_eq!(UciInterpreter::line_to_command("  \n"), Err(CommandParseError::Empty));
+    assert_eq!(UciInterpreter::line_to_command("register later"), Err(CommandParseError::UnknownCommand("register".to_string())));
+    assert_eq!(UciInterpreter::line_to_command("go movetime -1"), Err(CommandParseError::InvalidNumber("-1".to_string())));
+    assert_eq!(UciInterpreter::line_to_command("go depth"), Err(CommandParseError::MissingValue("depth".to_string())));
+    assert_eq!(UciInterpreter::line_to_command("position fen 8/8/8 w - - 0 1"), Err(CommandParseError::InvalidFen("8/8/8 w - - 0 1".to_string())));
+    assert_eq!(UciInterpreter::line_to_command("position startpos moves e2e4 e2e4"), Err(CommandParseError::IllegalMove("e2e4".to_string())));
+    assert_eq!(
+        UciInterpreter::line_to_command("go sideways"),
+        Err(CommandParseError::UnknownSubcommand { command: "go".to_string(), subcommand: "sideways".to_string() }),
+    );
+}
+
+#[test]
+fn check_perft() {
+    assert_eq!(UciInterpreter::line_to_command("go perft 5"), Ok(Command::Perft(5)));
+    assert_eq!(UciInterpreter::line_to_command("perft 3"), Ok(Command::Perft(3)));
+    assert_eq!(UciInterpreter::line_to_command("go perft"), Err(CommandParseError::MissingValue("perft".to_string())));
+}
+
+#[test]
+fn check_diagnostic_commands() {
+    assert_eq!(UciInterpreter::line_to_command("d"), Ok(Command::ShowPosition));
+    assert_eq!(UciInterpreter::line_to_command("eval"), Ok(Command::Evaluate));
+    assert_eq!(UciInterpreter::line_to_command("bench"), Ok(Command::Bench(None)));
+    assert_eq!(UciInterpreter::line_to_command("bench 3"), Ok(Command::Bench(Some(3))));
+    assert_eq!(UciInterpreter::line_to_command("bench deep"), Err(CommandParseError::InvalidNumber("deep".to_string())));
+}
@@ -0,0 +1,373 @@
+use std::str::FromStr;
+use std::sync::mpsc::Receiver;
+use chess::{Board, ChessMove, Color, Square};
+use itertools::Itertools;
+
+use crate::core::castling::Chess960Castling;
+use crate::input::fen::parse_fen;
+use crate::input::protocol_interpreter::{CalculateOptions, CommandParseError, parse_number};
+use super::protocol_interpreter::{Command, ProtocolInterpreter};
+
+// Interpreter for the Chess Engine Communication Protocol, as used by xboard and WinBoard.
+// Unlike UCI, the GUI only sends the moves: the interpreter keeps track of the game, including the engine's own moves.
+pub struct XBoardInterpreter {
+    board: Board,
+    visited_boards: Vec<u64>, // Hashes of the positions before `board`
+    plies_played: u64, // Since the start of the time control
+    force: bool, // Only keep track of the moves, without playing either side
+    time_control: TimeControl,
+    depth: Option<u32>, // Set by `sd`
+    own_time: Option<u64>, // in ms, set by `time`
+    opponent_time: Option<u64>, // in ms, set by `otim`
+    engine_moves: Receiver<(u64, ChessMove)>, // The moves played by the engine, which the GUI doesn't repeat, with their position id
+    position_id: u64, // How many positions were sent: the main loop counts them too, and tags the engine's moves with it
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum TimeControl {
+    /// Set by `level`, all in ms
+    Conventional {
+        moves_per_session: u64, // 0 for the whole game
+        base: u64,
+        increment: u64,
+    },
+    /// Set by `st`: the exact time per move, in ms
+    MoveTime(u64),
+}
+
+impl Default for TimeControl {
+    fn default() -> Self {
+        // xboard's own default: 40 moves in 5 minutes
+        TimeControl::Conventional {
+            moves_per_session: 40,
+            base: 5 * 60 * 1000,
+            increment: 0,
+        }
+    }
+}
+
+impl XBoardInterpreter {
+    pub fn new(engine_moves: Receiver<(u64, ChessMove)>) -> Self {
+        XBoardInterpreter {
+            board: Board::default(),
+            visited_boards: vec![],
+            plies_played: 0,
+            force: false,
+            time_control: TimeControl::default(),
+            depth: None,
+            own_time: None,
+            opponent_time: None,
+            engine_moves,
+            position_id: 0,
+        }
+    }
+
+    /// Catches up with the moves the engine played since the last command.
+    /// Replies to a position that was replaced since, by a new game or force mode, are dropped.
+    fn apply_engine_moves(&mut self) {
+        while let Ok((position_id, chess_move)) = self.engine_moves.try_recv() {
+            if position_id == self.position_id && self.board.legal(chess_move) {
+                self.play_move(chess_move);
+            }
+        }
+    }
+
+    fn play_move(&mut self, chess_move: ChessMove) {
+        self.visited_boards.push(self.board.get_hash());
+        self.board = self.board.make_move_new(chess_move);
+        self.plies_played += 1;
+    }
+
+    fn set_board(&mut self, board: Board) {
+        self.board = board;
+        self.visited_boards = vec![];
+        self.plies_played = 0;
+    }
+
+    fn determine_user_move(&mut self, arg: Option<&str>) -> Result<Vec<Command>, CommandParseError> {
+        let arg = arg.ok_or_else(|| CommandParseError::MissingValue("usermove".to_string()))?;
+        let chess_move = ChessMove::from_str(arg)
+            .ok()
+            .filter(|x| self.board.legal(*x))
+            .ok_or_else(|| CommandParseError::IllegalMove(arg.to_string()))?;
+
+        self.play_move(chess_move);
+
+        // Outside force mode, the engine answers every move of its opponent
+        let mut commands = vec![self.position_command()];
+        if !self.force {
+            commands.push(Command::Calculate(self.determine_calculate_options()));
+        }
+
+        Ok(commands)
+    }
+
+    fn position_command(&mut self) -> Command {
+        self.position_id += 1;
+        Command::SetPosition(self.board, Chess960Castling::default(), self.visited_boards.clone())
+    }
+
+    /// Stops the search, and sends the position again, so a reply still on its way is dropped
+    fn abandon_search(&mut self) -> Vec<Command> {
+        vec![Command::Stop, self.position_command()]
+    }
+
+    /// The engine plays the side to move, so its clock belongs to that side
+    fn determine_calculate_options(&self) -> CalculateOptions {
+        let mut options = CalculateOptions {
+            depth: self.depth,
+            ..Default::default()
+        };
+
+        match self.time_control {
+            TimeControl::MoveTime(x) => options.move_time = Some(x),
+            TimeControl::Conventional { moves_per_session, base, increment } => {
+                let own_time = self.own_time.unwrap_or(base);
+                let opponent_time = self.opponent_time.unwrap_or(base);
+                let (white_time, black_time) = match self.board.side_to_move() {
+                    Color::White => (own_time, opponent_time),
+                    Color::Black => (opponent_time, own_time),
+                };
+
+                options.white_time = Some(white_time);
+                options.black_time = Some(black_time);
+                options.white_increment = Some(increment);
+                options.black_increment = Some(increment);
+                if moves_per_session > 0 {
+                    options.moves_to_go = Some(moves_per_session - (self.plies_played / 2) % moves_per_session);
+                }
+            },
+        }
+
+        options
+    }
+
+    /// Parses `level MPS BASE INC`, where the base is in minutes or `minutes:seconds`, and the increment in seconds
+    fn determine_level<'a>(mut args: impl Iterator<Item=&'a str>) -> Result<TimeControl, CommandParseError> {
+        let moves_per_session = parse_number(args.next(), "level")?;
+        let base = args.next().ok_or_else(|| CommandParseError::MissingValue("level".to_string()))?;
+        let base = match base.split_once(':') {
+            Some((minutes, seconds)) => {
+                parse_number::<u64>(Some(minutes), "level")? * 60_000 + parse_number::<u64>(Some(seconds), "level")? * 1000
+            },
+            None => parse_number::<u64>(Some(base), "level")? * 60_000,
+        };
+        let increment = parse_seconds(args.next(), "level")?;
+
+        Ok(TimeControl::Conventional {
+            moves_per_session,
+            base,
+            increment,
+        })
+    }
+}
+
+impl ProtocolInterpreter for XBoardInterpreter {
+    fn line_to_commands(&mut self, line: &str) -> Result<Vec<Command>, CommandParseError> {
+        self.apply_engine_moves();
+
+        let mut split = line.split_whitespace();
+        let command_word = split.next().ok_or(CommandParseError::Empty)?;
+
+        match command_word {
+            "protover" => Ok(vec![Command::Identify]),
+            "new" => {
+                self.set_board(Board::default());
+                self.force = false;
+                self.depth = None;
+                self.own_time = None;
+                self.opponent_time = None;
+                let mut commands = self.abandon_search();
+                commands.insert(1, Command::NewGame);
+                Ok(commands)
+            },
+            "setboard" => {
+                let fen = split.join(" ");
+                // The moves of xboard only castle as the move generator does
+                let (board, castling) = parse_fen(&fen)?;
+                if !castling.is_empty() {
+                    return Err(CommandParseError::UnsupportedCastling(fen.split_whitespace().nth(2).unwrap_or_default().to_string()));
+                }
+                self.set_board(board);
+                Ok(self.abandon_search())
+            },
+            "force" => {
+                self.force = true;
+                Ok(self.abandon_search())
+            },
+            "go" => {
+                self.force = false;
+                Ok(vec![Command::Calculate(self.determine_calculate_options())])
+            },
+            "usermove" => self.determine_user_move(split.next()),
+            "level" => {
+                self.time_control = XBoardInterpreter::determine_level(split)?;
+                Ok(vec![])
+            },
+            "st" => {
+                self.time_control = TimeControl::MoveTime(parse_seconds(split.next(), command_word)?);
+                Ok(vec![])
+            },
+            "sd" => {
+                self.depth = Some(parse_number(split.next(), command_word)?);
+                Ok(vec![])
+            },
+            // Clocks are given in centiseconds
+            "time" => {
+                self.own_time = Some(parse_number::<u64>(split.next(), command_word)? * 10);
+                Ok(vec![])
+            },
+            "otim" => {
+                self.opponent_time = Some(parse_number::<u64>(split.next(), command_word)? * 10);
+                Ok(vec![])
+            },
+            "post" => Ok(vec![Command::ShowThinking(true)]),
+            "nopost" => Ok(vec![Command::ShowThinking(false)]),
+            "ping" => Ok(vec![Command::Ping(parse_number(split.next(), command_word)?)]),
+            "?" => Ok(vec![Command::Stop]),
+            "quit" => Ok(vec![Command::Quit]),
+            "result" => Ok(self.abandon_search()),
+            // The protocol was already picked, and the rest doesn't affect how the engine plays
+            "xboard" | "accepted" | "rejected" | "random" | "hard" | "easy" | "computer" | "name" => Ok(vec![]),
+            // Moves without `usermove`, for GUIs that didn't accept the feature
+            other if is_move_notation(other) => self.determine_user_move(Some(other)),
+            other => Err(CommandParseError::UnknownCommand(other.to_string())),
+        }
+    }
+}
+
+/// The features announced in reply to `protover`
+pub fn feature_line(engine_name: &str) -> String {
+    format!("feature myname=\"{engine_name}\" setboard=1 usermove=1 ping=1 sigint=0 sigterm=0 colors=0 analyze=0 done=1")
+}
+
+/// Parses a number of seconds, which may have a fractional part, into ms
+fn parse_seconds(value: Option<&str>, name: &str) -> Result<u64, CommandParseError> {
+    let seconds: f64 = parse_number(value, name)?;
+
+    Ok((seconds * 1000.0) as u64)
+}
+
+fn is_move_notation(word: &str) -> bool {
+    word.len() >= 4 && word.is_char_boundary(4) && Square::from_str(&word[..2]).is_ok() && Square::from_str(&word[2..4]).is_ok()
+}
+
+#[test]
+fn check_new_game() {
+    let (_, engine_moves_rx) = std::sync::mpsc::channel();
+    let mut interpreter = XBoardInterpreter::new(engine_moves_rx);
+
+    let expected = Ok(vec![Command::Stop, Command::NewGame, Command::SetPosition(Board::default(), Chess960Castling::default(), vec![])]);
+    assert_eq!(interpreter.line_to_commands("new"), expected);
+    assert_eq!(interpreter.line_to_commands("protover 2"), Ok(vec![Command::Identify]));
+    assert_eq!(interpreter.line_to_commands("accepted usermove"), Ok(vec![]));
+}
+
+#[test]
+fn check_user_move() {
+    let (engine_moves_tx, engine_moves_rx) = std::sync::mpsc::channel();
+    let mut interpreter = XBoardInterpreter::new(engine_moves_rx);
+    interpreter.line_to_commands("new").unwrap();
+    interpreter.line_to_commands("level 0 1 2").unwrap();
+
+    let user_move = ChessMove::new(Square::E2, Square::E4, None);
+    let board = Board::default().make_move_new(user_move);
+    let commands = interpreter.line_to_commands("usermove e2e4").unwrap();
+    assert_eq!(commands[0], Command::SetPosition(board, Chess960Castling::default(), vec![Board::default().get_hash()]));
+    assert!(matches!(commands[1], Command::Calculate(_)));
+
+    // The engine's reply is known once the GUI sends the next move
+    let engine_move = ChessMove::new(Square::E7, Square::E5, None);
+    engine_moves_tx.send((interpreter.position_id, engine_move)).unwrap();
+    let commands = interpreter.line_to_commands("g1f3").unwrap();
+    let expected_board = board
+        .make_move_new(engine_move)
+        .make_move_new(ChessMove::new(Square::G1, Square::F3, None));
+    assert!(matches!(&commands[0], Command::SetPosition(x, _, visited) if *x == expected_board && visited.len() == 3));
+
+    assert_eq!(interpreter.line_to_commands("usermove e2e5"), Err(CommandParseError::IllegalMove("e2e5".to_string())));
+}
+
+#[test]
+fn check_reply_to_abandoned_game() {
+    let (engine_moves_tx, engine_moves_rx) = std::sync::mpsc::channel();
+    let mut interpreter = XBoardInterpreter::new(engine_moves_rx);
+    interpreter.line_to_commands("new").unwrap();
+    interpreter.line_to_commands("usermove d2d4").unwrap();
+    let position_id = interpreter.position_id;
+
+    // The GUI starts over before the engine replies: the search is stopped, and its move is dropped
+    let commands = interpreter.line_to_commands("new").unwrap();
+    assert_eq!(commands[0], Command::Stop);
+    let engine_move = ChessMove::new(Square::E7, Square::E5, None);
+    engine_moves_tx.send((position_id, engine_move)).unwrap();
+
+    let user_move = ChessMove::new(Square::E2, Square::E4, None);
+    let commands = interpreter.line_to_commands("usermove e2e4").unwrap();
+    assert_eq!(commands[0], Command::SetPosition(Board::default().make_move_new(user_move), Chess960Castling::default(), vec![Board::default().get_hash()]));
+
+    assert_eq!(interpreter.line_to_commands("result 1-0 {White mates}"), Ok(vec![Command::Stop, Command::SetPosition(interpreter.board, Chess960Castling::default(), interpreter.visited_boards.clone())]));
+}
+
+#[test]
+fn check_force_mode() {
+    let (_, engine_moves_rx) = std::sync::mpsc::channel();
+    let mut interpreter = XBoardInterpreter::new(engine_moves_rx);
+    interpreter.line_to_commands("new").unwrap();
+    interpreter.line_to_commands("force").unwrap();
+
+    let commands = interpreter.line_to_commands("usermove e2e4").unwrap();
+    assert_eq!(commands.len(), 1);
+    assert!(matches!(commands[0], Command::SetPosition(..)));
+
+    let commands = interpreter.line_to_commands("force").unwrap();
+    assert!(matches!(commands[..], [Command::Stop, Command::SetPosition(..)]));
+
+    let commands = interpreter.line_to_commands("go").unwrap();
+    assert!(matches!(commands[..], [Command::Calculate(_)]));
+}
+
+#[test]
+fn check_time_controls() {
+    let (_, engine_moves_rx) = std::sync::mpsc::channel();
+    let mut interpreter = XBoardInterpreter::new(engine_moves_rx);
+    interpreter.line_to_commands("new").unwrap();
+    interpreter.line_to_commands("force").unwrap();
+    interpreter.line_to_commands("usermove e2e4").unwrap();
+
+    // The engine plays black here
+    interpreter.line_to_commands("level 40 2:30 0.5").unwrap();
+    interpreter.line_to_commands("time 6000").unwrap();
+    interpreter.line_to_commands("otim 5000").unwrap();
+    interpreter.line_to_commands("sd 7").unwrap();
+    let expected = CalculateOptions {
+        white_time: Some(50_000),
+        black_time: Some(60_000),
+        white_increment: Some(500),
+        black_increment: Some(500),
+        moves_to_go: Some(40),
+        depth: Some(7),
+        ..Default::default()
+    };
+    assert_eq!(interpreter.line_to_commands("go"), Ok(vec![Command::Calculate(expected)]));
+
+    interpreter.line_to_commands("st 3").unwrap();
+    let expected = CalculateOptions {
+        move_time: Some(3000),
+        depth: Some(7),
+        ..Default::default()
+    };
+    assert_eq!(interpreter.line_to_commands("go"), Ok(vec![Command::Calculate(expected)]));
+}
+
+#[test]
+fn check_xboard_parse_errors() {
+    let (_, engine_moves_rx) = std::sync::mpsc::channel();
+    let mut interpreter = XBoardInterpreter::new(engine_moves_rx);
+
+    assert_eq!(interpreter.line_to_commands(""), Err(CommandParseError::Empty));
+    assert_eq!(interpreter.line_to_commands("bogus"), Err(CommandParseError::UnknownCommand("bogus".to_string())));
+    assert_eq!(interpreter.line_to_commands("sd x"), Err(CommandParseError::InvalidNumber("x".to_string())));
+    assert_eq!(interpreter.line_to_commands("level 40"), Err(CommandParseError::MissingValue("level".to_string())));
+    assert_eq!(interpreter.line_to_commands("ping 3"), Ok(vec![Command::Ping(3)]));
+}
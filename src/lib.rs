@@ -2,3 +2,7 @@ pub mod core;
 pub mod input;
 pub mod tests;
 pub mod analysis;
+pub mod logging;
+pub mod output;
+pub mod engine;
+pub mod diagnostics;
@@ -0,0 +1,63 @@
+// Diagnostics that must stay off the protocol's stdout, unless the GUI asked for them.
+
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::Write;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::output;
+
+static DEBUG: AtomicBool = AtomicBool::new(false);
+static LOG_FILE: Mutex<Option<File>> = Mutex::new(None);
+static HAS_LOG_FILE: AtomicBool = AtomicBool::new(false); // Lets `is_enabled` skip the lock
+
+/// Logs a formatted message, without formatting it if nobody is listening.
+#[macro_export]
+macro_rules! debug_log {
+    ($($arg:tt)*) => {
+        if $crate::logging::is_enabled() {
+            $crate::logging::log(&format!($($arg)*));
+        }
+    };
+}
+
+/// Set by `debug on` and `debug off`: when on, the log is also sent to the GUI.
+pub fn set_debug(debug: bool) {
+    DEBUG.store(debug, Ordering::Relaxed);
+}
+
+pub fn is_debug() -> bool {
+    DEBUG.load(Ordering::Relaxed)
+}
+
+/// Appends the log to the file at `path`, or stops logging to a file if `path` is empty.
+pub fn set_log_file(path: &str) -> io::Result<()> {
+    let file = match path.trim() {
+        "" => None,
+        path => Some(OpenOptions::new().create(true).append(true).open(path)?),
+    };
+
+    HAS_LOG_FILE.store(file.is_some(), Ordering::Relaxed);
+    *LOG_FILE.lock().expect("log file poisoned") = file;
+    Ok(())
+}
+
+/// Whether a logged message ends up anywhere
+pub fn is_enabled() -> bool {
+    is_debug() || HAS_LOG_FILE.load(Ordering::Relaxed)
+}
+
+/// Prefer `debug_log!`, which doesn't format the message when logging is disabled.
+pub fn log(message: &str) {
+    if is_debug() {
+        // Every line has to be its own message, or the GUI takes the rest for commands
+        for line in message.lines() {
+            output::message(line);
+        }
+    }
+
+    if let Some(file) = LOG_FILE.lock().expect("log file poisoned").as_mut() {
+        // A failing log file shouldn't take down the engine
+        let _ = writeln!(file, "{message}");
+    }
+}
@@ -1,51 +1,139 @@
 use std::io;
 use std::io::{BufRead};
 use std::thread;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::mpsc::{channel, Receiver, Sender};
 use anyhow;
-use sn0l::core::search::SearchCommand;
-use sn0l::input::protocol_interpreter::Command;
+use chess::ChessMove;
+use sn0l::core::search::bench::BENCH_DEPTH;
+use sn0l::engine::{Engine, SearchOutcome};
+use sn0l::input::engine_options::{EngineOptionName, EngineOptions};
+use sn0l::input::protocol_interpreter::{Command, DebugState, SupportedProtocols};
 use sn0l::input::stdin::listen_to_stdin;
+use sn0l::input::uci_interpreter::UciInterpreter;
+use sn0l::input::xboard_interpreter::{feature_line, XBoardInterpreter};
+use sn0l::{diagnostics, logging, output};
 
+const ENGINE_NAME: &str = "sn0l 0.1";
 
 fn main() -> anyhow::Result<()> {
-    start_uci_protocol()
+    match determine_protocol() {
+        Some(protocol) => start_protocol(protocol),
+        None => Ok(()),
+    }
 }
 
-fn start_uci_protocol() -> anyhow::Result<()> {
-    // Make sure host (GUI) uses UCI protocol
+/// The GUI announces its protocol with its first command
+fn determine_protocol() -> Option<SupportedProtocols> {
     loop {
         let mut buffer = String::new();
-        io::stdin().lock().read_line(&mut buffer).expect("Failed stdin read");
+        if io::stdin().lock().read_line(&mut buffer).expect("Failed stdin read") == 0 {
+            return None;  // The GUI is gone
+        }
+
+        if buffer.trim() == "quit" {
+            return None;
+        }
 
-        println!("{buffer}");
-        if buffer == "uci\n" || buffer == "uci" {
-            break;
-        } else if buffer == "quit\n" || buffer == "quit" {
-            return Ok(());
+        if let Some(protocol) = SupportedProtocols::from_first_line(&buffer) {
+            return Some(protocol);
         }
 
-        // run again: GUI may have tried some other protocol, e.g. `xboard`
+        // run again: anything before the protocol is picked gets ignored
     }
+}
+
+fn start_protocol(protocol: SupportedProtocols) -> anyhow::Result<()> {
+    output::set_protocol(protocol);
 
     // stdin Channel
     let (input_tx, input_rx) = channel::<Command>();
-    // Search Engine Channel
-    let (search_tx, search_rx) = channel::<SearchCommand>();
+    // The moves the engine plays, with the position they answer, which the xboard interpreter needs to keep track of the game
+    let (played_tx, played_rx) = channel::<(u64, ChessMove)>();
+
+    pre_option_init(protocol, input_tx, played_rx);
+    if protocol == SupportedProtocols::UCI {
+        println!("uciok"); // confirm pre-init
+    }
 
-    pre_option_init(input_tx, search_rx);
-    println!("uciok"); // confirm pre-init
+    // Searches on its own thread, so a running search can be interrupted from here
+    let mut engine = Engine::new();
+    let position_id = Arc::new(AtomicU64::new(0)); // How many positions were set: the engine's moves are tagged with the one they answer
+    let mut position_rejected = false; // Until a valid position follows, searching would answer for the wrong one
 
     loop {
         let command = input_rx.recv().unwrap();
 
-        if let Some(search_command) = SearchCommand::from_command(command.clone()) {
-            search_tx.send(search_command).unwrap();
-        }
-
         match command {
+            // UCI identifies at the start, xboard only once it knows the GUI speaks version 2
+            Command::Identify if protocol == SupportedProtocols::XBoard => println!("{}", feature_line(ENGINE_NAME)),
             Command::IsReady => println!("readyok"),  // Main thread unblocked, so must be ready
-            Command::Quit => break,  // TODO: should interrupt search, instead of letting it finish
+            Command::Ping(x) => println!("pong {x}"),
+            Command::NewGame => engine.new_game(),
+            Command::SetPosition(board, castling, visited_boards) => {
+                position_id.fetch_add(1, Ordering::Relaxed);
+                position_rejected = false;
+                engine.set_chess960_position(board, castling, visited_boards);
+            },
+            Command::RejectPosition => position_rejected = true,
+            Command::Calculate(_) if position_rejected => {
+                output::message("not searching: the last position sent was invalid");
+                output::no_move();
+            },
+            Command::SetOption { name, value } => set_option(&mut engine, &name, value.as_deref()),
+            Command::Calculate(options) => {
+                let search = engine.go(options);
+                let played_tx = played_tx.clone();
+                let searched_id = position_id.load(Ordering::Relaxed);
+                let position_id = position_id.clone();
+
+                thread::spawn(move || {
+                    let outcome = search.wait();
+                    report_best_move(protocol, &outcome, searched_id, &position_id, &played_tx);
+                });
+            },
+            Command::Perft(depth) => {
+                let divide = engine.perft(depth);
+
+                thread::spawn(move || {
+                    let divide = divide.recv().expect("search thread stopped");
+                    for (chess_move, nodes) in divide.iter() {
+                        println!("{chess_move}: {nodes}");
+                    }
+                    println!();
+                    println!("Nodes searched: {}", divide.iter().map(|(_, nodes)| nodes).sum::<u64>());
+                });
+            },
+            Command::ShowPosition | Command::Evaluate => {
+                let position = engine.position();
+                let show_position = command == Command::ShowPosition;
+
+                thread::spawn(move || {
+                    let (board, visited_boards) = position.recv().expect("search thread stopped");
+                    let lines = match show_position {
+                        true => diagnostics::position_lines(&board, &visited_boards),
+                        false => diagnostics::evaluation_lines(&board),
+                    };
+                    for line in lines {
+                        println!("{line}");
+                    }
+                });
+            },
+            Command::Bench(depth) => {
+                let result = engine.bench(depth.unwrap_or(BENCH_DEPTH));
+
+                thread::spawn(move || {
+                    for line in diagnostics::bench_lines(&result.recv().expect("search thread stopped")) {
+                        println!("{line}");
+                    }
+                });
+            },
+            Command::PonderHit => engine.ponder_hit(),
+            Command::ToggleDebug(debug_state) => logging::set_debug(debug_state == DebugState::On),
+            Command::ShowThinking(show_thinking) => output::set_show_thinking(show_thinking),
+            Command::Stop => engine.stop(),
+            Command::Quit => break,  // Dropping the engine interrupts the search
             _ => (),  // currently unsupported command: Ignore, may have supported new protocols
         }
     }
@@ -53,20 +141,74 @@ fn start_uci_protocol() -> anyhow::Result<()> {
     return Ok(());
 }
 
-fn pre_option_init(input_tx: Sender<Command>, search_rx: Receiver<SearchCommand>) {
-    // The thread that listens to stdin
-    thread::spawn(move || {
-        listen_to_stdin(input_tx);
-    });
+/// Plays the move of a finished search, unless xboard replaced its position while searching: it would be taken as a move in the new one.
+/// UCI expects a `bestmove` for every `go`, even after a new `position`. Returns whether the move was played.
+fn report_best_move(
+    protocol: SupportedProtocols,
+    outcome: &SearchOutcome,
+    searched_id: u64,
+    position_id: &AtomicU64,
+    played_tx: &Sender<(u64, ChessMove)>,
+) -> bool {
+    if protocol == SupportedProtocols::XBoard && position_id.load(Ordering::Relaxed) != searched_id {
+        return false;
+    }
 
-    // The thread that runs the search engine
+    // Sent before the move is printed, so it's there when the reply comes in. Nobody listens under UCI.
+    let _ = played_tx.send((searched_id, outcome.best_move));
+    output::best_move(&outcome.board, outcome.best_move, outcome.ponder_move);
+    true
+}
+
+fn set_option(engine: &mut Engine, name: &str, value: Option<&str>) {
+    match engine.set_option(name, value) {
+        Ok(EngineOptionName::DebugLogFile) => {
+            let path = &engine.options().debug_log_file;
+            if let Err(error) = logging::set_log_file(path) {
+                output::message(&format!("cannot open log file {path}: {error}"));
+            }
+        },
+        Ok(EngineOptionName::Chess960) => output::set_chess960(engine.options().chess960),
+        Ok(_) => (),
+        Err(error) => output::message(&error.to_string()),
+    }
+}
+
+fn pre_option_init(protocol: SupportedProtocols, input_tx: Sender<Command>, played_rx: Receiver<(u64, ChessMove)>) {
+    // The thread that listens to stdin
     thread::spawn(move || {
-        sn0l::core::search::start_search_engine(search_rx);
+        match protocol {
+            SupportedProtocols::UCI => listen_to_stdin(input_tx, UciInterpreter),
+            SupportedProtocols::XBoard => listen_to_stdin(input_tx, XBoardInterpreter::new(played_rx)),
+        }
     });
 
-    println!("id name sn0l 0.1");
-    println!("id author Niels Groeneveld");
+    if protocol == SupportedProtocols::UCI {
+        println!("id name {ENGINE_NAME}");
+        println!("id author Niels Groeneveld");
 
-    // list options here, once there are some
+        for option_line in EngineOptions::uci_option_lines() {
+            println!("{option_line}");
+        }
+    }
 }
 
+#[test]
+fn check_abandoned_search() {
+    use chess::Board;
+    use sn0l::input::protocol_interpreter::CalculateOptions;
+
+    let engine = sn0l::tests::small_engine().build();
+    engine.set_position(Board::default(), vec![]);
+    let outcome = engine.go(CalculateOptions::depth(1)).wait();
+    let (played_tx, played_rx) = channel();
+
+    // `new` sent a position while searching
+    let position_id = AtomicU64::new(2);
+    assert!(!report_best_move(SupportedProtocols::XBoard, &outcome, 1, &position_id, &played_tx));
+    assert!(played_rx.try_recv().is_err());
+
+    assert!(report_best_move(SupportedProtocols::UCI, &outcome, 1, &position_id, &played_tx));
+    assert!(report_best_move(SupportedProtocols::XBoard, &outcome, 2, &position_id, &played_tx));
+    assert_eq!(played_rx.try_iter().collect::<Vec<_>>(), vec![(1, outcome.best_move), (2, outcome.best_move)]);
+}
@@ -0,0 +1,110 @@
+// The lines sent to the GUI that differ between the supported protocols.
+
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use chess::{Board, ChessMove};
+use itertools::Itertools;
+use crate::core::castling::{make_move, to_king_takes_rook};
+use crate::input::protocol_interpreter::{CommandParseError, SupportedProtocols};
+
+static PROTOCOL: OnceLock<SupportedProtocols> = OnceLock::new();
+static SHOW_THINKING: AtomicBool = AtomicBool::new(true);
+static CHESS960: AtomicBool = AtomicBool::new(false);
+
+/// Only the first call counts: the protocol is fixed once the GUI picked it.
+pub fn set_protocol(protocol: SupportedProtocols) {
+    let _ = PROTOCOL.set(protocol);
+}
+
+pub fn protocol() -> SupportedProtocols {
+    PROTOCOL.get().copied().unwrap_or(SupportedProtocols::UCI)
+}
+
+/// Set by `post` and `nopost` in xboard; UCI always gets the search info.
+pub fn set_show_thinking(show_thinking: bool) {
+    SHOW_THINKING.store(show_thinking, Ordering::Relaxed);
+}
+
+pub fn is_showing_thinking() -> bool {
+    protocol() == SupportedProtocols::UCI || SHOW_THINKING.load(Ordering::Relaxed)
+}
+
+/// Set by `UCI_Chess960`: castles are then written as the king taking its rook
+pub fn set_chess960(chess960: bool) {
+    CHESS960.store(chess960, Ordering::Relaxed);
+}
+
+pub fn is_chess960() -> bool {
+    CHESS960.load(Ordering::Relaxed)
+}
+
+/// `chess_move` as the GUI expects it, where `board` is the position it is played in
+pub fn move_string(board: &Board, chess_move: ChessMove) -> String {
+    match is_chess960() {
+        true => to_king_takes_rook(board, chess_move).to_string(),
+        false => chess_move.to_string(),
+    }
+}
+
+/// The moves of a line in play order, starting from `board`
+pub fn line_string(board: &Board, line: &[ChessMove]) -> String {
+    let mut board = *board;
+    line.iter()
+        .map(|chess_move| {
+            let result = move_string(&board, *chess_move);
+            board = make_move(&board, *chess_move);
+            result
+        })
+        .join(" ")
+}
+
+/// A line of free text, which the GUI shows without acting on it
+pub fn message(line: &str) {
+    match protocol() {
+        SupportedProtocols::UCI => println!("info string {line}"),
+        SupportedProtocols::XBoard => println!("# {line}"),
+    }
+}
+
+/// Reports a line that couldn't be parsed, while the engine keeps running
+pub fn parse_error(line: &str, error: &CommandParseError) {
+    match (protocol(), error) {
+        (SupportedProtocols::UCI, _) => message(&error.to_string()),
+        (SupportedProtocols::XBoard, CommandParseError::IllegalMove(chess_move)) => println!("Illegal move: {chess_move}"),
+        (SupportedProtocols::XBoard, _) => println!("Error ({error}): {}", line.trim()),
+    }
+}
+
+/// Answers a search that couldn't be started: UCI still expects a `bestmove`
+pub fn no_move() {
+    match protocol() {
+        SupportedProtocols::UCI => println!("bestmove 0000"),
+        SupportedProtocols::XBoard => (),
+    }
+}
+
+/// The move the engine plays in `board`, with the reply it expects if known
+pub fn best_move(board: &Board, best_move: ChessMove, ponder_move: Option<ChessMove>) {
+    let best_move_string = move_string(board, best_move);
+    match (protocol(), ponder_move) {
+        (SupportedProtocols::UCI, Some(ponder_move)) => println!(
+            "bestmove {best_move_string} ponder {}",
+            move_string(&make_move(board, best_move), ponder_move),
+        ),
+        (SupportedProtocols::UCI, None) => println!("bestmove {best_move_string}"),
+        (SupportedProtocols::XBoard, _) => println!("move {best_move_string}"),
+    }
+}
+
+#[test]
+fn check_line_string() {
+    use std::str::FromStr;
+
+    let board = Board::from_str("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+    let line = [ChessMove::from_str("e1g1").unwrap(), ChessMove::from_str("e8c8").unwrap()];
+
+    assert_eq!(line_string(&board, &line), "e1g1 e8c8");
+    set_chess960(true);
+    assert_eq!(line_string(&board, &line), "e1h1 e8a8");
+    set_chess960(false);
+}
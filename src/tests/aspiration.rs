@@ -0,0 +1,99 @@
+use std::str::FromStr;
+use chess::Board;
+use crate::analysis::match_orchestration::SearchAlgorithm;
+use crate::core::score::Centipawns;
+use crate::core::search::aspiration::AspirationWindows;
+use crate::core::search::transpositions::EvalBound;
+use crate::engine::Engine;
+use crate::input::protocol_interpreter::CalculateOptions;
+use crate::tests::{engine_without_table, search, POSITIONS};
+
+/// Tests for the aspiration windows of the alpha-beta searches
+
+const DEPTH: u32 = 4;
+const ALGORITHMS: [SearchAlgorithm; 2] = [SearchAlgorithm::AlphaBetaIterativeDeepening, SearchAlgorithm::PVSIterativeDeepening];
+// Too narrow for the score to stay inside between iterations
+const NARROW: AspirationWindows = AspirationWindows {
+    width: Centipawns::new(1),
+    widening: 2,
+};
+
+#[test]
+fn check_aspiration_keeps_evaluation() {
+    for fen in POSITIONS {
+        let board = Board::from_str(fen).unwrap();
+
+        for algorithm in ALGORITHMS {
+            // Without a table, a window can't change the score
+            let engine = || engine_without_table().algorithm(algorithm);
+            let full = search(&board, engine().aspiration(None), DEPTH);
+            let narrow = search(&board, engine().aspiration(Some(NARROW)), DEPTH);
+            let aspiration = search(&board, engine().aspiration(Some(AspirationWindows::default())), DEPTH);
+
+            assert_eq!(narrow.search_result.board_evaluation.board_evaluation(), full.search_result.board_evaluation.board_evaluation(), "{algorithm:?} {fen}");
+            assert_eq!(aspiration.search_result.board_evaluation.board_evaluation(), full.search_result.board_evaluation.board_evaluation(), "{algorithm:?} {fen}");
+            assert!(board.legal(narrow.best_move), "{algorithm:?} {fen}");
+        }
+    }
+}
+
+#[test]
+fn check_aspiration_logs_re_searches() {
+    let board = Board::from_str(POSITIONS[0]).unwrap();
+
+    for algorithm in ALGORITHMS {
+        let engine = || engine_without_table().algorithm(algorithm).record_search_rows();
+        let full = search(&board, engine().aspiration(None), DEPTH);
+        let narrow = search(&board, engine().aspiration(Some(NARROW)), DEPTH);
+
+        // The first iteration has no score to aspire to
+        assert!(full.search_rows.iter().all(|(_, mt_rows)| mt_rows.is_empty()), "{algorithm:?}");
+        assert!(narrow.search_rows[0].1.is_empty(), "{algorithm:?}");
+
+        for (position_row, mt_rows) in &narrow.search_rows[1..] {
+            let (last, failed) = mt_rows.split_last().expect("every iteration is logged");
+            assert!(last.eval_bound.is_exact(), "{algorithm:?}: {:?}", last.eval_bound);
+            assert_eq!(last.eval_bound.board_evaluation(), position_row.evaluation, "{algorithm:?}");
+            assert_eq!(mt_rows.iter().map(|x| x.nodes_evaluated).sum::<u32>(), position_row.nodes_evaluated, "{algorithm:?}");
+
+            for (search_num, mt_row) in mt_rows.iter().enumerate() {
+                assert_eq!(mt_row.search_num, search_num as u32);
+                let (alpha, beta) = mt_row.window.expect("aspiration searches have a window");
+                assert!(alpha <= mt_row.test_value && mt_row.test_value <= beta, "{algorithm:?}: {} outside {:?}", mt_row.test_value, mt_row.window);
+            }
+            // Only the side the score fell outside of widens
+            for (failed, next) in failed.iter().zip(&mt_rows[1..]) {
+                let (alpha, beta) = failed.window.unwrap();
+                let (next_alpha, next_beta) = next.window.unwrap();
+                match failed.eval_bound {
+                    EvalBound::UpperBound(x) => assert!(x < alpha && next_alpha < alpha && next_beta == beta, "{algorithm:?}: {:?} in {:?}", failed.eval_bound, failed.window),
+                    EvalBound::LowerBound(x) => assert!(x > beta && next_beta > beta && next_alpha == alpha, "{algorithm:?}: {:?} in {:?}", failed.eval_bound, failed.window),
+                    EvalBound::Exact(_) => panic!("{algorithm:?}: searched again after an exact score"),
+                }
+            }
+        }
+        assert!(
+            narrow.search_rows.iter().any(|(_, mt_rows)| mt_rows.len() > 1),
+            "{algorithm:?}: a 1 centipawn window never failed",
+        );
+    }
+}
+
+#[test]
+fn check_aspiration_with_transposition_table() {
+    for fen in POSITIONS {
+        let board = Board::from_str(fen).unwrap();
+
+        for algorithm in ALGORITHMS {
+            let engine = Engine::builder()
+                .algorithm(algorithm)
+                .mtd_h_params(vec![])
+                .aspiration(Some(NARROW))
+                .build();
+            engine.set_position(board, vec![]);
+
+            let outcome = engine.go(CalculateOptions::depth(DEPTH + 1)).wait();
+            assert!(board.legal(outcome.best_move), "{algorithm:?} {fen}");
+        }
+    }
+}
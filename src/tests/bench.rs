@@ -0,0 +1,29 @@
+use crate::analysis::match_orchestration::SearchAlgorithm;
+use crate::core::search::bench::bench;
+use crate::core::search::SearchDepth;
+use crate::core::search::transpositions::high_depth_transposition::HighDepthTranspositionTable;
+use crate::input::engine_options::EngineOptions;
+
+/// The bench only works as a signature of the search if its node count is the same every run
+
+const DEPTH: u32 = 3;
+
+#[test]
+fn check_bench_is_deterministic() {
+    for algorithm in [
+        SearchAlgorithm::MTDBiIterativeDeepeningConspiracy,
+        SearchAlgorithm::MTDFIterativeDeepening,
+        SearchAlgorithm::AlphaBetaIterativeDeepening,
+    ] {
+        let engine_options = EngineOptions {
+            algorithm,
+            threads: 4, // Left out of the bench
+            ..Default::default()
+        };
+        let run = || bench(DEPTH, &engine_options, &[], || Box::new(HighDepthTranspositionTable::new(SearchDepth::Depth(2))));
+
+        let first_result = run();
+        assert!(first_result.nodes > 0);
+        assert_eq!(first_result.nodes, run().nodes, "{algorithm:?}");
+    }
+}
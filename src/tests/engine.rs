@@ -0,0 +1,117 @@
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use chess::{Board, BoardStatus, ChessMove, Color, Piece, Square};
+use crate::analysis::match_orchestration::SearchAlgorithm;
+use crate::core::evaluation::single_evaluation;
+use crate::core::is_default_move;
+use crate::core::score::{BoardEvaluation, Centipawns};
+use crate::core::search::search_result::SearchResult;
+use crate::core::search::info::IterationInfo;
+use crate::core::search::searcher::ALGORITHMS;
+use crate::input::engine_options::{EngineOptionError, EngineOptionName};
+use crate::input::fen::parse_fen;
+use crate::input::protocol_interpreter::CalculateOptions;
+use crate::tests::{search, small_engine};
+
+/// Tests driving the search through the `Engine` facade
+
+const DEPTH: u32 = 3;
+// Black to move, after 1. e4 e5 2. Nf3 Nc6 3. d4
+const FEN: &str = "r1bqkbnr/pppp1ppp/2n5/4p3/3PP3/5N2/PPP2PPP/RNBQKB1R b KQkq - 0 3";
+
+#[test]
+fn check_chess960_castle_with_every_algorithm() {
+    // Only castling long mates: the king stays next to c1, and the rook lands on d1
+    let (board, castling) = parse_fen("2rkr3/2p1p3/8/8/8/8/8/RK6 w A - 0 1").unwrap();
+
+    for entry in ALGORITHMS.iter() {
+        let algorithm = entry.algorithm;
+        let engine = small_engine().algorithm(algorithm).build();
+        engine.set_chess960_position(board, castling, vec![]);
+
+        let outcome = engine.go(CalculateOptions::depth(DEPTH)).wait();
+        assert_eq!(outcome.best_move, ChessMove::new(Square::B1, Square::A1, None), "{algorithm:?}");
+    }
+}
+
+#[test]
+fn check_evaluator() {
+    // Only a white pawn on e4 counts, so it's pushed there right away
+    fn pawn_on_e4(board: &Board, board_status: BoardStatus) -> BoardEvaluation {
+        if board_status != BoardStatus::Ongoing {
+            return single_evaluation(board, board_status);
+        }
+        let pawn_on_e4 = board.piece_on(Square::E4) == Some(Piece::Pawn) && board.color_on(Square::E4) == Some(Color::White);
+        BoardEvaluation::PieceScore(Centipawns::new(if pawn_on_e4 { 100 } else { 0 }))
+    }
+
+    for entry in ALGORITHMS.iter() {
+        let algorithm = entry.algorithm;
+        let outcome = search(&Board::default(), small_engine().algorithm(algorithm).evaluator(pawn_on_e4), 2);
+        assert_eq!(outcome.best_move, ChessMove::new(Square::E2, Square::E4, None), "{algorithm:?}");
+        assert_eq!(outcome.search_result.eval_bound().board_evaluation(), BoardEvaluation::PieceScore(Centipawns::new(100)), "{algorithm:?}");
+    }
+}
+
+#[test]
+fn check_info_callback() {
+    let board = Board::from_str(FEN).unwrap();
+    let infos: Arc<Mutex<Vec<IterationInfo>>> = Arc::new(Mutex::new(vec![]));
+
+    let callback_infos = infos.clone();
+    let engine = small_engine()
+        .algorithm(SearchAlgorithm::AlphaBetaIterativeDeepening)
+        .multi_pv(2)
+        .on_info(move |info| callback_infos.lock().unwrap().push(info.clone()))
+        .build();
+    engine.set_position(board, vec![]);
+    let outcome = engine.go(CalculateOptions::depth(DEPTH)).wait();
+
+    let infos = infos.lock().unwrap();
+    let depths: Vec<u32> = infos.iter().map(|x| x.depth).collect();
+    assert_eq!(depths, (2..=DEPTH).collect::<Vec<_>>());
+
+    let last_info = infos.last().unwrap();
+    assert_eq!(last_info.lines.len(), 2);
+    assert_eq!(last_info.lines[0].principal_variation.first(), Some(&outcome.best_move));
+    assert_ne!(last_info.lines[1].principal_variation.first(), Some(&outcome.best_move));
+}
+
+#[test]
+fn check_stop() {
+    let board = Board::from_str(FEN).unwrap();
+    let engine = small_engine().build();
+    engine.set_position(board, vec![]);
+
+    let search = engine.go(CalculateOptions::infinite());
+    engine.stop();
+    assert!(board.legal(search.wait().best_move));
+
+    // The next search isn't stopped as well
+    let outcome = engine.go(CalculateOptions::depth(DEPTH)).wait();
+    assert!(!is_default_move(&outcome.search_result.best_move));
+}
+
+#[test]
+fn check_set_option() {
+    let mut engine = small_engine().build();
+
+    assert_eq!(engine.set_option("multipv", Some("3")), Ok(EngineOptionName::MultiPV));
+    assert_eq!(engine.options().multi_pv, 3);
+    assert!(matches!(engine.set_option("Hash", Some("0")), Err(EngineOptionError::InvalidValue { .. })));
+    assert_eq!(engine.options().hash_size, 128);
+}
+
+#[test]
+fn check_record_search_rows() {
+    let engine = small_engine()
+        .algorithm(SearchAlgorithm::MTDBiIterativeDeepening)
+        .record_search_rows()
+        .build();
+    engine.set_position(Board::from_str(FEN).unwrap(), vec![]);
+
+    let outcome = engine.go(CalculateOptions::depth(DEPTH)).wait();
+    // One for every iteration
+    assert_eq!(outcome.search_rows.len(), DEPTH as usize);
+    assert!(outcome.search_rows.iter().all(|(_, mt_rows)| !mt_rows.is_empty()));
+}
@@ -0,0 +1,42 @@
+use std::str::FromStr;
+use chess::Board;
+use crate::analysis::match_orchestration::SearchAlgorithm;
+use crate::core::search::extensions::{ExtensionOptions, ONE_PLY};
+use crate::tests::{engine_without_table, search, MIDDLE_GAME};
+
+/// Tests for the search extensions, and the selective depth they make the search reach
+
+const DEPTH: u32 = 3;
+const CHECKS: ExtensionOptions = ExtensionOptions {
+    check: ONE_PLY,
+    recapture: ONE_PLY / 2,
+};
+
+#[test]
+fn check_selective_depth_without_extensions() {
+    // Bxh7+ and the knight checks give white plenty of lines to extend
+    let board = Board::from_str(MIDDLE_GAME).unwrap();
+
+    for algorithm in [SearchAlgorithm::AlphaBetaIterativeDeepening, SearchAlgorithm::MTDFIterativeDeepening] {
+        let outcome = search(&board, engine_without_table().algorithm(algorithm).extensions(ExtensionOptions::default()), DEPTH);
+        assert_eq!(outcome.search_result.selective_depth, DEPTH, "{algorithm:?}");
+    }
+}
+
+#[test]
+fn check_extensions_reach_deeper() {
+    let board = Board::from_str(MIDDLE_GAME).unwrap();
+
+    for algorithm in [SearchAlgorithm::AlphaBetaIterativeDeepening, SearchAlgorithm::MTDFIterativeDeepening] {
+        let extended = search(&board, engine_without_table().algorithm(algorithm).extensions(CHECKS), DEPTH);
+
+        assert!(board.legal(extended.best_move), "{algorithm:?}");
+        assert!(
+            extended.search_result.selective_depth > DEPTH,
+            "{algorithm:?}: seldepth {} at depth {DEPTH}",
+            extended.search_result.selective_depth,
+        );
+        // Never more extended plies than nominal ones
+        assert!(extended.search_result.selective_depth <= 2 * DEPTH, "{algorithm:?}");
+    }
+}
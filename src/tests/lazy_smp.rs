@@ -0,0 +1,78 @@
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use chess::Board;
+use crate::analysis::match_orchestration::SearchAlgorithm;
+use crate::core::search::info::IterationInfo;
+use crate::engine::SearchOutcome;
+use crate::input::protocol_interpreter::CalculateOptions;
+use crate::tests::{small_engine, MATE_IN_ONE, MIDDLE_GAME};
+
+/// Tests for searching with several threads
+
+const DEPTH: u32 = 4;
+const THREADS: usize = 3;
+
+#[test]
+fn check_threads_find_mate() {
+    let board = Board::from_str(MATE_IN_ONE).unwrap();
+
+    for algorithm in [SearchAlgorithm::AlphaBetaIterativeDeepening, SearchAlgorithm::MTDFIterativeDeepening] {
+        let engine = small_engine().algorithm(algorithm).threads(THREADS).build();
+        engine.set_position(board, vec![]);
+
+        let outcome = engine.go(CalculateOptions::depth(DEPTH)).wait();
+        assert_eq!(outcome.best_move.to_string(), "f3f7", "{algorithm:?}");
+    }
+}
+
+#[test]
+fn check_only_main_thread_reports() {
+    let board = Board::from_str(MIDDLE_GAME).unwrap();
+    let infos: Arc<Mutex<Vec<IterationInfo>>> = Arc::new(Mutex::new(vec![]));
+
+    let callback_infos = infos.clone();
+    let engine = small_engine()
+        .algorithm(SearchAlgorithm::AlphaBetaIterativeDeepening)
+        .threads(THREADS)
+        .on_info(move |info| callback_infos.lock().unwrap().push(info.clone()))
+        .record_search_rows()
+        .build();
+    engine.set_position(board, vec![]);
+    let outcome = engine.go(CalculateOptions::depth(DEPTH)).wait();
+
+    let depths: Vec<u32> = infos.lock().unwrap().iter().map(|x| x.depth).collect();
+    assert_eq!(depths, (2..=DEPTH).collect::<Vec<_>>());
+    assert_eq!(outcome.search_rows.len(), DEPTH as usize);
+}
+
+#[test]
+fn check_threads_stop() {
+    let board = Board::from_str(MIDDLE_GAME).unwrap();
+    let engine = small_engine().threads(THREADS).build();
+    engine.set_position(board, vec![]);
+
+    let search = engine.go(CalculateOptions::infinite());
+    thread::sleep(Duration::from_millis(50));
+    search.stop();
+    assert!(board.legal(search.wait().best_move));
+}
+
+#[test]
+fn check_single_thread_deterministic() {
+    let board = Board::from_str(MIDDLE_GAME).unwrap();
+
+    for algorithm in [SearchAlgorithm::AlphaBetaIterativeDeepening, SearchAlgorithm::MTDBiIterativeDeepeningConspiracy] {
+        let search = || -> SearchOutcome {
+            let engine = small_engine().algorithm(algorithm).build();
+            engine.set_position(board, vec![]);
+            engine.go(CalculateOptions::depth(DEPTH)).wait()
+        };
+
+        let (first, second) = (search(), search());
+        assert_eq!(first.best_move, second.best_move, "{algorithm:?}");
+        assert_eq!(first.search_result.board_evaluation, second.search_result.board_evaluation, "{algorithm:?}");
+        assert_eq!(first.search_result.nodes_searched, second.search_result.nodes_searched, "{algorithm:?}");
+    }
+}
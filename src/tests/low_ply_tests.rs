@@ -4,9 +4,11 @@ use crate::core::search::iterative_deepening::iterative_deepening_search;
 use crate::core::search::mtdbi::mtdbi_iterative_deepening_search;
 use crate::core::search::mtdf::mtdf_iterative_deepening_search;
 use crate::core::search::search_result::debug_search_result::DebugSearchResult;
+use crate::core::search::search_control::SearchControl;
 use crate::core::search::SearchDepth;
 use crate::core::search::transpositions::high_depth_transposition::HighDepthTranspositionTable;
 use crate::core::search::transpositions::no_transposition::NoTranspositionTable;
+use crate::core::search::transpositions::TranspositionTable;
 use crate::input::protocol_interpreter::CalculateOptions;
 use crate::tests::{check_position, epd, log_failed_positions, TestError};
 
@@ -23,13 +25,15 @@ fn check_positions() -> Result<()> {
     let mut failed_positions = vec![];
     for record in records.into_iter() {
         let result = check_position(&record, |board| {
-            let mut transposition_table = HighDepthTranspositionTable::new(SearchDepth::Depth(2));
+            let mut transposition_table: Box<dyn TranspositionTable> = Box::new(HighDepthTranspositionTable::new(SearchDepth::Depth(2)));
             // let (result, _, _): (DebugSearchResult, _, _) = iterative_deepening_search(
             let (result, _, _): (DebugSearchResult, _, _) = mtdbi_iterative_deepening_search(
                 board,
                 &mut transposition_table,
                 vec![],
-                CalculateOptions::Depth(6),
+                CalculateOptions::depth(6),
+                |_, _| {},
+                &SearchControl::new(),
             );
 
             println!("{result:?}");
@@ -4,6 +4,7 @@ use crate::core::search::conspiracy_search::merging::merge_remove_overwritten;
 use crate::core::search::conspiracy_search::mtd_w_conspiracy::mtd_iterative_deepening_search;
 use crate::core::search::mtdbi::determine_mtdbi_step;
 use crate::core::search::search_result::debug_search_result::DebugSearchResult;
+use crate::core::search::search_control::SearchControl;
 use crate::core::search::SearchDepth;
 use crate::core::search::transpositions::high_depth_transposition::HighDepthTranspositionTable;
 use crate::core::search::transpositions::no_transposition::NoTranspositionTable;
@@ -31,12 +32,13 @@ fn check_positions() -> Result<()> {
                 board,
                 &mut transposition_table,
                 vec![],
-                CalculateOptions::Depth(6),
+                CalculateOptions::depth(6),
                 determine_mtdbi_step,
                 BUCKET_SIZE,
                 NUM_BUCKETS,
                 merge_remove_overwritten,
                 |_, _| {},
+                &SearchControl::new(),
             );
 
             println!("{result:?}");
@@ -2,6 +2,14 @@ use std::str::FromStr;
 use chess::{Board, ChessMove};
 use thiserror::Error;
 use crate::core::search::search_result::debug_search_result::DebugSearchResult;
+use crate::core::search::searcher::ALGORITHMS;
+use crate::core::search::extensions::{ExtensionOptions, ONE_PLY};
+use crate::core::search::pruning::{LateMoveReductions, NullMovePruning, PruningOptions};
+use crate::core::search::SearchDepth;
+use crate::core::search::transpositions::high_depth_transposition::HighDepthTranspositionTable;
+use crate::core::search::transpositions::no_transposition::NoTranspositionTable;
+use crate::engine::{Engine, EngineBuilder, SearchOutcome};
+use crate::input::protocol_interpreter::CalculateOptions;
 use crate::tests::epd::{EPDParseError, EPDRecord};
 
 pub mod win_at_chess;
@@ -11,8 +19,87 @@ mod eval_bound;
 mod mt_alpha_beta_equivalence;
 mod mtdf_alpha_beta_equivalence;
 mod mtdbi_alpha_beta_equivalence;
+mod pvs_alpha_beta_equivalence;
 mod conspiracy_counter;
 mod low_ply_tests_conspiracy;
+mod search_control;
+mod multi_pv;
+mod engine;
+mod perft;
+mod bench;
+mod pruning;
+mod extensions;
+mod aspiration;
+mod lazy_smp;
+mod parallel_mtd;
+
+/// The positions the engine tests search
+pub const MIDDLE_GAME: &str = "r1bq1rk1/pp2bppp/2n2n2/3p4/3P4/2NB1N2/PP3PPP/R1BQ1RK1 w - - 0 10";
+pub const MATE_IN_ONE: &str = "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5Q2/PPPP1PPP/RNB1K1NR w KQkq - 4 4"; // Qxf7#
+pub const POSITIONS: [&str; 3] = [
+    MIDDLE_GAME,
+    MATE_IN_ONE,
+    "2rq1rk1/pp1bppbp/2np1np1/8/3NP3/1BN1BP2/PPPQ2PP/2KR3R b - - 8 11",
+];
+
+/// An engine without a table: every line is searched to its own depth, and searches can't influence each other
+pub fn engine_without_table() -> EngineBuilder {
+    Engine::builder()
+        .transposition_table(|_| Box::new(NoTranspositionTable))
+        .mtd_h_params(vec![])
+}
+
+/// An engine without the default table of 128 MB
+pub fn small_engine() -> EngineBuilder {
+    Engine::builder()
+        .transposition_table(|_| Box::new(HighDepthTranspositionTable::new(SearchDepth::Depth(2))))
+        .mtd_h_params(vec![])
+}
+
+/// Searches `board` to `depth` with the engine `builder` makes
+pub fn search(board: &Board, builder: EngineBuilder, depth: u32) -> SearchOutcome {
+    let engine = builder.build();
+    engine.set_position(*board, vec![]);
+
+    engine.go(CalculateOptions::depth(depth)).wait()
+}
+
+/// Every algorithm plays a legal move in every configuration, and searches again afterwards.
+/// The tests of the options themselves only use the algorithms they behave differently in.
+#[test]
+fn check_every_algorithm() {
+    const DEPTH: u32 = 4;
+    let board = Board::from_str(MIDDLE_GAME).unwrap();
+    let configurations: [(&str, fn(EngineBuilder) -> EngineBuilder); 4] = [
+        ("default", |x| x),
+        ("pruning", |x| x.pruning(PruningOptions {
+            null_move: Some(NullMovePruning { reduction: 2 }),
+            late_move_reductions: Some(LateMoveReductions::default()),
+        })),
+        ("extensions", |x| x.extensions(ExtensionOptions {
+            check: ONE_PLY,
+            recapture: ONE_PLY / 2,
+        })),
+        // The table is lent to the threads, and has to be handed back for the next search
+        ("threads", |x| x.threads(3)),
+    ];
+
+    for entry in ALGORITHMS.iter() {
+        for (configuration, configure) in configurations {
+            let algorithm = entry.algorithm;
+            let engine = configure(small_engine().algorithm(algorithm)).build();
+            engine.set_position(board, vec![]);
+
+            for _ in 0..2 {
+                let outcome = engine.go(CalculateOptions::depth(DEPTH)).wait();
+                assert!(board.legal(outcome.best_move), "{algorithm:?} ({configuration}) played {}", outcome.best_move);
+                assert_eq!(outcome.best_move, outcome.search_result.best_move, "{algorithm:?} ({configuration})");
+                assert_eq!(outcome.conspiracy_counter.is_some(), entry.is_conspiracy_search, "{algorithm:?} ({configuration})");
+                assert!(outcome.search_rows.is_empty(), "{algorithm:?} ({configuration})");
+            }
+        }
+    }
+}
 
 
 #[derive(Error, Debug, Copy, Clone)]
@@ -5,13 +5,17 @@ use anyhow::{bail, Result};
 use chess::Board;
 use crate::core::evaluation::single_evaluation;
 use crate::core::score::{BoardEvaluation, Centipawns};
+use crate::core::search::extensions::ExtensionState;
 use crate::core::search::iterative_deepening::iterative_deepening_search;
 use crate::core::search::mt::search_mt;
+use crate::core::search::position_history::PositionHistory;
 use crate::core::search::search_result::debug_search_result::DebugSearchResult;
+use crate::core::search::search_control::SearchControl;
 use crate::core::search::SearchDepth;
 use crate::core::search::transpositions::EvalBound;
 use crate::core::search::transpositions::high_depth_transposition::HighDepthTranspositionTable;
 use crate::core::search::transpositions::no_transposition::NoTranspositionTable;
+use crate::core::search::transpositions::TranspositionTable;
 use crate::input::protocol_interpreter::CalculateOptions;
 use crate::tests::{epd, win_at_chess};
 use crate::tests::epd::EPDParseError;
@@ -37,13 +41,15 @@ fn check_mt_exact() -> Result<()> {
         let time = Instant::now();
         let (result, _, _): (DebugSearchResult, u32, u32) = {
             // let mut transposition_table = HighDepthTranspositionTable::new(SearchDepth::Depth(2));
-            let mut transposition_table = NoTranspositionTable::default();
+            let mut transposition_table: Box<dyn TranspositionTable> = Box::new(NoTranspositionTable::default());
 
             iterative_deepening_search(
                 &board,
                 &mut transposition_table,
                 vec![],
-                CalculateOptions::Depth(MAX_DEPTH),
+                CalculateOptions::depth(MAX_DEPTH),
+                |_, _| {},
+                &SearchControl::new(),
             )
         };
         println!("alpha beta time ms: {}", time.elapsed().as_millis());
@@ -53,7 +59,7 @@ fn check_mt_exact() -> Result<()> {
         let time = Instant::now();
         let mt_result: DebugSearchResult = {
             // let mut transposition_table = HighDepthTranspositionTable::new(SearchDepth::Depth(2));
-            let mut transposition_table = NoTranspositionTable::default();
+            let mut transposition_table: Box<dyn TranspositionTable> = Box::new(NoTranspositionTable::default());
             let simple_evaluation = single_evaluation(&board, board.status());
 
             let simple_score;
@@ -65,11 +71,14 @@ fn check_mt_exact() -> Result<()> {
             search_mt(
                 &board,
                 &mut transposition_table,
-                vec![],
+                &mut PositionHistory::new(&[], &board),
                 simple_score,
                 result.board_evaluation,
                 0,
                 MAX_DEPTH,
+                ExtensionState::default(),
+                true,
+                &SearchControl::new(),
             )
         };
         println!("mt time ms: {}", time.elapsed().as_millis());
@@ -109,13 +118,15 @@ fn check_mt_different() -> Result<()> {
         let time = Instant::now();
         let (result, _, _): (DebugSearchResult, u32, u32) = {
             // let mut transposition_table = HighDepthTranspositionTable::new(SearchDepth::Depth(2));
-            let mut transposition_table = NoTranspositionTable::default();
+            let mut transposition_table: Box<dyn TranspositionTable> = Box::new(NoTranspositionTable::default());
 
             iterative_deepening_search(
                 &board,
                 &mut transposition_table,
                 vec![],
-                CalculateOptions::Depth(MAX_DEPTH),
+                CalculateOptions::depth(MAX_DEPTH),
+                |_, _| {},
+                &SearchControl::new(),
             )
         };
 
@@ -126,7 +137,7 @@ fn check_mt_different() -> Result<()> {
         let time = Instant::now();
         let mt_result: DebugSearchResult = {
             // let mut transposition_table = HighDepthTranspositionTable::new(SearchDepth::Depth(2));
-            let mut transposition_table = NoTranspositionTable::default();
+            let mut transposition_table: Box<dyn TranspositionTable> = Box::new(NoTranspositionTable::default());
             let simple_evaluation = single_evaluation(&board, board.status());
 
             let simple_score;
@@ -138,11 +149,14 @@ fn check_mt_different() -> Result<()> {
             search_mt(
                 &board,
                 &mut transposition_table,
-                vec![],
+                &mut PositionHistory::new(&[], &board),
                 simple_score,
                 EvalBound::Exact(BoardEvaluation::PieceScore(Centipawns::new(0))),
                 0,
                 MAX_DEPTH,
+                ExtensionState::default(),
+                true,
+                &SearchControl::new(),
             )
         };
         println!("mt time ms: {}", time.elapsed().as_millis());
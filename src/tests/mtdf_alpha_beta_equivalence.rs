@@ -7,9 +7,11 @@ use crate::core::score::{BoardEvaluation, Centipawns};
 use crate::core::search::iterative_deepening::iterative_deepening_search;
 use crate::core::search::mtdf::mtdf_iterative_deepening_search;
 use crate::core::search::search_result::debug_search_result::DebugSearchResult;
+use crate::core::search::search_control::SearchControl;
 use crate::core::search::SearchDepth;
 use crate::core::search::transpositions::high_depth_transposition::HighDepthTranspositionTable;
 use crate::core::search::transpositions::no_transposition::NoTranspositionTable;
+use crate::core::search::transpositions::TranspositionTable;
 use crate::input::protocol_interpreter::CalculateOptions;
 use crate::tests::{epd, win_at_chess};
 use crate::tests::epd::EPDParseError;
@@ -39,14 +41,16 @@ fn check_mtdf_alpha_beta_equivalence() -> Result<()> {
         let board = Board::from_str(&record.fen).map_err(|_| EPDParseError::InvalidFEN).unwrap();
         let time = Instant::now();
         let (result, _, _): (DebugSearchResult, u32, u32) = {
-            let mut transposition_table = HighDepthTranspositionTable::new(SearchDepth::Depth(2));
+            let mut transposition_table: Box<dyn TranspositionTable> = Box::new(HighDepthTranspositionTable::new(SearchDepth::Depth(2)));
             // let mut transposition_table = NoTranspositionTable::default();
 
             iterative_deepening_search(
                 &board,
                 &mut transposition_table,
                 vec![],
-                CalculateOptions::Depth(MAX_DEPTH),
+                CalculateOptions::depth(MAX_DEPTH),
+                |_, _| {},
+                &SearchControl::new(),
             )
         };
         total_alpha_beta_time += time.elapsed().as_millis();
@@ -58,7 +62,7 @@ fn check_mtdf_alpha_beta_equivalence() -> Result<()> {
 
         let time = Instant::now();
         let (mtdf_result, _, _): (DebugSearchResult, u32, u32) = {
-            let mut transposition_table = HighDepthTranspositionTable::new(SearchDepth::Depth(2));
+            let mut transposition_table: Box<dyn TranspositionTable> = Box::new(HighDepthTranspositionTable::new(SearchDepth::Depth(2)));
             // let mut transposition_table = NoTranspositionTable::default();
 
              mtdf_iterative_deepening_search(
@@ -66,7 +70,9 @@ fn check_mtdf_alpha_beta_equivalence() -> Result<()> {
                 &mut transposition_table,
                 vec![],
                 // BoardEvaluation::PieceScore(Centipawns::new(0)),
-                CalculateOptions::Depth(MAX_DEPTH),
+                CalculateOptions::depth(MAX_DEPTH),
+                |_, _| {},
+                &SearchControl::new(),
             )
         };
         total_mtdf_time += time.elapsed().as_millis();
@@ -0,0 +1,119 @@
+use std::str::FromStr;
+use std::time::Duration;
+use chess::{Board, ChessMove, Color};
+use crate::core::score::{BoardEvaluation, Centipawns};
+use crate::core::search::alpha_beta::search_depth_pruned;
+use crate::core::search::mtd::mtd_search;
+use crate::core::search::mtdbi::determine_mtdbi_step;
+use crate::core::search::multi_pv::search_multi_pv;
+use crate::core::search::search_control::SearchControl;
+use crate::core::search::search_result::debug_search_result::DebugSearchResult;
+use crate::core::search::search_result::SearchResult;
+use crate::core::search::SearchDepth;
+use crate::core::search::transpositions::high_depth_transposition::HighDepthTranspositionTable;
+use crate::core::search::transpositions::TranspositionTable;
+use crate::input::protocol_interpreter::CalculateOptions;
+
+/// Tests whether every MultiPV line gets the same value as a search of its root move alone
+
+const DEPTH: u32 = 3;
+const MULTI_PV: usize = 3;
+// Black to move, after 1. e4 e5 2. Nf3 Nc6 3. d4
+const FEN: &str = "r1bqkbnr/pppp1ppp/2n5/4p3/3PP3/5N2/PPP2PPP/RNBQKB1R b KQkq - 0 3";
+
+#[test]
+fn check_alpha_beta_multi_pv() {
+    let board = Board::from_str(FEN).unwrap();
+    let control = SearchControl::new().with_multi_pv(MULTI_PV);
+
+    let mut transposition_table = new_transposition_table();
+    let (best_line, _): (DebugSearchResult, _) = search_depth_pruned(&board, &mut transposition_table, vec![], DEPTH, None, &control);
+    let lines = search_multi_pv(&board, &control, best_line, |line_control| {
+        search_depth_pruned(&board, &mut transposition_table, vec![], DEPTH, None, line_control).0
+    });
+
+    check_lines(&board, &lines, |chess_move| {
+        let (result, _): (DebugSearchResult, _) = search_depth_pruned(
+            &board,
+            &mut new_transposition_table(),
+            vec![],
+            DEPTH,
+            None,
+            &only_move_control(&board, chess_move),
+        );
+        result.eval_bound().board_evaluation()
+    });
+}
+
+#[test]
+fn check_mtd_multi_pv() {
+    let board = Board::from_str(FEN).unwrap();
+    let control = SearchControl::new().with_multi_pv(MULTI_PV);
+    let first_guess = BoardEvaluation::PieceScore(Centipawns::new(0));
+
+    let mut transposition_table = new_transposition_table();
+    let (best_line, _, _): (DebugSearchResult, _, _) = mtd_search(&board, &mut transposition_table, vec![], DEPTH, first_guess, determine_mtdbi_step, &control);
+    let lines = search_multi_pv(&board, &control, best_line, |line_control| {
+        mtd_search(&board, &mut transposition_table, vec![], DEPTH, first_guess, determine_mtdbi_step, line_control).0
+    });
+
+    check_lines(&board, &lines, |chess_move| {
+        let (result, _, _): (DebugSearchResult, _, _) = mtd_search(
+            &board,
+            &mut new_transposition_table(),
+            vec![],
+            DEPTH,
+            first_guess,
+            determine_mtdbi_step,
+            &only_move_control(&board, chess_move),
+        );
+        result.eval_bound().board_evaluation()
+    });
+}
+
+#[test]
+fn check_multi_pv_runs_out_of_moves() {
+    // In check from the rook: only Ka2 and Kb2
+    let board = Board::from_str("k7/8/8/8/8/8/8/K6r w - - 0 1").unwrap();
+    let control = SearchControl::new().with_multi_pv(MULTI_PV);
+
+    let mut transposition_table = new_transposition_table();
+    let (best_line, _): (DebugSearchResult, _) = search_depth_pruned(&board, &mut transposition_table, vec![], DEPTH, None, &control);
+    let lines = search_multi_pv(&board, &control, best_line, |line_control| {
+        search_depth_pruned(&board, &mut transposition_table, vec![], DEPTH, None, line_control).0
+    });
+
+    assert_eq!(lines.len(), 2);
+}
+
+/// Checks that the lines have distinct moves, are ordered from best to worst, and hold the exact value of their move
+fn check_lines<F>(board: &Board, lines: &[DebugSearchResult], mut exact_value: F) where
+    F: FnMut(ChessMove) -> BoardEvaluation {
+    assert_eq!(lines.len(), MULTI_PV);
+
+    for (index, line) in lines.iter().enumerate() {
+        assert!(lines[..index].iter().all(|x| x.best_move != line.best_move));
+        assert_eq!(line.eval_bound().board_evaluation(), exact_value(line.best_move));
+    }
+
+    for pair in lines.windows(2) {
+        let (better, worse) = (pair[0].eval_bound().board_evaluation(), pair[1].eval_bound().board_evaluation());
+        match board.side_to_move() {
+            Color::White => assert!(better >= worse),
+            Color::Black => assert!(better <= worse),
+        }
+    }
+}
+
+fn only_move_control(board: &Board, chess_move: ChessMove) -> SearchControl {
+    let options = CalculateOptions {
+        search_moves: vec![chess_move],
+        ..Default::default()
+    };
+
+    SearchControl::new().with_limits(&options, board, Duration::ZERO)
+}
+
+fn new_transposition_table() -> Box<dyn TranspositionTable> {
+    Box::new(HighDepthTranspositionTable::new(SearchDepth::Depth(2)))
+}
@@ -0,0 +1,111 @@
+use std::str::FromStr;
+use chess::Board;
+use crate::analysis::match_orchestration::SearchAlgorithm;
+use crate::analysis::mtd_h_utils::MtdHParams;
+use crate::core::score::{BoardEvaluation, Centipawns};
+use crate::tests::{engine_without_table, search, small_engine, MATE_IN_ONE, POSITIONS};
+
+/// Tests for parallel MTD, which probes several test values at once
+
+const DEPTH: u32 = 4;
+const PROBES: [usize; 3] = [1, 2, 4];
+
+#[test]
+fn check_parallel_mtd_matches_alpha_beta() {
+    for fen in POSITIONS {
+        let board = Board::from_str(fen).unwrap();
+        let alpha_beta = search(&board, engine_without_table().algorithm(SearchAlgorithm::AlphaBetaIterativeDeepening), DEPTH);
+
+        for probes in PROBES {
+            let outcome = search(&board, engine_without_table().algorithm(SearchAlgorithm::ParallelMTDIterativeDeepening).mtd_probes(probes), DEPTH);
+            assert_eq!(
+                outcome.search_result.board_evaluation.board_evaluation(),
+                alpha_beta.search_result.board_evaluation.board_evaluation(),
+                "{probes} probes {fen}",
+            );
+            assert!(board.legal(outcome.best_move), "{probes} probes {fen}");
+            assert!(outcome.conspiracy_counter.is_some(), "{probes} probes {fen}");
+        }
+    }
+}
+
+#[test]
+fn check_parallel_mtd_with_distribution_matches_alpha_beta() {
+    // Quantiles of the distribution of the iteration two plies up, instead of bisection points
+    let params = (3..=DEPTH)
+        .map(|target_depth| MtdHParams {
+            training_depth: target_depth - 2,
+            target_depth,
+            p: 0.5,
+            w_side_down: 1.0,
+            w_side_up: 1.0,
+            c: 0.01,
+        })
+        .collect::<Vec<_>>();
+
+    for fen in POSITIONS {
+        let board = Board::from_str(fen).unwrap();
+        let alpha_beta = search(&board, engine_without_table().algorithm(SearchAlgorithm::AlphaBetaIterativeDeepening), DEPTH);
+        let outcome = search(&board, engine_without_table().algorithm(SearchAlgorithm::ParallelMTDIterativeDeepening).mtd_h_params(params.clone()).mtd_probes(3), DEPTH);
+
+        assert_eq!(
+            outcome.search_result.board_evaluation.board_evaluation(),
+            alpha_beta.search_result.board_evaluation.board_evaluation(),
+            "{fen}",
+        );
+    }
+}
+
+#[test]
+fn check_parallel_mtd_logs_probes() {
+    let board = Board::from_str(POSITIONS[0]).unwrap();
+
+    for probes in PROBES {
+        let engine = engine_without_table()
+            .algorithm(SearchAlgorithm::ParallelMTDIterativeDeepening)
+            .mtd_probes(probes)
+            .record_search_rows();
+
+        let outcome = search(&board, engine, DEPTH);
+        assert_eq!(outcome.search_rows.len(), DEPTH as usize);
+
+        for (position_row, mt_rows) in &outcome.search_rows {
+            assert!(!mt_rows.is_empty(), "{probes} probes");
+            assert_eq!(mt_rows.iter().map(|x| x.nodes_evaluated).sum::<u32>(), position_row.nodes_evaluated, "{probes} probes");
+            assert!(position_row.conspiracy_counter.is_some(), "{probes} probes");
+
+            for (search_num, mt_row) in mt_rows.iter().enumerate() {
+                assert_eq!(mt_row.search_num, search_num as u32);
+                assert!(mt_row.conspiracy_counter.is_some());
+                assert!(mt_row.window.is_none());
+            }
+        }
+
+        // The first round starts at 0 and spreads the other probes over the bounds
+        let first_round = &outcome.search_rows[0].1[..probes];
+        assert_eq!(first_round[0].test_value, BoardEvaluation::PieceScore(Centipawns::new(0)));
+        for (index, mt_row) in first_round.iter().enumerate() {
+            assert!(!first_round[..index].iter().any(|x| x.test_value == mt_row.test_value), "{probes} probes: {} twice", mt_row.test_value);
+        }
+    }
+}
+
+#[test]
+fn check_parallel_mtd_with_transposition_table() {
+    for fen in POSITIONS {
+        let board = Board::from_str(fen).unwrap();
+
+        for probes in PROBES {
+            let engine = small_engine()
+                .algorithm(SearchAlgorithm::ParallelMTDIterativeDeepening)
+                .mtd_probes(probes);
+
+            let outcome = search(&board, engine, DEPTH + 1);
+            assert!(board.legal(outcome.best_move), "{probes} probes {fen}");
+        }
+    }
+
+    let board = Board::from_str(MATE_IN_ONE).unwrap();
+    let outcome = search(&board, engine_without_table().algorithm(SearchAlgorithm::ParallelMTDIterativeDeepening).mtd_probes(4), DEPTH);
+    assert_eq!(outcome.best_move.to_string(), "f3f7");
+}
@@ -0,0 +1,96 @@
+use std::str::FromStr;
+use chess::Board;
+use crate::core::castling::Chess960Castling;
+use crate::core::perft::{chess960_perft, perft, perft_divide};
+use crate::input::fen::parse_fen;
+use crate::input::uci_interpreter::UciInterpreter;
+
+/// Known perft counts, see https://www.chessprogramming.org/Perft_Results
+
+const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+// En passant captures that would expose the king, and a rook pinned along the rank
+const EN_PASSANT_PINS: &str = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+const PROMOTIONS: &str = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+// Promotions with and without capture, for both sides
+const UNDERPROMOTIONS: &str = "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1";
+const CHECKS_AND_CASTLING: &str = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+// Chess960 positions, with the king and rooks away from their standard squares
+const CHESS960: [(&str, [u64; 4]); 3] = [
+    ("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9", [21, 528, 12189, 326672]),
+    ("2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9", [21, 807, 18002, 667366]),
+    ("b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9", [20, 479, 10471, 273318]),
+];
+
+#[test]
+fn check_start_position() {
+    check_counts(&Board::default(), &[20, 400, 8902, 197281]);
+}
+
+#[test]
+fn check_kiwipete() {
+    check_counts(&Board::from_str(KIWIPETE).unwrap(), &[48, 2039, 97862]);
+}
+
+#[test]
+fn check_en_passant_pins() {
+    check_counts(&Board::from_str(EN_PASSANT_PINS).unwrap(), &[14, 191, 2812, 43238]);
+}
+
+#[test]
+fn check_promotions() {
+    check_counts(&Board::from_str(PROMOTIONS).unwrap(), &[6, 264, 9467]);
+    check_counts(&Board::from_str(UNDERPROMOTIONS).unwrap(), &[24, 496, 9483]);
+}
+
+#[test]
+fn check_checks_and_castling() {
+    check_counts(&Board::from_str(CHECKS_AND_CASTLING).unwrap(), &[44, 1486, 62379]);
+}
+
+#[test]
+fn check_chess960() {
+    for (fen, counts) in CHESS960 {
+        let (board, castling) = parse_fen(fen).unwrap();
+        for (depth, count) in (1..).zip(counts) {
+            assert_eq!(chess960_perft(&board, castling, depth), count, "{fen} depth {depth}");
+        }
+    }
+}
+
+#[test]
+fn check_divide() {
+    let board = Board::from_str(KIWIPETE).unwrap();
+    let divide = perft_divide(&board, Chess960Castling::default(), 2);
+
+    assert_eq!(divide.len(), 48);
+    assert_eq!(divide.iter().map(|(_, nodes)| nodes).sum::<u64>(), 2039);
+    assert!(perft_divide(&board, Chess960Castling::default(), 0).is_empty());
+}
+
+#[test]
+fn check_positions_from_moves() {
+    // The board reached by `position ... moves` has to carry the en passant square and the castling rights
+    let cases = [
+        // Double step next to a pawn: e5xd6 en passant is possible
+        ("startpos moves e2e4 a7a6 e4e5 d7d5", "rnbqkbnr/1pp1pppp/p7/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3"),
+        // The king and the h1 rook moved back and forth, so white can't castle anymore
+        ("startpos moves g1f3 g8f6 e2e3 e7e6 f1e2 f8e7 e1f1 e8f8 f1e1 f8e8", "rnbqk2r/ppppbppp/4pn2/8/8/4PN2/PPPPBPPP/RNBQK2R w - - 10 6"),
+        (
+            &format!("fen {KIWIPETE} moves e1g1 b4c3"),
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/4P3/2p2Q1p/PPPBBPPP/R4RK1 w kq - 0 2",
+        ),
+    ];
+
+    for (position, fen) in cases {
+        let board = UciInterpreter::determine_board(position.split_whitespace()).unwrap();
+        let expected_board = Board::from_str(fen).unwrap();
+
+        assert_eq!(board, expected_board, "{position}");
+    }
+}
+
+fn check_counts(board: &Board, counts: &[u64]) {
+    for (depth, count) in (1..).zip(counts) {
+        assert_eq!(perft(board, depth), *count, "depth {depth}");
+    }
+}
@@ -0,0 +1,84 @@
+use std::str::FromStr;
+use chess::Board;
+use crate::analysis::match_orchestration::SearchAlgorithm;
+use crate::core::score::BoardEvaluation;
+use crate::core::search::pruning::{LateMoveReductions, NullMovePruning, PruningOptions};
+use crate::tests::{engine_without_table, search, MIDDLE_GAME};
+
+/// Tests for the forward pruning of the searches
+
+const DEPTH: u32 = 5;
+const NULL_MOVE: PruningOptions = PruningOptions {
+    null_move: Some(NullMovePruning { reduction: 2 }),
+    late_move_reductions: None,
+};
+
+fn late_move_reductions() -> PruningOptions {
+    PruningOptions {
+        null_move: None,
+        late_move_reductions: Some(LateMoveReductions::default()),
+    }
+}
+
+#[test]
+fn check_null_move_prunes() {
+    let board = Board::from_str(MIDDLE_GAME).unwrap();
+
+    for algorithm in [SearchAlgorithm::AlphaBetaIterativeDeepening, SearchAlgorithm::MTDFIterativeDeepening] {
+        let full = search(&board, engine_without_table().algorithm(algorithm).pruning(PruningOptions::default()), DEPTH);
+        let pruned = search(&board, engine_without_table().algorithm(algorithm).pruning(NULL_MOVE), DEPTH);
+
+        assert!(board.legal(pruned.best_move), "{algorithm:?}");
+        assert!(
+            pruned.search_result.nodes_searched < full.search_result.nodes_searched,
+            "{algorithm:?}: {} nodes with null-move pruning, {} without",
+            pruned.search_result.nodes_searched,
+            full.search_result.nodes_searched,
+        );
+    }
+}
+
+#[test]
+fn check_no_null_move_in_pawn_endings() {
+    // Zugzwang is common here, so the material guard leaves the search exact
+    let board = Board::from_str("8/5k2/3p4/1p1Pp2p/pP2Pp1P/P4P1K/8/8 b - - 0 1").unwrap();
+
+    let full = search(&board, engine_without_table().algorithm(SearchAlgorithm::AlphaBetaIterativeDeepening).pruning(PruningOptions::default()), DEPTH);
+    let pruned = search(&board, engine_without_table().algorithm(SearchAlgorithm::AlphaBetaIterativeDeepening).pruning(NULL_MOVE), DEPTH);
+
+    assert_eq!(pruned.search_result.board_evaluation, full.search_result.board_evaluation);
+    assert_eq!(pruned.search_result.nodes_searched, full.search_result.nodes_searched);
+}
+
+#[test]
+fn check_late_move_reductions_prune() {
+    let board = Board::from_str(MIDDLE_GAME).unwrap();
+
+    for algorithm in [SearchAlgorithm::AlphaBetaIterativeDeepening, SearchAlgorithm::MTDFIterativeDeepening] {
+        let full = search(&board, engine_without_table().algorithm(algorithm).pruning(PruningOptions::default()), DEPTH);
+        let reduced = search(&board, engine_without_table().algorithm(algorithm).pruning(late_move_reductions()), DEPTH);
+
+        assert!(board.legal(reduced.best_move), "{algorithm:?}");
+        assert!(
+            reduced.search_result.nodes_searched < full.search_result.nodes_searched,
+            "{algorithm:?}: {} nodes with late move reductions, {} without",
+            reduced.search_result.nodes_searched,
+            full.search_result.nodes_searched,
+        );
+    }
+}
+
+#[test]
+fn check_late_move_reductions_keep_mates() {
+    // 1. Kc7 and 2. Ra1#: black's pawn moves get reduced, but the mating check never is
+    let board = Board::from_str("k7/6pp/2K5/8/8/8/8/1R6 w - - 0 1").unwrap();
+
+    for algorithm in [SearchAlgorithm::AlphaBetaIterativeDeepening, SearchAlgorithm::MTDFIterativeDeepening] {
+        let outcome = search(&board, engine_without_table().algorithm(algorithm).pruning(late_move_reductions()), DEPTH);
+        assert!(
+            matches!(outcome.search_result.board_evaluation.board_evaluation(), BoardEvaluation::WhiteMate(_)),
+            "{algorithm:?}: {:?}",
+            outcome.search_result.board_evaluation,
+        );
+    }
+}
@@ -0,0 +1,81 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Instant;
+use anyhow::{bail, Result};
+use chess::Board;
+use crate::core::search::iterative_deepening::iterative_deepening_search;
+use crate::core::search::pvs::pvs_iterative_deepening_search;
+use crate::core::search::search_result::debug_search_result::DebugSearchResult;
+use crate::core::search::search_control::SearchControl;
+use crate::core::search::transpositions::no_transposition::NoTranspositionTable;
+use crate::core::search::transpositions::TranspositionTable;
+use crate::input::protocol_interpreter::CalculateOptions;
+use crate::tests::{epd, win_at_chess};
+use crate::tests::epd::EPDParseError;
+use crate::tests::mt_alpha_beta_equivalence::log_dissimilar_answers;
+
+/// Tests whether PVS returns the same solutions as Alpha-Beta pruning.
+/// Without a transposition table, so that neither search can pick up a bound of the other window.
+const MAX_DEPTH: u32 = 4;
+
+#[test]
+fn check_pvs_alpha_beta_equivalence() -> Result<()> {
+    let epd_path = PathBuf::from(win_at_chess::EPD_PATH);
+    let records = epd::read_epd(epd_path.as_path()).expect("failed to read epd");
+
+    let mut failed_positions = vec![];
+    let mut total_alpha_beta_nodes_searched = 0;
+    let mut total_pvs_nodes_searched = 0;
+    for record in records.into_iter().step_by(10) {
+        let board = Board::from_str(&record.fen).map_err(|_| EPDParseError::InvalidFEN).unwrap();
+
+        let time = Instant::now();
+        let (result, _, _): (DebugSearchResult, u32, u32) = {
+            let mut transposition_table: Box<dyn TranspositionTable> = Box::new(NoTranspositionTable);
+
+            iterative_deepening_search(
+                &board,
+                &mut transposition_table,
+                vec![],
+                CalculateOptions::depth(MAX_DEPTH),
+                |_, _| {},
+                &SearchControl::new(),
+            )
+        };
+        total_alpha_beta_nodes_searched += result.nodes_searched;
+        println!("alpha beta time ms: {}", time.elapsed().as_millis());
+
+        let time = Instant::now();
+        let (pvs_result, _, _): (DebugSearchResult, u32, u32) = {
+            let mut transposition_table: Box<dyn TranspositionTable> = Box::new(NoTranspositionTable);
+
+            pvs_iterative_deepening_search(
+                &board,
+                &mut transposition_table,
+                vec![],
+                CalculateOptions::depth(MAX_DEPTH),
+                |_, _| {},
+                &SearchControl::new(),
+            )
+        };
+        total_pvs_nodes_searched += pvs_result.nodes_searched;
+        println!("pvs time ms: {}", time.elapsed().as_millis());
+
+        if result.board_evaluation.board_evaluation() != pvs_result.board_evaluation.board_evaluation() {
+            failed_positions.push((record.id.clone(), result, pvs_result));
+        }
+    }
+
+    println!("total_alpha_beta_nodes_searched {}", total_alpha_beta_nodes_searched);
+    println!("total_pvs_nodes_searched {}", total_pvs_nodes_searched);
+
+    let some_failed_positions = failed_positions.len() > 0;
+    for failed_position in failed_positions {
+        log_dissimilar_answers(&failed_position.0.unwrap_or("unknown ID".to_string()), &failed_position.1, &failed_position.2);
+    }
+
+    if some_failed_positions {
+        bail!("Failed some positions");
+    }
+    Ok(())
+}
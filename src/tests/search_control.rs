@@ -0,0 +1,262 @@
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use chess::{Board, ChessMove, MoveGen, Square};
+use crate::core::search::conspiracy_search::merging::merge_remove_overwritten;
+use crate::core::search::conspiracy_search::mtd_w_conspiracy::mtd_iterative_deepening_search;
+use crate::core::search::iterative_deepening::iterative_deepening_search;
+use crate::core::search::mtdbi::determine_mtdbi_step;
+use crate::core::search::search_control::SearchControl;
+use crate::core::search::search_result::debug_search_result::DebugSearchResult;
+use crate::core::search::SearchDepth;
+use crate::core::search::time_management::SimulatedClock;
+use crate::core::search::transpositions::high_depth_transposition::HighDepthTranspositionTable;
+use crate::core::search::transpositions::TranspositionTable;
+use crate::input::protocol_interpreter::CalculateOptions;
+
+/// Tests whether an infinite search returns promptly once it is told to stop
+const STOP_AFTER: Duration = Duration::from_millis(200);
+const MAX_STOP_DELAY: Duration = Duration::from_millis(500);
+const MOVE_TIME: u64 = 100;
+const NODE_LIMIT: u64 = 5000;
+
+#[test]
+fn check_alpha_beta_stops() {
+    let board = Board::default();
+    let control = SearchControl::new();
+    stop_later(&control);
+
+    let time = Instant::now();
+    let mut transposition_table: Box<dyn TranspositionTable> = Box::new(HighDepthTranspositionTable::new(SearchDepth::Depth(2)));
+    let (result, _, _): (DebugSearchResult, _, _) = iterative_deepening_search(
+        &board,
+        &mut transposition_table,
+        vec![],
+        CalculateOptions::infinite(),
+        |_, _| {},
+        &control,
+    );
+
+    assert!(time.elapsed() < STOP_AFTER + MAX_STOP_DELAY);
+    assert!(MoveGen::new_legal(&board).any(|x| x == result.best_move));
+}
+
+#[test]
+fn check_mtd_conspiracy_stops() {
+    let board = Board::default();
+    let control = SearchControl::new();
+    stop_later(&control);
+
+    let time = Instant::now();
+    let mut transposition_table: Box<dyn TranspositionTable> = Box::new(HighDepthTranspositionTable::new(SearchDepth::Depth(2)));
+    let (result, _, _, _): (DebugSearchResult, _, _, _) = mtd_iterative_deepening_search(
+        &board,
+        &mut transposition_table,
+        vec![],
+        CalculateOptions::infinite(),
+        determine_mtdbi_step,
+        20,
+        101,
+        merge_remove_overwritten,
+        |_, _| {},
+        &control,
+    );
+
+    assert!(time.elapsed() < STOP_AFTER + MAX_STOP_DELAY);
+    assert!(MoveGen::new_legal(&board).any(|x| x == result.best_move));
+}
+
+#[test]
+fn check_move_time_deadline() {
+    let board = Board::default();
+    let options = CalculateOptions::move_time(MOVE_TIME);
+
+    let time = Instant::now();
+    let control = SearchControl::new().with_limits(&options, &board, Duration::ZERO);
+    let mut transposition_table: Box<dyn TranspositionTable> = Box::new(HighDepthTranspositionTable::new(SearchDepth::Depth(2)));
+    let (result, _, _, _): (DebugSearchResult, _, _, _) = mtd_iterative_deepening_search(
+        &board,
+        &mut transposition_table,
+        vec![],
+        options,
+        determine_mtdbi_step,
+        20,
+        101,
+        merge_remove_overwritten,
+        |_, _| {},
+        &control,
+    );
+
+    assert!(time.elapsed() < Duration::from_millis(MOVE_TIME) + MAX_STOP_DELAY);
+    assert!(MoveGen::new_legal(&board).any(|x| x == result.best_move));
+}
+
+#[test]
+fn check_ponder_hit() {
+    let board = Board::default();
+    let options = CalculateOptions {
+        move_time: Some(MOVE_TIME),
+        ponder: true,
+        ..Default::default()
+    };
+
+    let time = Instant::now();
+    let control = SearchControl::new();
+    control.set_pondering(true);
+    let control = control.with_limits(&options, &board, Duration::ZERO);
+
+    // The move time only starts counting at the ponderhit
+    let ponder_control = control.clone();
+    thread::spawn(move || {
+        thread::sleep(STOP_AFTER);
+        ponder_control.ponder_hit();
+    });
+
+    let mut transposition_table: Box<dyn TranspositionTable> = Box::new(HighDepthTranspositionTable::new(SearchDepth::Depth(2)));
+    let (result, _, _, _): (DebugSearchResult, _, _, _) = mtd_iterative_deepening_search(
+        &board,
+        &mut transposition_table,
+        vec![],
+        options,
+        determine_mtdbi_step,
+        20,
+        101,
+        merge_remove_overwritten,
+        |_, _| {},
+        &control,
+    );
+
+    assert!(time.elapsed() >= STOP_AFTER + Duration::from_millis(MOVE_TIME));
+    assert!(time.elapsed() < STOP_AFTER + Duration::from_millis(MOVE_TIME) + MAX_STOP_DELAY);
+    assert!(MoveGen::new_legal(&board).any(|x| x == result.best_move));
+}
+
+#[test]
+fn check_node_limit() {
+    let board = Board::default();
+    let options = CalculateOptions {
+        nodes: Some(NODE_LIMIT),
+        ..Default::default()
+    };
+
+    let control = SearchControl::new().with_limits(&options, &board, Duration::ZERO);
+    let mut transposition_table: Box<dyn TranspositionTable> = Box::new(HighDepthTranspositionTable::new(SearchDepth::Depth(2)));
+    let (result, _, _): (DebugSearchResult, _, _) = iterative_deepening_search(
+        &board,
+        &mut transposition_table,
+        vec![],
+        options,
+        |_, _| {},
+        &control,
+    );
+
+    // The node that hits the limit still gets counted
+    assert_eq!(control.nodes_searched(), NODE_LIMIT);
+    assert!(MoveGen::new_legal(&board).any(|x| x == result.best_move));
+}
+
+#[test]
+fn check_search_moves() {
+    let board = Board::default();
+    let only_move = ChessMove::new(Square::A2, Square::A3, None);
+    let options = CalculateOptions {
+        depth: Some(3),
+        search_moves: vec![only_move],
+        ..Default::default()
+    };
+
+    let control = SearchControl::new().with_limits(&options, &board, Duration::ZERO);
+    let mut transposition_table: Box<dyn TranspositionTable> = Box::new(HighDepthTranspositionTable::new(SearchDepth::Depth(2)));
+    let (result, _, _, _): (DebugSearchResult, _, _, _) = mtd_iterative_deepening_search(
+        &board,
+        &mut transposition_table,
+        vec![],
+        options,
+        determine_mtdbi_step,
+        20,
+        101,
+        merge_remove_overwritten,
+        |_, _| {},
+        &control,
+    );
+
+    assert_eq!(result.best_move, only_move);
+}
+
+#[test]
+fn check_simulated_clock_limits() {
+    let board = Board::default();
+    let options = CalculateOptions {
+        white_time: Some(60_000),
+        ponder: true,
+        ..Default::default()
+    };
+    let clock = Arc::new(SimulatedClock::new());
+    let control = SearchControl::with_clock(clock.clone());
+    control.set_pondering(true);
+    let control = control.with_limits(&options, &board, Duration::ZERO);
+
+    // The opponent's time doesn't count
+    clock.advance(Duration::from_secs(60));
+    assert!(control.has_time_for_iteration());
+    assert!(!control.is_stopped());
+
+    control.ponder_hit();
+    control.update_best_move(ChessMove::new(Square::E2, Square::E4, None));
+    assert!(control.has_time_for_iteration());
+    assert!(!control.is_stopped());
+
+    // Past the soft limit, but still under the hard limit
+    clock.advance(Duration::from_secs(2));
+    assert!(!control.has_time_for_iteration());
+    assert!(!control.is_stopped());
+
+    clock.advance(Duration::from_secs(10));
+    assert!(control.is_stopped());
+}
+
+#[test]
+fn check_ordering_per_search() {
+    let board = Board::default();
+    let control = SearchControl::new().with_limits(&CalculateOptions::depth(4), &board, Duration::ZERO);
+    let mut transposition_table: Box<dyn TranspositionTable> = Box::new(HighDepthTranspositionTable::new(SearchDepth::Depth(2)));
+    let _: (DebugSearchResult, _, _, _) = mtd_iterative_deepening_search(
+        &board,
+        &mut transposition_table,
+        vec![],
+        CalculateOptions::depth(4),
+        determine_mtdbi_step,
+        20,
+        101,
+        merge_remove_overwritten,
+        |_, _| {},
+        &control,
+    );
+
+    // Learned over the iterations and probes, and shared by the clones of the control
+    let killers = control.with_ordering(|ordering| ordering.killers(1));
+    assert!(killers[0].is_some());
+    assert_eq!(control.excluding_root_moves(&board, &[]).unwrap().with_ordering(|ordering| ordering.killers(1)), killers);
+
+    // Carried over to another thread
+    let ordering = control.take_ordering();
+    thread::scope(|scope| {
+        scope.spawn(|| {
+            assert_eq!(control.with_ordering(|ordering| ordering.killers(1)), [None, None]);
+            control.resume_ordering(ordering);
+            assert_eq!(control.with_ordering(|ordering| ordering.killers(1)), killers);
+        });
+    });
+
+    // The next search starts over
+    let next_control = control.with_limits(&CalculateOptions::depth(4), &board, Duration::ZERO);
+    assert_eq!(next_control.with_ordering(|ordering| ordering.killers(1)), [None, None]);
+}
+
+fn stop_later(control: &SearchControl) {
+    let control = control.clone();
+    thread::spawn(move || {
+        thread::sleep(STOP_AFTER);
+        control.stop();
+    });
+}
@@ -9,8 +9,10 @@ use crate::core::search::alpha_beta::search_depth_pruned;
 use crate::core::search::iterative_deepening::iterative_deepening_search;
 use crate::core::search::search_result;
 use crate::core::search::search_result::debug_search_result::DebugSearchResult;
+use crate::core::search::search_control::SearchControl;
 use crate::core::search::search_result::SearchResult;
 use crate::core::search::transpositions::no_transposition::NoTranspositionTable;
+use crate::core::search::transpositions::TranspositionTable;
 use crate::input::protocol_interpreter::CalculateOptions;
 use crate::tests::{check_position, log_failed_positions, TestError};
 
@@ -28,12 +30,14 @@ fn check_positions() -> Result<()> {
     let mut failed_positions = vec![];
     for record in records.into_iter() {
         let result = check_position(&record, |board| {
-            let mut transposition_table = NoTranspositionTable::default();
+            let mut transposition_table: Box<dyn TranspositionTable> = Box::new(NoTranspositionTable::default());
             let (result, _, _): (DebugSearchResult, u32, u32) = iterative_deepening_search(
                 board,
                 &mut transposition_table,
                 vec![],
-                CalculateOptions::Depth(6),
+                CalculateOptions::depth(6),
+                |_, _| {},
+                &SearchControl::new(),
             );
 
             println!("{result:?}");
@@ -52,7 +56,7 @@ fn check_positions() -> Result<()> {
         //     &board,
         //     &mut transposition_table,
         //     vec![],
-        //     CalculateOptions::Depth(8),
+        //     CalculateOptions::depth(8),
         // );
 
         // assert_eq!(search_result.best_move, record.best_move);